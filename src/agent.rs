@@ -1,11 +1,22 @@
 use anyhow::Result;
 use colored::Colorize;
 
+use crate::ask::{Origin, SuspendedRun};
+use crate::checkpoint::{self, CheckpointStore};
 use crate::client::{AnthropicClient, StreamEvent};
 use crate::context::ContextManager;
+use crate::inbox::Inbox;
+use crate::manager::Manager;
 use crate::memory::TaskStorage;
-use crate::tools;
-use crate::types::{ContentBlock, Thinking, ToolDefinition, Usage};
+use crate::process::ProcessTable;
+use crate::sandbox::Sandbox;
+use crate::shell_session::ShellSession;
+use crate::tools::custom::CustomTool;
+use crate::tools::policy::ToolPolicy;
+use crate::telegram::api::ChatTarget;
+use crate::tools::{self, ask_user::AskContext};
+use crate::types::{ContentBlock, Role, Thinking, ToolDefinition, ToolResultContent, Usage};
+use crate::worktree::Worktree;
 
 /// The core agent loop — prompt → tool → result → repeat
 pub struct AgentLoop {
@@ -20,6 +31,34 @@ pub struct AgentLoop {
     brave_api_key: Option<String>,
     github_token: Option<String>,
     task_storage: Option<TaskStorage>,
+    chat: Option<ChatTarget>,
+    /// Where `ask_user` questions go
+    origin: Option<Origin>,
+    /// Background run id — with a persisted context, makes the agent resumable after a restart
+    run_id: Option<String>,
+    /// Save the context every N rounds of tool calls
+    checkpoint_interval: u32,
+    /// Steering from the manager and notes from sibling agents
+    inbox: Option<Inbox>,
+    /// Own git worktree — file, shell and git tools are rooted there
+    worktree: Option<Worktree>,
+    /// Prior contents of files the tools change, for undo
+    checkpoints: Option<CheckpointStore>,
+    /// Background processes from process_start — killed when the last holder drops them
+    processes: ProcessTable,
+    /// Persistent bash for `shell` — None runs each command in a fresh one
+    shell: Option<ShellSession>,
+    /// Confines shell, process and custom tools, for bots shared with other people
+    sandbox: Option<Sandbox>,
+    /// Tools from `[[tools.custom]]`, run as commands
+    custom_tools: Vec<CustomTool>,
+    /// The sub-agent pool, for the manager's spawn/steer/kill tools
+    manager: Option<Manager>,
+    /// Which tools it may use — also what a resumed run gets
+    policy: ToolPolicy,
+    /// Dry-run mode: tools that change things are previewed, not run. Holds
+    /// what they would have done this turn.
+    dry_run: Option<Vec<String>>,
 }
 
 impl AgentLoop {
@@ -47,6 +86,20 @@ impl AgentLoop {
             brave_api_key,
             github_token,
             task_storage: None,
+            chat: None,
+            origin: None,
+            run_id: None,
+            checkpoint_interval: 1,
+            inbox: None,
+            worktree: None,
+            checkpoints: None,
+            processes: ProcessTable::new(),
+            shell: None,
+            sandbox: None,
+            custom_tools: Vec::new(),
+            manager: None,
+            policy: ToolPolicy::default(),
+            dry_run: None,
         }
     }
 
@@ -56,10 +109,269 @@ impl AgentLoop {
         self
     }
 
+    /// Attach the Telegram chat this agent is serving (for send_file)
+    pub fn with_chat(mut self, chat: ChatTarget) -> Self {
+        self.chat = Some(chat);
+        self
+    }
+
+    /// Set the surface that started this agent (Telegram chat, dashboard, CLI)
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Mark this as a background run that can be resumed after a restart
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Checkpoint the context every `n` rounds of tool calls instead of every round
+    pub fn with_checkpoint_interval(mut self, n: u32) -> Self {
+        self.checkpoint_interval = n.max(1);
+        self
+    }
+
+    /// Read an inbox between API calls; enables `message_agent` and `request_escalation`
+    pub fn with_inbox(mut self, inbox: Inbox) -> Self {
+        self.inbox = Some(inbox);
+        self
+    }
+
+    /// Root file, shell and git tools in a worktree
+    pub fn with_worktree(mut self, worktree: Worktree) -> Self {
+        self.worktree = Some(worktree);
+        self
+    }
+
+    /// Snapshot files before write_file, edit_file and apply_patch change them
+    pub fn with_file_checkpoints(mut self, store: CheckpointStore) -> Self {
+        self.checkpoints = Some(store);
+        self
+    }
+
+    /// Share a process table that outlives this loop, like a chat's
+    pub fn with_processes(mut self, processes: ProcessTable) -> Self {
+        self.processes = processes;
+        self
+    }
+
+    /// Run `shell` in `session`, keeping cwd and environment between calls, and offer shell_reset
+    pub fn with_shell_session(mut self, session: ShellSession) -> Self {
+        if let Some(def) = self.tools.iter_mut().find(|t| t.name == "shell") {
+            *def = tools::shell::session_definition();
+            self.tools.push(tools::shell::reset_definition());
+        }
+        self.shell = Some(session);
+        self
+    }
+
+    /// Run shell commands, background processes and custom tools inside
+    /// `sandbox`. Tools that can't be confined, like claude_code, are dropped.
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.tools.retain(|t| !crate::sandbox::UNSANDBOXED_TOOLS.contains(&t.name.as_str()));
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Offer the config's custom tools; a name a built-in already has is skipped
+    pub fn with_custom_tools(mut self, custom: Vec<CustomTool>) -> Self {
+        for tool in custom {
+            if self.tools.iter().any(|t| t.name == tool.name) {
+                tracing::warn!("Custom tool '{}' shadows a built-in; skipped", tool.name);
+                continue;
+            }
+            self.tools.push(tool.definition());
+            self.custom_tools.push(tool);
+        }
+        self
+    }
+
+    /// Offer the manager tools: bot management, and sub-agents run by `manager`'s orchestrator
+    pub fn with_manager(mut self, manager: Manager) -> Self {
+        self.tools.extend(Manager::tool_definitions());
+        self.manager = Some(manager);
+        self
+    }
+
+    /// The tools offered to the model
+    pub fn tool_definitions(&self) -> &[ToolDefinition] {
+        &self.tools
+    }
+
+    /// Offer only the tools `policy` permits
+    pub fn with_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        policy.filter(&mut self.tools);
+        self.policy = policy;
+        self
+    }
+
+    /// Preview every tool that would change something instead of running it,
+    /// and end each answer with a list of what was held back
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.set_dry_run(dry_run);
+        self
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run.then(Vec::new);
+    }
+
+    /// What this turn held back, in dry-run mode
+    pub fn dry_run_actions(&self) -> Option<&[String]> {
+        self.dry_run.as_deref()
+    }
+
+    /// `text` with the dry-run summary after it, in dry-run mode
+    fn with_summary(&self, text: String) -> String {
+        match &self.dry_run {
+            Some(actions) if text.is_empty() => tools::dry_run::summary(actions),
+            Some(actions) => format!("{text}\n\n{}", tools::dry_run::summary(actions)),
+            None => text,
+        }
+    }
+
+    pub fn file_checkpoints(&mut self) -> Option<&mut CheckpointStore> {
+        self.checkpoints.as_mut()
+    }
+
+    /// Continue a run that was suspended in `ask_user`: answer that call,
+    /// mark any other unanswered calls from the same response as not run,
+    /// then carry on the loop
+    pub async fn resume(&mut self, tool_use_id: &str, result: &str) -> Result<TurnResult> {
+        for id in self.pending_tool_calls() {
+            if id == tool_use_id {
+                self.context.add_tool_result(&id, result, false);
+            } else {
+                self.context.add_tool_result(&id, "Not run — the agent was restarted while waiting for the user.", true);
+            }
+        }
+        self.context.save()?;
+        self.run_loop().await
+    }
+
+    /// Pick up a run from its last checkpoint after the process died mid-turn.
+    /// Tool calls it was in the middle of are reported as not run.
+    pub async fn continue_run(&mut self) -> Result<TurnResult> {
+        let pending = self.pending_tool_calls();
+        if pending.is_empty()
+            && let Some(last) = self.context.messages.last()
+            && last.role == Role::Assistant
+        {
+            // It had already answered; only the bookkeeping was lost
+            let text = last
+                .content
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("");
+            return Ok(TurnResult { text, usage: Usage::default(), hit_turn_limit: false, escalation: None });
+        }
+        for id in pending {
+            self.context.add_tool_result(&id, "Not run — the agent was restarted before it finished.", true);
+        }
+        self.context.save()?;
+        self.run_loop().await
+    }
+
+    /// Tool calls in the latest assistant message that have no result yet
+    fn pending_tool_calls(&self) -> Vec<String> {
+        let answered: std::collections::HashSet<String> = self
+            .context
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|b| match b {
+                ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.clone()),
+                _ => None,
+            })
+            .collect();
+        self.context
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.role == Role::Assistant)
+            .map(|m| {
+                m.content
+                    .iter()
+                    .filter_map(|b| match b {
+                        ContentBlock::ToolUse { id, .. } if !answered.contains(id) => Some(id.clone()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Checkpoint for `ask_user`, if this run can be picked up after a restart
+    fn suspended_run(&self, tool_use_id: &str) -> Option<SuspendedRun> {
+        Some(SuspendedRun {
+            run_id: self.run_id.clone()?,
+            context_path: self.context.persist_path()?.clone(),
+            tool_use_id: tool_use_id.to_string(),
+            model: self.model.clone(),
+            system_prompt: self.system_prompt.clone(),
+            thinking: self.thinking,
+            storage: self.task_storage.clone(),
+            tools: self.policy.clone(),
+            dry_run: self.dry_run.is_some(),
+            worktree: self.worktree.clone(),
+        })
+    }
+
+    /// Put messages from the manager and other agents into the context,
+    /// alongside the latest tool results if there are any
+    fn deliver_mail(&mut self) {
+        let Some(inbox) = &self.inbox else { return };
+        let mail = inbox.drain();
+        if mail.is_empty() {
+            return;
+        }
+        eprintln!("{}", format!("📨 {} new message(s)", mail.len()).dimmed());
+        let text = mail
+            .iter()
+            .map(|m| format!("[Message from {}]\n{}", m.from, m.text))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        match self.context.messages.last_mut() {
+            Some(last) if last.role == Role::User => last.content.push(ContentBlock::Text { text }),
+            _ => self.context.add_user_message(&text),
+        }
+    }
+
     /// Run a single user turn — may result in multiple API calls if tools are used
     pub async fn run_turn(&mut self, user_message: &str) -> Result<TurnResult> {
+        if let Some(actions) = &mut self.dry_run {
+            actions.clear();
+        }
+        if let Some(store) = &mut self.checkpoints {
+            store.begin_turn(user_message);
+        }
         self.context.add_user_message(user_message);
+        self.run_loop().await
+    }
 
+    /// Run a user turn whose message carries images/documents as well as text
+    pub async fn run_turn_with_content(&mut self, content: Vec<ContentBlock>) -> Result<TurnResult> {
+        if let Some(actions) = &mut self.dry_run {
+            actions.clear();
+        }
+        if let Some(store) = &mut self.checkpoints {
+            let request = content.iter().find_map(|b| match b {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            });
+            store.begin_turn(request.unwrap_or("(attachment)"));
+        }
+        self.context.add_user_content(content);
+        self.run_loop().await
+    }
+
+    async fn run_loop(&mut self) -> Result<TurnResult> {
         let mut total_usage = Usage::default();
         let mut turns = 0;
 
@@ -67,11 +379,15 @@ impl AgentLoop {
             turns += 1;
             if turns > self.max_turns {
                 return Ok(TurnResult {
-                    text: "[Turn limit reached]".into(),
+                    text: self.with_summary("[Turn limit reached]".into()),
                     usage: total_usage,
+                    hit_turn_limit: true,
+                    escalation: None,
                 });
             }
 
+            self.deliver_mail();
+
             // Check if we should compact
             if self.context.estimated_tokens() > 80_000 {
                 eprintln!("{}", "⚡ Compacting conversation (token limit)...".dimmed());
@@ -139,6 +455,10 @@ impl AgentLoop {
                 .collect();
 
             if tool_calls.is_empty() {
+                // Mail that came in while it was answering gets a look before it stops
+                if self.inbox.as_ref().is_some_and(Inbox::has_mail) {
+                    continue;
+                }
                 // No tools — extract text and return
                 let text = response
                     .content
@@ -154,35 +474,105 @@ impl AgentLoop {
                 self.context.save()?;
 
                 return Ok(TurnResult {
-                    text,
+                    text: self.with_summary(text),
                     usage: total_usage,
+                    hit_turn_limit: false,
+                    escalation: None,
                 });
             }
 
             // Execute tools
+            let mut escalation = None;
             for (id, name, input) in tool_calls {
                 eprintln!("\n{} {}", "🔧".dimmed(), name.cyan());
+                let input = match &self.worktree {
+                    Some(worktree) => worktree.root_input(&name, &input),
+                    None => input,
+                };
+
+                // Only what it was offered — a made-up or filtered-out tool is refused, not run
+                let offered = self.tools.iter().any(|t| t.name == name);
+                let previewing = offered && self.dry_run.is_some() && tools::dry_run::intercepts(&name);
+
+                if let Some(store) = self
+                    .checkpoints
+                    .as_mut()
+                    .filter(|_| offered && !previewing && checkpoint::FILE_TOOLS.contains(&name.as_str()))
+                    && let Err(e) = store.before_tool(&name, &input)
+                {
+                    tracing::warn!("Checkpoint before {name} failed: {e}");
+                }
 
-                let result =
-                    tools::execute_tool(&name, &input, self.brave_api_key.as_deref(), None, self.github_token.as_deref(), self.task_storage.as_ref()).await;
+                let result = if !offered {
+                    Err(anyhow::anyhow!("tool '{name}' isn't available here"))
+                } else if previewing {
+                    let preview = tools::dry_run::preview(&name, &input, self.task_storage.as_ref()).await;
+                    if let Some(actions) = &mut self.dry_run {
+                        actions.push(tools::dry_run::action(&name, &preview));
+                    }
+                    preview.map(|text| ToolResultContent::Text(format!("[Dry run — nothing was changed]\n{text}")))
+                } else if name == "ask_user" {
+                    // Suspend: checkpoint the context so the question can outlive this process
+                    let run = self.suspended_run(&id);
+                    if run.is_some() {
+                        self.context.save()?;
+                    }
+                    let ctx = AskContext { chat: self.chat.as_ref(), origin: self.origin.as_ref(), run };
+                    tools::ask_user::execute(&input, ctx).await.map(ToolResultContent::Text)
+                } else if let (Some(inbox), "message_agent") = (&self.inbox, name.as_str()) {
+                    tools::agents::message_agent_execute(&input, inbox).map(ToolResultContent::Text)
+                } else if let (Some(_), "request_escalation") = (&self.inbox, name.as_str()) {
+                    // Stop after this round; the orchestrator relaunches the run on the next tier
+                    escalation = Some(input["reason"].as_str().unwrap_or("no reason given").to_string());
+                    Ok(ToolResultContent::Text("Escalated — a stronger model takes over from here.".into()))
+                } else if name == "shell" {
+                    match &self.shell {
+                        Some(session) => tools::shell::execute_in_session(&input, session, self.sandbox.as_ref()).await,
+                        None => tools::shell::execute(&input, self.sandbox.as_ref()).await,
+                    }
+                    .map(ToolResultContent::Text)
+                } else if let (Some(session), "shell_reset") = (&self.shell, name.as_str()) {
+                    let text = if session.reset().await { "Shell session reset." } else { "No shell session was running." };
+                    Ok(ToolResultContent::Text(text.into()))
+                } else if name.starts_with("process_") {
+                    tools::process::execute(&name, &input, &self.processes, self.sandbox.as_ref()).await.map(ToolResultContent::Text)
+                } else if let Some(manager) = self.manager.as_ref().filter(|_| Manager::handles(&name)) {
+                    manager.execute(&name, &input, self.origin.as_ref(), &self.policy).await.map(ToolResultContent::Text)
+                } else if let Some(tool) = self.custom_tools.iter().find(|t| t.name == name) {
+                    tool.execute(&input, self.sandbox.as_ref()).await.map(ToolResultContent::Text)
+                } else {
+                    tools::execute_tool(&name, &input, self.brave_api_key.as_deref(), None, self.github_token.as_deref(), self.task_storage.as_ref(), self.chat.as_ref()).await
+                };
 
                 let (content, is_error) = match result {
                     Ok(output) => (output, false),
-                    Err(e) => (format!("Error: {e}"), true),
+                    Err(e) => (ToolResultContent::Text(format!("Error: {e}")), true),
                 };
 
                 // Truncate tool output for display
-                let display = if content.len() > 200 {
-                    format!("{}...", &content[..200])
+                let text = content.text();
+                let display = if text.len() > 200 {
+                    format!("{}...", &text[..text.floor_char_boundary(200)])
                 } else {
-                    content.clone()
+                    text
                 };
                 eprintln!("{}", display.dimmed());
 
-                self.context.add_tool_result(&id, &content, is_error);
+                self.context.add_tool_result_content(&id, content, is_error);
             }
 
-            self.context.save()?;
+            if escalation.is_some() {
+                self.context.save()?;
+                return Ok(TurnResult {
+                    text: String::new(),
+                    usage: total_usage,
+                    hit_turn_limit: false,
+                    escalation,
+                });
+            }
+            if turns % self.checkpoint_interval == 0 {
+                self.context.save()?;
+            }
             // Loop back to get the next response
         }
     }
@@ -192,4 +582,8 @@ impl AgentLoop {
 pub struct TurnResult {
     pub text: String,
     pub usage: Usage,
+    /// Stopped after max_turns rounds of tool calls, not with a final answer
+    pub hit_turn_limit: bool,
+    /// The agent asked to be handed to a stronger model, and why
+    pub escalation: Option<String>,
 }
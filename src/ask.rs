@@ -0,0 +1,299 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::oneshot;
+
+use crate::memory::TaskStorage;
+use crate::telegram::api::{ChatTarget, TelegramBot};
+use crate::tools::policy::ToolPolicy;
+use crate::types::Thinking;
+use crate::worktree::Worktree;
+
+/// Questions agents in this process are waiting on (the `ask_user` tool)
+static WAITING: LazyLock<Mutex<HashMap<String, Waiting>>> = LazyLock::new(Default::default);
+/// Telegram bots by config name, for questions from agents that aren't in a chat turn
+static BOTS: LazyLock<Mutex<HashMap<String, TelegramBot>>> = LazyLock::new(Default::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Prefix for inline keyboard callback data: `ask:<id>:<option index>`
+pub const CALLBACK_PREFIX: &str = "ask:";
+
+/// Where a question is shown and answered — the surface that started the agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "surface", rename_all = "snake_case")]
+pub enum Origin {
+    Telegram { bot: String, chat_id: i64, thread_id: Option<i64> },
+    Dashboard,
+    Cli,
+}
+
+impl From<&ChatTarget> for Origin {
+    fn from(chat: &ChatTarget) -> Self {
+        Self::Telegram { bot: chat.name.clone(), chat_id: chat.chat_id, thread_id: chat.thread_id }
+    }
+}
+
+/// An open `ask_user` question. Persisted while it waits so it can be listed
+/// on the dashboard and, for resumable agents, answered after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub id: String,
+    pub origin: Origin,
+    pub question: String,
+    pub options: Vec<String>,
+    /// Free-text replies count as answers
+    pub allow_text: bool,
+    /// Used when nobody answers before the deadline
+    pub default: Option<String>,
+    pub asked_at: DateTime<Utc>,
+    pub deadline: DateTime<Utc>,
+    /// Answer given while no agent was waiting (the asker died with the old process)
+    #[serde(default)]
+    pub answer: Option<String>,
+    /// The suspended agent, if it can be resumed after a restart
+    #[serde(default)]
+    pub run: Option<SuspendedRun>,
+}
+
+/// Enough to rebuild an agent whose context was checkpointed mid-`ask_user`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspendedRun {
+    pub run_id: String,
+    pub context_path: PathBuf,
+    /// The pending `ask_user` call the answer belongs to
+    pub tool_use_id: String,
+    pub model: String,
+    pub system_prompt: String,
+    pub thinking: Thinking,
+    pub storage: Option<TaskStorage>,
+    #[serde(default)]
+    pub tools: ToolPolicy,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The sub-agent's own git worktree, if it had one
+    #[serde(default)]
+    pub worktree: Option<Worktree>,
+}
+
+impl Question {
+    /// Map a typed reply onto an option: "2" picks the second button
+    pub fn parse_answer(&self, text: &str) -> String {
+        let text = text.trim();
+        text.parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| self.options.get(i))
+            .cloned()
+            .unwrap_or_else(|| text.to_string())
+    }
+
+    /// Tool result for an answer
+    pub fn answered(&self, answer: &str) -> String {
+        format!("User answered: {answer}")
+    }
+
+    /// Tool result once the deadline has passed
+    pub fn timed_out(&self) -> String {
+        let minutes = (self.deadline - self.asked_at).num_minutes();
+        match &self.default {
+            Some(d) => format!("No answer after {minutes} minutes — going with the default: {d}"),
+            None => format!("No answer after {minutes} minutes. Proceed with your best judgement or stop."),
+        }
+    }
+}
+
+struct Waiting {
+    origin: Origin,
+    options: Vec<String>,
+    allow_text: bool,
+    tx: oneshot::Sender<String>,
+}
+
+/// A short id that fits in callback data
+pub fn new_id() -> String {
+    let n = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{:x}{n:x}", t & 0xffff_ffff)
+}
+
+/// Make a bot available to questions from agents outside a chat turn
+pub fn register_bot(name: &str, bot: TelegramBot) {
+    BOTS.lock().unwrap().insert(name.to_string(), bot);
+}
+
+pub fn bot(name: &str) -> Option<TelegramBot> {
+    BOTS.lock().unwrap().get(name).cloned()
+}
+
+/// Register a question an agent in this process is waiting on. The receiver
+/// yields the answer; dropping it (or calling `cancel`) withdraws the question.
+pub fn wait_for(q: &Question) -> oneshot::Receiver<String> {
+    let (tx, rx) = oneshot::channel();
+    let mut waiting = WAITING.lock().unwrap();
+    waiting.retain(|_, w| !w.tx.is_closed());
+    waiting.insert(
+        q.id.clone(),
+        Waiting { origin: q.origin.clone(), options: q.options.clone(), allow_text: q.allow_text, tx },
+    );
+    rx
+}
+
+pub fn cancel(id: &str) {
+    WAITING.lock().unwrap().remove(id);
+}
+
+/// Whether an agent in this process is still waiting on `id`
+pub fn is_live(id: &str) -> bool {
+    WAITING.lock().unwrap().get(id).is_some_and(|w| !w.tx.is_closed())
+}
+
+/// Answer a question by id — the waiting agent if there is one, otherwise
+/// the persisted question of a suspended agent
+pub fn answer(id: &str, text: &str) -> bool {
+    if let Some(w) = WAITING.lock().unwrap().remove(id) {
+        return w.tx.send(text.to_string()).is_ok();
+    }
+    QuestionStore::open().answer(id, text)
+}
+
+/// Handle `ask:<id>:<index>` callback data. Returns the chosen option, or
+/// `None` if the question is gone (answered, timed out, or its turn died in a restart).
+pub fn answer_callback(data: &str) -> Option<String> {
+    let (id, index) = data.strip_prefix(CALLBACK_PREFIX)?.rsplit_once(':')?;
+    let index: usize = index.parse().ok()?;
+    let live = WAITING.lock().unwrap().get(id).map(|w| w.options.clone());
+    let options = match live {
+        Some(options) => options,
+        None => QuestionStore::open().get(id)?.options,
+    };
+    let label = options.get(index)?.clone();
+    answer(id, &label).then_some(label)
+}
+
+/// Answer the open free-text question in this Telegram conversation, if any
+pub fn answer_text(origin: &Origin, text: &str) -> bool {
+    let live = WAITING
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, w)| w.origin == *origin && w.allow_text && !w.tx.is_closed())
+        .map(|(id, _)| id.clone());
+    if let Some(id) = live {
+        return answer(&id, text);
+    }
+    let store = QuestionStore::open();
+    store
+        .list()
+        .into_iter()
+        .find(|q| q.origin == *origin && q.allow_text && q.run.is_some() && q.answer.is_none())
+        .is_some_and(|q| store.answer(&q.id, text))
+}
+
+/// On-disk record of open questions — one JSON file each
+pub struct QuestionStore {
+    dir: PathBuf,
+}
+
+impl QuestionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The shared store under the DevMan data directory
+    pub fn open() -> Self {
+        Self::new(
+            dirs::data_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("devman")
+                .join("questions"),
+        )
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    pub fn save(&self, q: &Question) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self.path(&q.id).with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(q)?)?;
+        std::fs::rename(&tmp, self.path(&q.id))?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<Question> {
+        // Ids come from callback data — don't let them wander out of the directory
+        if !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let content = std::fs::read_to_string(self.path(id)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn remove(&self, id: &str) {
+        let _ = std::fs::remove_file(self.path(id));
+    }
+
+    /// All persisted questions, oldest first
+    pub fn list(&self) -> Vec<Question> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut questions: Vec<Question> = entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+            .filter_map(|c| serde_json::from_str(&c).ok())
+            .collect();
+        questions.sort_by_key(|q| q.asked_at);
+        questions
+    }
+
+    /// Record an answer for a suspended agent. Questions without a run
+    /// died with the process that asked them and can't take answers.
+    pub fn answer(&self, id: &str, text: &str) -> bool {
+        let Some(mut q) = self.get(id) else {
+            return false;
+        };
+        if q.run.is_none() || q.answer.is_some() {
+            return false;
+        }
+        q.answer = Some(text.to_string());
+        self.save(&q).is_ok()
+    }
+
+    /// Drop questions whose asker can't be resumed (their turn ended with the old process)
+    pub fn discard_stale(&self) {
+        for q in self.list() {
+            if q.run.is_none() && !is_live(&q.id) {
+                self.remove(&q.id);
+            }
+        }
+    }
+
+    /// Take the suspended runs that are ready to continue — answered, or past
+    /// their deadline — along with the tool result to resume them with
+    pub fn take_ready(&self, now: DateTime<Utc>) -> Vec<(Question, String)> {
+        let mut ready = Vec::new();
+        for q in self.list() {
+            if q.run.is_none() || is_live(&q.id) {
+                continue;
+            }
+            let result = match &q.answer {
+                Some(a) => q.answered(a),
+                None if now >= q.deadline => q.timed_out(),
+                None => continue,
+            };
+            self.remove(&q.id);
+            ready.push((q, result));
+        }
+        ready
+    }
+}
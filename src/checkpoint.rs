@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::diff::unified_diff;
+use crate::tools;
+
+/// Tools that change files, and so get a snapshot first
+pub const FILE_TOOLS: &[&str] = &["write_file", "edit_file", "apply_patch"];
+
+/// A file as it was before a turn first touched it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub tool: String,
+    /// Blob holding the old contents; `None` if the file didn't exist
+    pub blob: Option<String>,
+}
+
+/// One user turn's file changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub number: u32,
+    /// Start of the message that started it
+    pub request: String,
+    pub started: DateTime<Utc>,
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// A file changed in a session, from its first snapshot to now
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// 'A' created, 'D' deleted, 'M' modified
+    pub kind: char,
+    pub turns: Vec<u32>,
+    pub diff: String,
+}
+
+/// Prior contents of every file an agent's tools changed in one session —
+/// a chat, a CLI session or a sub-agent run — so its turns can be undone.
+/// Lives in `<dir>/index.json` with the old contents under `<dir>/blobs/`,
+/// and doesn't need git.
+pub struct CheckpointStore {
+    dir: PathBuf,
+    turns: Vec<Turn>,
+    /// Set by `begin_turn`; recorded once it changes a file
+    pending: Option<Turn>,
+}
+
+impl CheckpointStore {
+    pub fn open(dir: PathBuf) -> Self {
+        let turns = std::fs::read_to_string(dir.join("index.json"))
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        Self { dir, turns, pending: None }
+    }
+
+    /// Turns that changed files, oldest first
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Snapshots from here on belong to a new turn
+    pub fn begin_turn(&mut self, request: &str) {
+        let number = self.turns.last().map_or(0, |t| t.number) + 1;
+        let request: String = request.chars().take(80).collect();
+        self.pending = Some(Turn { number, request, started: Utc::now(), snapshots: Vec::new() });
+    }
+
+    fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join("index.json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&self.turns)?)?;
+        std::fs::rename(&tmp, self.dir.join("index.json"))?;
+        Ok(())
+    }
+
+    /// Save `path` as it is now, unless this turn already has it
+    pub fn snapshot(&mut self, path: &Path, tool: &str) -> Result<()> {
+        let path = if path.is_relative() { std::env::current_dir()?.join(path) } else { path.to_path_buf() };
+        if let Some(pending) = self.pending.take() {
+            self.turns.push(pending);
+        }
+        if self.turns.is_empty() {
+            self.begin_turn("");
+            self.turns.extend(self.pending.take());
+        }
+        let turn = self.turns.last_mut().expect("a turn was just started");
+        if turn.snapshots.iter().any(|s| s.path == path) {
+            return Ok(());
+        }
+
+        let blob = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let name = format!("{}-{}", turn.number, turn.snapshots.len());
+                let blobs = self.dir.join("blobs");
+                std::fs::create_dir_all(&blobs)?;
+                std::fs::write(blobs.join(&name), bytes)?;
+                Some(name)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("reading {} for a checkpoint", path.display())),
+        };
+        turn.snapshots.push(Snapshot { path, tool: tool.to_string(), blob });
+        self.save()
+    }
+
+    /// Snapshot the files a tool call is about to change
+    pub fn before_tool(&mut self, tool: &str, input: &serde_json::Value) -> Result<()> {
+        let paths = match tool {
+            "write_file" | "edit_file" => {
+                let Some(path) = input["path"].as_str() else { return Ok(()) };
+                match path.strip_prefix("~/") {
+                    Some(rest) => vec![dirs::home_dir().unwrap_or_default().join(rest)],
+                    None => vec![PathBuf::from(path)],
+                }
+            }
+            "apply_patch" => tools::patch::touched_paths(input).unwrap_or_default(),
+            _ => return Ok(()),
+        };
+        for path in paths {
+            self.snapshot(&path, tool)?;
+        }
+        Ok(())
+    }
+
+    fn restore(&self, snapshot: &Snapshot) -> Result<String> {
+        let shown = snapshot.path.display();
+        match &snapshot.blob {
+            Some(blob) => {
+                let bytes = std::fs::read(self.dir.join("blobs").join(blob))
+                    .with_context(|| format!("checkpoint of {shown} is missing"))?;
+                if let Some(parent) = snapshot.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&snapshot.path, bytes)?;
+                Ok(format!("restored {shown}"))
+            }
+            None if snapshot.path.exists() => {
+                std::fs::remove_file(&snapshot.path)?;
+                Ok(format!("removed {shown}"))
+            }
+            None => Ok(format!("{shown} already gone")),
+        }
+    }
+
+    /// Put back every file the last `n` turns changed, as it was before them.
+    /// Returns what was done to each file.
+    pub fn undo(&mut self, n: usize) -> Result<Vec<String>> {
+        if self.turns.is_empty() {
+            anyhow::bail!("no file changes to undo");
+        }
+        let keep = self.turns.len().saturating_sub(n.max(1));
+        let undone = self.turns.split_off(keep);
+
+        // A file's oldest snapshot among the undone turns is how it was before them
+        let mut done = Vec::new();
+        let mut seen = Vec::new();
+        for snapshot in undone.iter().flat_map(|t| &t.snapshots) {
+            if seen.contains(&&snapshot.path) {
+                continue;
+            }
+            seen.push(&snapshot.path);
+            done.push(self.restore(snapshot).unwrap_or_else(|e| format!("{}: {e}", snapshot.path.display())));
+        }
+        for blob in undone.iter().flat_map(|t| &t.snapshots).filter_map(|s| s.blob.as_ref()) {
+            let _ = std::fs::remove_file(self.dir.join("blobs").join(blob));
+        }
+        self.pending = None;
+        self.save()?;
+        Ok(done)
+    }
+
+    /// Every file changed this session that differs from how it started
+    pub fn changes(&self) -> Vec<FileChange> {
+        let mut changes: Vec<FileChange> = Vec::new();
+        for turn in &self.turns {
+            for snapshot in &turn.snapshots {
+                if let Some(change) = changes.iter_mut().find(|c| c.path == snapshot.path) {
+                    change.turns.push(turn.number);
+                    continue;
+                }
+                let before = match &snapshot.blob {
+                    Some(blob) => std::fs::read(self.dir.join("blobs").join(blob)).ok(),
+                    None => None,
+                };
+                let after = std::fs::read(&snapshot.path).ok();
+                if before == after {
+                    continue;
+                }
+                let kind = match (&before, &after) {
+                    (None, _) => 'A',
+                    (_, None) => 'D',
+                    _ => 'M',
+                };
+                let text = |b: &Option<Vec<u8>>| b.as_deref().map(String::from_utf8_lossy).unwrap_or_default().into_owned();
+                let diff = unified_diff(&snapshot.path.display().to_string(), &text(&before), &text(&after), 3);
+                changes.push(FileChange { path: snapshot.path.clone(), kind, turns: vec![turn.number], diff });
+            }
+        }
+        changes
+    }
+
+    /// `/changes`: files touched this session, then their diffs up to `max_chars`
+    pub fn report(&self, max_chars: usize) -> String {
+        let changes = self.changes();
+        if changes.is_empty() {
+            return "No file changes this session.".into();
+        }
+        let mut out = format!("{} file(s) changed over {} turn(s):\n", changes.len(), self.turns.len());
+        for c in &changes {
+            let turns: Vec<String> = c.turns.iter().map(u32::to_string).collect();
+            out.push_str(&format!("{} {} (turn {})\n", c.kind, c.path.display(), turns.join(", ")));
+        }
+        for c in &changes {
+            let block = format!("\n```diff\n{}```\n", c.diff);
+            if out.len() + block.len() > max_chars {
+                out.push_str("\n(more diffs not shown)");
+                break;
+            }
+            out.push_str(&block);
+        }
+        out
+    }
+
+    /// Forget the session's checkpoints, leaving the files as they are
+    pub fn clear(&mut self) -> Result<()> {
+        self.turns.clear();
+        self.pending = None;
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,20 @@
 use anyhow::Result;
 use colored::Colorize;
-use std::io::{self, BufRead, Write};
+use std::io::{self, Write};
 
 use crate::agent::AgentLoop;
+use crate::ask::Origin;
 use crate::auth::AuthStore;
+use crate::checkpoint::CheckpointStore;
 use crate::client::AnthropicClient;
 use crate::config::Config;
 use crate::context::ContextManager;
+use crate::shell_session::ShellSession;
 use crate::tools;
 use crate::types::Thinking;
 
 /// Interactive chat REPL
-pub async fn run(config: &Config) -> Result<()> {
+pub async fn run(config: &Config, dry_run: bool) -> Result<()> {
     let auth = AuthStore::load()?;
     let api_key = auth.anthropic_api_key()?;
     let client = AnthropicClient::new(api_key);
@@ -40,20 +43,31 @@ pub async fn run(config: &Config) -> Result<()> {
         Thinking::Off,
         brave_key,
         github_token,
-    );
+    )
+    .with_origin(Origin::Cli)
+    .with_custom_tools(tools::custom::load_custom_tools(config))
+    .with_file_checkpoints(CheckpointStore::open(state_dir.join("checkpoints").join("cli")))
+    .with_dry_run(dry_run);
+    let shell = ShellSession::new();
+    if config.tools.shell_sessions {
+        agent = agent.with_shell_session(shell.clone());
+    }
 
-    eprintln!("{}", "DevMan 🔧 — type /quit to exit, /clear to reset".bold());
+    eprintln!("{}", "DevMan 🔧 — type /quit to exit, /clear to reset, /undo [N] or /changes for file edits, /dryrun to toggle previews".bold());
+    if dry_run {
+        eprintln!("{}", "Dry run: changes are previewed, not made.".yellow());
+    }
     eprintln!();
 
+    // Not locked for the whole session — ask_user reads answers from stdin mid-turn
     let stdin = io::stdin();
-    let mut reader = stdin.lock();
 
     loop {
         eprint!("{}", "You: ".green().bold());
         io::stderr().flush()?;
 
         let mut input = String::new();
-        if reader.read_line(&mut input)? == 0 {
+        if stdin.read_line(&mut input)? == 0 {
             break; // EOF
         }
 
@@ -67,9 +81,26 @@ pub async fn run(config: &Config) -> Result<()> {
             "/quit" | "/exit" | "/q" => break,
             "/clear" => {
                 agent.context = ContextManager::new();
+                if let Some(store) = agent.file_checkpoints() {
+                    let _ = store.clear();
+                }
+                shell.reset().await;
                 eprintln!("{}", "Conversation cleared.".dimmed());
                 continue;
             }
+            "/changes" => {
+                if let Some(store) = agent.file_checkpoints() {
+                    eprintln!("{}", store.report(usize::MAX));
+                }
+                continue;
+            }
+            "/dryrun" => {
+                let on = agent.dry_run_actions().is_none();
+                agent.set_dry_run(on);
+                let state = if on { "on — changes are previewed, not made" } else { "off" };
+                eprintln!("{}", format!("Dry run {state}.").dimmed());
+                continue;
+            }
             "/cost" => {
                 eprintln!(
                     "Tokens: {} input, {} output",
@@ -78,6 +109,19 @@ pub async fn run(config: &Config) -> Result<()> {
                 );
                 continue;
             }
+            cmd if cmd == "/undo" || cmd.starts_with("/undo ") => {
+                let arg = cmd["/undo".len()..].trim();
+                let Ok(n) = (if arg.is_empty() { Ok(1) } else { arg.parse::<usize>() }) else {
+                    eprintln!("{}", "Usage: /undo [number of turns]".red());
+                    continue;
+                };
+                match agent.file_checkpoints().map(|s| s.undo(n)) {
+                    Some(Ok(done)) => eprintln!("{}", format!("Undid {n} turn(s) of file changes:\n{}", done.join("\n")).dimmed()),
+                    Some(Err(e)) => eprintln!("{}", format!("{e}").red()),
+                    None => {}
+                }
+                continue;
+            }
             _ => {}
         }
 
@@ -85,6 +129,9 @@ pub async fn run(config: &Config) -> Result<()> {
 
         match agent.run_turn(trimmed).await {
             Ok(result) => {
+                if let Some(actions) = agent.dry_run_actions() {
+                    eprintln!("\n{}", tools::dry_run::summary(actions).yellow());
+                }
                 eprintln!(
                     "{}",
                     format!(
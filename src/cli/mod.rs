@@ -2,3 +2,4 @@ pub mod chat;
 pub mod init;
 pub mod run;
 pub mod serve;
+pub mod workflow;
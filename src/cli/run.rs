@@ -2,15 +2,17 @@ use anyhow::Result;
 use colored::Colorize;
 
 use crate::agent::AgentLoop;
+use crate::ask::Origin;
 use crate::auth::AuthStore;
 use crate::client::AnthropicClient;
 use crate::config::Config;
 use crate::context::ContextManager;
+use crate::shell_session::ShellSession;
 use crate::tools;
 use crate::types::Thinking;
 
-/// Run a single task and exit
-pub async fn run(config: &Config, message: &str) -> Result<()> {
+/// Run a single task and exit — with `dry_run`, changes are previewed, not made
+pub async fn run(config: &Config, message: &str, dry_run: bool) -> Result<()> {
     let auth = AuthStore::load()?;
     let api_key = auth.anthropic_api_key()?;
     let client = AnthropicClient::new(api_key);
@@ -33,7 +35,13 @@ pub async fn run(config: &Config, message: &str) -> Result<()> {
         Thinking::Off,
         brave_key,
         github_token,
-    );
+    )
+    .with_origin(Origin::Cli)
+    .with_custom_tools(tools::custom::load_custom_tools(config))
+    .with_dry_run(dry_run);
+    if config.tools.shell_sessions {
+        agent = agent.with_shell_session(ShellSession::new());
+    }
 
     let result = agent.run_turn(message).await?;
 
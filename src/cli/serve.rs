@@ -1,31 +1,120 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::signal;
 use tokio::sync::RwLock;
 
-use crate::agent::AgentLoop;
+use crate::agent::{AgentLoop, TurnResult};
+use crate::ask::{self, Origin, Question, QuestionStore};
 use crate::auth::AuthStore;
+use crate::checkpoint::CheckpointStore;
 use crate::client::AnthropicClient;
-use crate::config::{Config, ScopedBotConfig};
+use crate::config::{Config, SandboxConfig, ScopedBotConfig};
 use crate::context::ContextManager;
-use crate::cost::CostTracker;
-use crate::cron::CronScheduler;
+use crate::cost::{Cost, CostTracker};
+use crate::cron::{CronJob, CronScheduler};
 use crate::dashboard::{self, SharedState as DashboardState, broadcast_log};
-use crate::dashboard::api::AgentInfo;
+use crate::manager::{self, Manager};
+use crate::media;
 use crate::memory::{MemoryManager, TaskStorage};
-use crate::telegram::api::TelegramBot;
-use crate::telegram::types::TgMessage;
+use crate::process::ProcessTable;
+use crate::sandbox::Sandbox;
+use crate::shell_session::ShellSession;
+use crate::tools::custom::CustomTool;
+use crate::tools::policy::ToolPolicy;
+use crate::telegram::api::{ChatRef, ChatTarget, TelegramBot};
+use crate::telegram::commands::{self, BotCommand};
+use crate::telegram::format;
+use crate::telegram::types::{CallbackQuery, Chat, TgMessage, Update, User};
 use crate::tools;
 use crate::tools::bot_management::RESTART_REQUESTED;
-use crate::types::Thinking;
+use crate::types::{ContentBlock, Thinking, ToolDefinition};
+use crate::voice::VoiceEngine;
 use std::sync::atomic::Ordering;
 
-/// Per-chat conversation state
+/// A conversation: a chat, or one forum topic within a group
+type ConvKey = (i64, Option<i64>);
+
+/// Per-conversation state
 struct ChatState {
     context: ContextManager,
+    settings: ChatSettings,
+    settings_path: PathBuf,
+    /// File checkpoints for /undo and /changes
+    checkpoints_dir: PathBuf,
+    /// Background processes the agent started here; they live until /new
+    processes: ProcessTable,
+    /// Bash session for `shell` when `tools.shell_sessions` is on; reset by /new
+    shell: ShellSession,
+}
+
+/// Per-conversation overrides set with slash commands, saved next to the
+/// conversation as `<chat_id>[_t<topic>].settings.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChatSettings {
+    /// Model id (tiers are resolved when set)
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    thinking: Option<Thinking>,
+    /// Active task slug, within the bot's task scope
+    #[serde(default)]
+    task: Option<String>,
+    /// Running total for this chat
+    #[serde(default)]
+    cost: Cost,
+    /// Preview the agent's changes instead of making them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+impl ChatState {
+    fn load(chats_dir: &Path, (chat_id, thread_id): ConvKey) -> Self {
+        // Forum topics get their own files; plain chats keep the old name
+        let stem = match thread_id {
+            Some(t) => format!("{chat_id}_t{t}"),
+            None => chat_id.to_string(),
+        };
+        let settings_path = chats_dir.join(format!("{stem}.settings.json"));
+        let settings = std::fs::read_to_string(&settings_path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            context: ContextManager::with_persistence(chats_dir.join(format!("{stem}.json"))),
+            settings,
+            settings_path,
+            checkpoints_dir: chats_dir.join(format!("{stem}.checkpoints")),
+            processes: ProcessTable::new(),
+            shell: ShellSession::new(),
+        }
+    }
+
+    fn save_settings(&self) {
+        let result = serde_json::to_string_pretty(&self.settings)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| std::fs::write(&self.settings_path, json).map_err(Into::into));
+        if let Err(e) = result {
+            tracing::error!("Failed to save {}: {e}", self.settings_path.display());
+        }
+    }
+}
+
+/// Everything a message handler needs besides the bot itself
+#[derive(Clone, Copy)]
+struct Shared<'a> {
+    api_key: &'a str,
+    tool_defs: &'a [crate::types::ToolDefinition],
+    custom_tools: &'a [CustomTool],
+    brave_api_key: &'a Option<String>,
+    github_token: &'a Option<String>,
+    cost_tracker: &'a Arc<RwLock<CostTracker>>,
+    config: &'a Config,
+    dash: Option<&'a DashboardState>,
 }
 
 /// A running bot instance (manager or scoped)
@@ -33,7 +122,9 @@ struct BotInstance {
     name: String,
     bot: TelegramBot,
     offset: i64,
-    chat_states: HashMap<i64, ChatState>,
+    /// Updates fetched while a turn was running, handled once it finishes
+    pending: VecDeque<Update>,
+    chat_states: HashMap<ConvKey, ChatState>,
     chats_dir: PathBuf,
     model: String,
     system_prompt: String,
@@ -51,13 +142,84 @@ struct BotInstance {
     max_budget_usd: f64,
     /// Timeout per Claude Code call (seconds)
     dev_timeout_seconds: u64,
+    /// "off", "mirror" (reply in voice to voice messages) or "always"
+    voice_reply: String,
+    /// Shared STT/TTS engine, if any backend is configured
+    voice: Option<VoiceEngine>,
+    group: GroupPolicy,
+    /// Sandbox for shell and process tools; None runs them as the devman user
+    sandbox: Option<SandboxConfig>,
+    /// Tools it's offered and allowed to run
+    tool_policy: ToolPolicy,
+    /// The sub-agent pool, for the manager bot's agent tools
+    manager: Option<Manager>,
+}
+
+/// Who may use a bot in group chats, and when it answers there
+struct GroupPolicy {
+    /// Groups where any member may talk to the bot
+    allowed_groups: Vec<i64>,
+    /// "mention" (answer @mentions and replies) or "all"
+    trigger: String,
+    /// The bot's own user, from getMe
+    me: Option<User>,
+}
+
+impl GroupPolicy {
+    /// Allowed users anywhere, or anyone in an allowed group
+    fn permits(&self, bot: &TelegramBot, chat: &Chat, user: Option<&User>) -> bool {
+        let user_ok = user.is_some_and(|u| bot.is_allowed(u.id));
+        user_ok || (chat.is_group() && self.allowed_groups.contains(&chat.id))
+    }
+
+    /// False for `/command@other_bot`
+    fn is_for_us(&self, cmd: &BotCommand) -> bool {
+        match (cmd.bot, self.me.as_ref().and_then(|m| m.username.as_deref())) {
+            (Some(target), Some(ours)) => target.eq_ignore_ascii_case(ours),
+            _ => true,
+        }
+    }
+
+    /// Should a (non-command) group message get an answer?
+    fn triggered_by(&self, msg: &TgMessage) -> bool {
+        if self.trigger == "all" {
+            return true;
+        }
+        let Some(me) = &self.me else { return false };
+        msg.is_reply_to(me.id) || me.username.as_deref().is_some_and(|u| msg.mentions(u, me.id))
+    }
 }
 
 impl BotInstance {
-    /// Get appropriate TaskStorage for this bot
-    fn task_storage(&self) -> Option<TaskStorage> {
+    /// Conversation state for a chat or topic, loaded from disk on first use
+    fn chat(&mut self, dest: ChatRef) -> &mut ChatState {
+        let chats_dir = &self.chats_dir;
+        let key = (dest.chat_id, dest.thread_id);
+        self.chat_states
+            .entry(key)
+            .or_insert_with(|| ChatState::load(chats_dir, key))
+    }
+
+    /// The conversation at `dest`, as an `ask_user` origin
+    fn origin(&self, dest: ChatRef) -> ask::Origin {
+        ask::Origin::Telegram { bot: self.name.clone(), chat_id: dest.chat_id, thread_id: dest.thread_id }
+    }
+
+    /// Tasks this bot may pick with /task
+    fn available_tasks(&self) -> Vec<String> {
+        if self.task_scope.is_empty() || self.task_scope == ["*"] {
+            MemoryManager::new(MemoryManager::default_root()).list_tasks()
+        } else {
+            self.task_scope.clone()
+        }
+    }
+
+    /// Get appropriate TaskStorage for this bot (scoped to the chat's active task, if any)
+    fn task_storage(&self, active_task: Option<&str>) -> Option<TaskStorage> {
         let mm = MemoryManager::new(MemoryManager::default_root());
-        if self.memory_access == "full" || self.task_scope.is_empty() || self.task_scope == ["*"] {
+        if let Some(task) = active_task {
+            Some(mm.task_storage(task))
+        } else if self.memory_access == "full" || self.task_scope.is_empty() || self.task_scope == ["*"] {
             Some(mm.global_storage())
         } else if self.task_scope.len() == 1 {
             // Single task scope — give scoped storage for that task
@@ -70,9 +232,23 @@ impl BotInstance {
     }
 }
 
-/// Extract text + download attachments from a Telegram message
-async fn extract_message_content(bot: &TelegramBot, msg: &TgMessage, download_dir: &PathBuf) -> String {
+/// What we pulled out of an incoming Telegram message
+struct IncomingContent {
+    text: String,
+    /// Image/document blocks for the model to see
+    attachments: Vec<ContentBlock>,
+    /// The text is a transcribed voice note
+    from_voice: bool,
+}
+
+/// Extract text + download attachments from a Telegram message.
+/// Photos, image documents and PDFs also come back as content blocks so
+/// the model can see them; voice notes are transcribed when STT is
+/// configured; everything else is described by path.
+async fn extract_message_content(bot: &TelegramBot, voice_engine: Option<&VoiceEngine>, msg: &TgMessage, download_dir: &PathBuf) -> IncomingContent {
     let mut parts: Vec<String> = Vec::new();
+    let mut blocks: Vec<ContentBlock> = Vec::new();
+    let mut from_voice = false;
 
     if let Some(ref t) = msg.text {
         parts.push(t.clone());
@@ -80,12 +256,15 @@ async fn extract_message_content(bot: &TelegramBot, msg: &TgMessage, download_di
         parts.push(c.clone());
     }
 
-    if let Some(ref photos) = msg.photo {
-        if let Some(largest) = photos.last() {
-            match bot.download_by_id(&largest.file_id, download_dir, "photo").await {
-                Ok(path) => parts.push(format!("[Image downloaded: {} ({}×{})]", path.display(), largest.width, largest.height)),
-                Err(e) => parts.push(format!("[Failed to download image: {e}]")),
+    if let Some(ref photos) = msg.photo
+        && let Some(photo) = media::pick_photo_size(photos)
+    {
+        match bot.download_by_id(&photo.file_id, download_dir, "photo").await {
+            Ok(path) => {
+                parts.push(format!("[Image downloaded: {} ({}×{})]", path.display(), photo.width, photo.height));
+                attach_file(&path, &mut parts, &mut blocks);
             }
+            Err(e) => parts.push(format!("[Failed to download image: {e}]")),
         }
     }
 
@@ -96,6 +275,7 @@ async fn extract_message_content(bot: &TelegramBot, msg: &TgMessage, download_di
                 let mime = doc.mime_type.as_deref().unwrap_or("unknown");
                 let size = doc.file_size.unwrap_or(0);
                 parts.push(format!("[File downloaded: {} ({mime}, {size} bytes)]", path.display()));
+                attach_file(&path, &mut parts, &mut blocks);
             }
             Err(e) => parts.push(format!("[Failed to download file: {e}]")),
         }
@@ -103,7 +283,17 @@ async fn extract_message_content(bot: &TelegramBot, msg: &TgMessage, download_di
 
     if let Some(ref voice) = msg.voice {
         match bot.download_by_id(&voice.file_id, download_dir, "voice").await {
-            Ok(path) => parts.push(format!("[Voice message downloaded: {} ({}s)]", path.display(), voice.duration)),
+            Ok(path) => match voice_engine.filter(|v| v.can_transcribe()) {
+                Some(engine) => match engine.stt(&path).await {
+                    Ok(transcript) if !transcript.trim().is_empty() => {
+                        parts.push(transcript.trim().to_string());
+                        from_voice = true;
+                    }
+                    Ok(_) => parts.push(format!("[Voice message downloaded: {} ({}s) — no speech detected]", path.display(), voice.duration)),
+                    Err(e) => parts.push(format!("[Voice message downloaded: {} ({}s) — transcription failed: {e}]", path.display(), voice.duration)),
+                },
+                None => parts.push(format!("[Voice message downloaded: {} ({}s)]", path.display(), voice.duration)),
+            },
             Err(e) => parts.push(format!("[Failed to download voice: {e}]")),
         }
     }
@@ -129,63 +319,636 @@ async fn extract_message_content(bot: &TelegramBot, msg: &TgMessage, download_di
         parts.push(format!("[Sticker: {emoji}]"));
     }
 
-    parts.join("\n")
+    IncomingContent {
+        text: parts.join("\n"),
+        attachments: blocks,
+        from_voice,
+    }
+}
+
+/// Attach an image/PDF as a content block if Claude can take it
+fn attach_file(path: &std::path::Path, parts: &mut Vec<String>, blocks: &mut Vec<ContentBlock>) {
+    match media::file_block(path) {
+        Ok(Some((_, block))) => blocks.push(block),
+        Ok(None) => {}
+        Err(e) => parts.push(format!("[Not attached: {e}]")),
+    }
 }
 
 /// Process a single message for a bot instance
-async fn handle_message(
-    instance: &mut BotInstance,
-    msg: TgMessage,
-    api_key: &str,
-    tool_defs: &[crate::types::ToolDefinition],
-    brave_api_key: &Option<String>,
-    github_token: &Option<String>,
-    cost_tracker: &Arc<RwLock<CostTracker>>,
-    config: &Config,
-    dash: Option<&DashboardState>,
-) {
+async fn handle_message(instance: &mut BotInstance, msg: TgMessage, sh: &Shared<'_>) {
     let user = match msg.from {
         Some(ref u) => u,
         None => return,
     };
 
-    if !instance.bot.is_allowed(user.id) {
+    if !instance.group.permits(&instance.bot, &msg.chat, Some(user)) {
+        return;
+    }
+
+    let in_group = msg.chat.is_group();
+    let user_name = user.display_name();
+    // Forum topics are separate conversations; in groups we answer as a reply
+    let dest = ChatRef {
+        chat_id: msg.chat.id,
+        thread_id: if msg.is_topic_message { msg.message_thread_id } else { None },
+        reply_to: in_group.then_some(msg.message_id),
+    };
+
+    // Slash commands never reach the agent
+    if let Some(cmd) = msg.text.as_deref().and_then(commands::parse)
+        && commands::is_known(&cmd.name)
+    {
+        if !instance.group.is_for_us(&cmd) {
+            return;
+        }
+        let reply = handle_command(instance, dest, &cmd, sh.config);
+        if let Err(e) = instance.bot.send_message(dest, &reply).await {
+            tracing::error!("[{}] Failed to send command reply: {e}", instance.name);
+        }
         return;
     }
 
-    let chat_id = msg.chat.id;
-    let user_name = user.username.clone().unwrap_or_else(|| user.first_name.clone());
+    if in_group && !instance.group.triggered_by(&msg) {
+        return;
+    }
     let download_dir = dirs::data_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("devman")
         .join("tmp");
 
-    let text = extract_message_content(&instance.bot, &msg, &download_dir).await;
-    if text.is_empty() {
+    let incoming = extract_message_content(&instance.bot, instance.voice.as_ref(), &msg, &download_dir).await;
+    let mut text = match instance.group.me.as_ref().and_then(|m| m.username.as_deref()) {
+        Some(username) if in_group => commands::strip_mention(&incoming.text, username),
+        _ => incoming.text,
+    };
+    if text.is_empty() && incoming.attachments.is_empty() && msg.replied().is_none() {
+        return;
+    }
+
+    // A background agent may be waiting on an answer from this conversation
+    if incoming.attachments.is_empty() && !text.is_empty() && ask::answer_text(&instance.origin(dest), &text) {
         return;
     }
 
+    // Say who's talking in groups, and what they're replying to
+    if in_group {
+        text = format!("{user_name}: {text}");
+    }
+    if let Some(quoted) = reply_context(&msg, instance.group.me.as_ref()) {
+        text = format!("{quoted}\n{text}");
+    }
+
+    // Attachments first, then the text — the API works best that way round
+    let mut content = incoming.attachments;
+    content.push(ContentBlock::Text { text: text.clone() });
+
     let preview = text.lines().next().unwrap_or("").to_string();
     eprintln!("{} [{}] {} {}", "📩".dimmed(), instance.name.yellow(), user_name.cyan(), preview.dimmed());
-    if let Some(d) = dash {
+    if let Some(d) = sh.dash {
         broadcast_log(d, format!("[{}] 📩 {} {}", instance.name, user_name, preview));
     }
 
-    let _ = instance.bot.send_typing(chat_id).await;
+    respond(instance, dest, &text, content, incoming.from_voice, sh).await;
+}
+
+/// Run the agent (or Claude Code) on a request and send the reply
+async fn respond(instance: &mut BotInstance, dest: ChatRef, text: &str, content: Vec<ContentBlock>, from_voice: bool, sh: &Shared<'_>) {
+    let _ = instance.bot.send_typing(dest).await;
 
     // Route based on bot type
-    if instance.bot_type == "dev" {
+    let reply = if instance.bot_type == "dev" {
         // Dev bot — use internal agent loop with dev-enhanced system prompt and working directory
         // Falls back to Claude Code CLI if `claude auth` is configured
-        if claude_code_available().await {
-            handle_dev_message(instance, chat_id, &text, api_key, cost_tracker, dash).await;
+        // A sandboxed bot always takes the agent loop: the CLI can't be confined
+        if instance.sandbox.is_none() && claude_code_available().await {
+            handle_dev_message(instance, dest, text, sh).await
         } else {
             // Use standard agent loop but with dev-oriented setup
-            handle_standard_message(instance, chat_id, &text, api_key, tool_defs, brave_api_key, github_token, cost_tracker, dash).await;
+            handle_standard_message(instance, dest, content, sh).await
         }
     } else {
         // Standard bot — use internal agent loop
-        handle_standard_message(instance, chat_id, &text, api_key, tool_defs, brave_api_key, github_token, cost_tracker, dash).await;
+        handle_standard_message(instance, dest, content, sh).await
+    };
+
+    let wants_voice = match instance.voice_reply.as_str() {
+        "always" => true,
+        "mirror" => from_voice,
+        _ => false,
+    };
+    if wants_voice && let Some(reply) = reply {
+        send_voice_reply(instance, dest, &reply).await;
+    }
+}
+
+/// Callback data for the "Continue" button shown at the turn limit
+const CONTINUE_CALLBACK: &str = "turn:continue";
+
+/// Button presses that arrive while no turn is running
+async fn handle_callback(instance: &mut BotInstance, cb: CallbackQuery, sh: &Shared<'_>) {
+    let data = cb.data.as_deref().unwrap_or("");
+    let Some(msg) = cb.message else {
+        let _ = instance.bot.answer_callback(&cb.id, None).await;
+        return;
+    };
+    if !instance.group.permits(&instance.bot, &msg.chat, Some(&cb.from)) {
+        let _ = instance.bot.answer_callback(&cb.id, Some("You're not allowed to use this bot.")).await;
+        return;
+    }
+
+    if let Some(rest) = data.strip_prefix(ask::CALLBACK_PREFIX) {
+        // From a background agent, or one suspended across a restart;
+        // questions from a chat's own turn are answered mid-turn
+        let id = rest.split(':').next().unwrap_or("");
+        // A live agent updates the message itself
+        let suspended = !ask::is_live(id);
+        match ask::answer_callback(data) {
+            Some(answer) => {
+                let _ = instance.bot.answer_callback(&cb.id, None).await;
+                if suspended {
+                    let question = msg.text.as_deref().unwrap_or("❓");
+                    let _ = instance.bot.edit_message(msg.chat.id, msg.message_id, &format!("{question}\n\n✅ {answer} — resuming")).await;
+                }
+            }
+            None => {
+                let _ = instance.bot.answer_callback(&cb.id, Some("This question has expired.")).await;
+            }
+        }
+    } else if data == CONTINUE_CALLBACK {
+        let _ = instance.bot.answer_callback(&cb.id, None).await;
+        let _ = instance.bot.edit_message(msg.chat.id, msg.message_id, "⏸ Turn limit reached — continuing…").await;
+        let dest = ChatRef {
+            chat_id: msg.chat.id,
+            thread_id: if msg.is_topic_message { msg.message_thread_id } else { None },
+            reply_to: None,
+        };
+        let text = "Continue where you left off.";
+        respond(instance, dest, text, vec![ContentBlock::Text { text: text.into() }], false, sh).await;
+    } else {
+        let _ = instance.bot.answer_callback(&cb.id, None).await;
+    }
+}
+
+/// Longest quoted reply we pass along
+const MAX_QUOTE_CHARS: usize = 1000;
+
+/// "[Replying to X: …]" for messages that reply to (or quote) another message
+fn reply_context(msg: &TgMessage, me: Option<&User>) -> Option<String> {
+    let replied = msg.replied()?;
+    let who = match (&replied.from, me) {
+        (Some(u), Some(me)) if u.id == me.id => "your earlier message".to_string(),
+        (Some(u), _) => u.display_name(),
+        (None, _) => "a message".to_string(),
+    };
+    let quoted = msg
+        .quote
+        .as_ref()
+        .map(|q| q.text.as_str())
+        .or(replied.body())
+        .unwrap_or("[media]");
+    let quoted: String = quoted.chars().take(MAX_QUOTE_CHARS).collect();
+    Some(format!("[Replying to {who}: \"{quoted}\"]"))
+}
+
+/// Diffs beyond this are left out of /changes — a few Telegram messages' worth
+const CHANGES_REPORT_CHARS: usize = 12_000;
+
+/// Answer a slash command for this chat
+fn handle_command(instance: &mut BotInstance, dest: ChatRef, cmd: &BotCommand, config: &Config) -> String {
+    let available_tasks = instance.available_tasks();
+    let default_model = instance.model.clone();
+    let chat = instance.chat(dest);
+    let arg = cmd.args.to_lowercase();
+
+    let reply = match cmd.name.as_str() {
+        "start" | "help" => commands::help_text(),
+        "new" if arg == "compact" => {
+            chat.context.compact(6);
+            match chat.context.save() {
+                Ok(()) => "🗜️ Conversation compacted to a summary plus the last few messages.".to_string(),
+                Err(e) => format!("❌ Failed to save: {e}"),
+            }
+        }
+        "new" => match chat.context.clear().and_then(|()| CheckpointStore::open(chat.checkpoints_dir.clone()).clear()) {
+            Ok(()) => {
+                // Dropping the old table kills whatever it was running
+                chat.processes = ProcessTable::new();
+                chat.shell = ShellSession::new();
+                "🆕 Started a fresh conversation.".to_string()
+            }
+            Err(e) => format!("❌ Failed to clear: {e}"),
+        },
+        "undo" => match if arg.is_empty() { Some(1) } else { arg.parse::<usize>().ok() } {
+            Some(n) => match CheckpointStore::open(chat.checkpoints_dir.clone()).undo(n) {
+                Ok(done) => format!("↩️ Undid {n} turn(s) of file changes:\n{}", done.join("\n")),
+                Err(e) => format!("❌ {e}"),
+            },
+            None => "Use /undo, or /undo N to undo the last N turns.".to_string(),
+        },
+        "changes" => CheckpointStore::open(chat.checkpoints_dir.clone()).report(CHANGES_REPORT_CHARS),
+        // A running turn catches /stop itself — reaching here means we're idle
+        "stop" => "Nothing is running.".to_string(),
+        "model" => match arg.as_str() {
+            "" => format!(
+                "Model: `{}`{}\nUse /model quick, standard, complex or default.",
+                chat.settings.model.as_deref().unwrap_or(&default_model),
+                if chat.settings.model.is_some() { " (override)" } else { "" }
+            ),
+            "default" => {
+                chat.settings.model = None;
+                format!("Model reset to `{default_model}`.")
+            }
+            tier => match model_for_tier(config, tier) {
+                Some(model) => {
+                    chat.settings.model = Some(model.clone());
+                    format!("Model for this chat: `{model}`")
+                }
+                // Full model ids pass through as-is
+                None if tier.starts_with("claude-") => {
+                    chat.settings.model = Some(cmd.args.to_string());
+                    format!("Model for this chat: `{}`", cmd.args)
+                }
+                None => format!("Unknown model tier: {tier}. Use quick, standard, complex or default."),
+            },
+        },
+        "thinking" => match arg.as_str() {
+            "" => format!(
+                "Thinking: {}\nUse /thinking off, low, medium, high or default.",
+                chat.settings.thinking.unwrap_or_default().as_str()
+            ),
+            "default" => {
+                chat.settings.thinking = None;
+                "Thinking reset to off.".to_string()
+            }
+            level => match Thinking::parse(level) {
+                Some(t) => {
+                    chat.settings.thinking = Some(t);
+                    format!("Thinking for this chat: {}", t.as_str())
+                }
+                None => format!("Unknown thinking level: {level}. Use off, low, medium, high or default."),
+            },
+        },
+        "task" => match cmd.args {
+            "" if available_tasks.is_empty() => "No tasks available.".to_string(),
+            "" => {
+                let mut out = String::from("Tasks (pick with /task <name>, clear with /task none):\n");
+                for t in &available_tasks {
+                    let marker = if chat.settings.task.as_deref() == Some(t) { "▶️" } else { "•" };
+                    out.push_str(&format!("{marker} {t}\n"));
+                }
+                out
+            }
+            "none" => {
+                chat.settings.task = None;
+                "Active task cleared.".to_string()
+            }
+            task if available_tasks.iter().any(|t| t == task) => {
+                chat.settings.task = Some(task.to_string());
+                format!("Active task: {task}")
+            }
+            task => format!("{task} isn't in this bot's task scope. Send /task to list them."),
+        },
+        "dryrun" => match arg.as_str() {
+            "" => format!(
+                "Dry run: {}\nUse /dryrun on or off.",
+                if chat.settings.dry_run { "on — changes are previewed, not made" } else { "off" }
+            ),
+            "on" => {
+                chat.settings.dry_run = true;
+                "🧪 Dry run on: file, git, GitHub, storage and bot changes are previewed, not made.".to_string()
+            }
+            "off" => {
+                chat.settings.dry_run = false;
+                "Dry run off.".to_string()
+            }
+            other => format!("Unknown setting: {other}. Use /dryrun on or off."),
+        },
+        "cost" => format!(
+            "💰 This chat: ${:.4} ({} in / {} out tokens)\n📚 Context: {} messages, ~{}k tokens",
+            chat.settings.cost.estimated_cost_usd,
+            chat.settings.cost.input_tokens,
+            chat.settings.cost.output_tokens,
+            chat.context.messages.len(),
+            chat.context.estimated_tokens() / 1000,
+        ),
+        "status" => format!(
+            "🤖 Model: `{}`\n🧠 Thinking: {}\n📋 Task: {}\n🧪 Dry run: {}\n📚 Context: {} messages, ~{}k tokens\n💰 Cost: ${:.4}",
+            chat.settings.model.as_deref().unwrap_or(&default_model),
+            chat.settings.thinking.unwrap_or_default().as_str(),
+            chat.settings.task.as_deref().unwrap_or("none"),
+            if chat.settings.dry_run { "on" } else { "off" },
+            chat.context.messages.len(),
+            chat.context.estimated_tokens() / 1000,
+            chat.settings.cost.estimated_cost_usd,
+        ),
+        other => format!("Unknown command: /{other}"),
+    };
+
+    chat.save_settings();
+    reply
+}
+
+/// Owned handles for agents that run outside a chat turn (cron jobs, resumed runs)
+#[derive(Clone)]
+struct Background {
+    api_key: String,
+    tool_defs: Vec<ToolDefinition>,
+    custom_tools: Vec<CustomTool>,
+    brave_api_key: Option<String>,
+    github_token: Option<String>,
+    cost_tracker: Arc<RwLock<CostTracker>>,
+    max_turns: u32,
+    max_tokens: u32,
+    dash: Option<DashboardState>,
+    /// `agents/` — one directory per run, shared with the orchestrator
+    runs_dir: PathBuf,
+}
+
+impl Background {
+    fn agent(&self, context: ContextManager, model: &str, system_prompt: &str, thinking: Thinking) -> AgentLoop {
+        AgentLoop::new(
+            AnthropicClient::new(self.api_key.clone()),
+            context,
+            model.to_string(),
+            system_prompt.to_string(),
+            self.tool_defs.clone(),
+            self.max_turns,
+            self.max_tokens,
+            thinking,
+            self.brave_api_key.clone(),
+            self.github_token.clone(),
+        )
+        .with_custom_tools(self.custom_tools.clone())
+    }
+
+    fn log(&self, line: String) {
+        eprintln!("  {line}");
+        if let Some(d) = &self.dash {
+            broadcast_log(d, line);
+        }
+    }
+
+    /// Run a cron `AgentTask` in its own run directory, so it can suspend in
+    /// `ask_user`. Questions go to the job's origin, or the dashboard.
+    fn run_cron(&self, job: &CronJob, message: &str, model: &str, system_prompt: &str) {
+        let slug: String = job
+            .name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .take(20)
+            .collect();
+        let run_id = format!("cron-{}-{slug}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+        let context = ContextManager::with_persistence(self.runs_dir.join(&run_id).join("conversation.json"));
+        let origin = job.origin.clone().or_else(|| self.dash.is_some().then_some(Origin::Dashboard));
+
+        let mut agent = self
+            .agent(context, model, system_prompt, Thinking::Off)
+            .with_run_id(&run_id)
+            .with_tool_policy(job.tools.clone())
+            .with_dry_run(job.dry_run);
+        if let Some(origin) = origin.clone() {
+            agent = self.with_origin(agent, origin);
+        }
+        let (bg, label, model, message) = (self.clone(), job.name.clone(), model.to_string(), message.to_string());
+        tokio::spawn(async move {
+            let result = agent.run_turn(&message).await;
+            bg.finish(&run_id, &label, &model, origin.as_ref(), result).await;
+        });
+    }
+
+    /// Continue a background run that was suspended in `ask_user` when the process
+    /// stopped. Sub-agents are resumed by their orchestrator instead.
+    fn resume(&self, q: Question, result: String) {
+        let Some(run) = q.run else { return };
+        self.log(format!("▶️ Resuming {} — {result}", run.run_id));
+        let context = ContextManager::with_persistence(run.context_path.clone());
+        let mut agent = self
+            .agent(context, &run.model, &run.system_prompt, run.thinking)
+            .with_run_id(&run.run_id)
+            .with_tool_policy(run.tools.clone())
+            .with_dry_run(run.dry_run);
+        if let Some(storage) = run.storage {
+            agent = agent.with_storage(storage);
+        }
+        if let Some(worktree) = run.worktree {
+            agent = agent.with_worktree(worktree);
+        }
+        agent = self.with_origin(agent, q.origin.clone());
+        let bg = self.clone();
+        tokio::spawn(async move {
+            let outcome = agent.resume(&run.tool_use_id, &result).await;
+            bg.finish(&run.run_id, &run.run_id, &run.model, Some(&q.origin), outcome).await;
+        });
+    }
+
+    /// Attach the origin, plus the chat itself for Telegram (send_file etc.)
+    fn with_origin(&self, agent: AgentLoop, origin: Origin) -> AgentLoop {
+        let agent = agent.with_origin(origin.clone());
+        match origin {
+            Origin::Telegram { bot: name, chat_id, thread_id } => match ask::bot(&name) {
+                Some(bot) => agent.with_chat(ChatTarget { bot, name, chat_id, thread_id }),
+                None => agent,
+            },
+            _ => agent,
+        }
+    }
+
+    /// Save and report a finished background run — to its chat, if it came from one
+    async fn finish(&self, run_id: &str, label: &str, model: &str, origin: Option<&Origin>, result: Result<TurnResult>) {
+        let report = match result {
+            Ok(result) => {
+                let _ = std::fs::write(self.runs_dir.join(run_id).join("output.md"), &result.text);
+                self.cost_tracker.write().await.record(
+                    model,
+                    Some(label),
+                    result.usage.input_tokens,
+                    result.usage.output_tokens,
+                    0,
+                    0,
+                );
+                let preview: String = result.text.chars().take(200).collect();
+                self.log(format!("✅ {label}: {preview}"));
+                format!("✅ *{label}* finished\n\n{}", result.text)
+            }
+            Err(e) => {
+                self.log(format!("❌ {label}: {e}"));
+                format!("❌ *{label}* failed: {e}")
+            }
+        };
+        if let Some(Origin::Telegram { bot, chat_id, thread_id }) = origin
+            && let Some(bot) = ask::bot(bot)
+        {
+            let dest = ChatRef { chat_id: *chat_id, thread_id: *thread_id, reply_to: None };
+            if let Err(e) = bot.send_message(dest, &report).await {
+                tracing::error!("Failed to report {run_id}: {e}");
+            }
+        }
+    }
+}
+
+/// Pass on what the manager's sub-agents have to say: to the chat that
+/// started them, and to the dashboard log. Refreshes the dashboard's agents table.
+async fn deliver_reports(manager: &Manager, dash: Option<&DashboardState>) {
+    for report in manager.poll().await {
+        let headline: String = report.text.lines().next().unwrap_or_default().chars().take(200).collect();
+        eprintln!("  {headline}");
+        if let Some(d) = dash {
+            broadcast_log(d, headline);
+        }
+        if let Some(Origin::Telegram { bot, chat_id, thread_id }) = &report.origin
+            && let Some(bot) = ask::bot(bot)
+        {
+            let dest = ChatRef { chat_id: *chat_id, thread_id: *thread_id, reply_to: None };
+            if let Err(e) = bot.send_message(dest, &report.text).await {
+                tracing::error!("Failed to deliver a sub-agent report: {e}");
+            }
+        }
+    }
+    if let Some(d) = dash {
+        *d.agents.write().await = manager.agent_infos().await;
+    }
+}
+
+/// Resolve a model tier name to the configured model id
+fn model_for_tier(config: &Config, tier: &str) -> Option<String> {
+    match tier {
+        "quick" => Some(config.models.quick.clone()),
+        "standard" => Some(config.models.standard.clone()),
+        "complex" => Some(config.models.complex.clone()),
+        "manager" => Some(config.models.manager.clone()),
+        _ => None,
+    }
+}
+
+/// How often a running turn checks Telegram for /stop (long-poll seconds)
+const STOP_POLL_SECS: u32 = 10;
+
+/// What a running turn does with an update that arrives mid-turn
+enum Intercept {
+    /// /stop for this conversation
+    Stop,
+    /// Consumed already (an answer to `ask_user`)
+    Handled,
+    /// Handle once the turn is over
+    Queue,
+}
+
+/// Drive a turn while watching the bot's updates for stop requests and
+/// answers to questions. Other updates are queued in `pending`. Returns `None`
+/// if the turn was stopped (the turn future is dropped, which cancels it).
+async fn run_stoppable<F: Future>(
+    bot: &TelegramBot,
+    offset: &mut i64,
+    pending: &mut VecDeque<Update>,
+    intercept: impl Fn(&Update) -> Intercept,
+    turn: F,
+) -> Option<F::Output> {
+    tokio::pin!(turn);
+    loop {
+        let poll = async {
+            match bot.get_updates(*offset, STOP_POLL_SECS).await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    tracing::debug!("Poll during turn failed: {e}");
+                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                    Vec::new()
+                }
+            }
+        };
+        tokio::select! {
+            out = &mut turn => return Some(out),
+            updates = poll => {
+                let mut stop = false;
+                for update in updates {
+                    *offset = update.update_id + 1;
+                    match intercept(&update) {
+                        Intercept::Stop => stop = true,
+                        Intercept::Handled => {}
+                        Intercept::Queue => pending.push_back(update),
+                    }
+                }
+                if stop {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Sort an update that arrives while a turn runs in the conversation at `dest`
+fn intercept_for(bot: &TelegramBot, group: &GroupPolicy, update: &Update, origin: &ask::Origin) -> Intercept {
+    // Button answers to ask_user
+    if let Some(cb) = &update.callback_query {
+        let permitted = cb.message.as_ref().is_some_and(|m| group.permits(bot, &m.chat, Some(&cb.from)));
+        if let Some(data) = cb.data.as_deref()
+            && data.starts_with(ask::CALLBACK_PREFIX)
+            && permitted
+            && ask::answer_callback(data).is_some()
+        {
+            let (bot, id) = (bot.clone(), cb.id.clone());
+            tokio::spawn(async move {
+                let _ = bot.answer_callback(&id, None).await;
+            });
+            return Intercept::Handled;
+        }
+        return Intercept::Queue;
+    }
+
+    let Some(msg) = &update.message else { return Intercept::Queue };
+    let thread_id = if msg.is_topic_message { msg.message_thread_id } else { None };
+    let ask::Origin::Telegram { chat_id, thread_id: dest_thread, .. } = origin else { return Intercept::Queue };
+    if msg.chat.id != *chat_id || thread_id != *dest_thread || !group.permits(bot, &msg.chat, msg.from.as_ref()) {
+        return Intercept::Queue;
+    }
+    let Some(text) = msg.text.as_deref() else { return Intercept::Queue };
+
+    if let Some(cmd) = commands::parse(text) {
+        return if cmd.name == "stop" && group.is_for_us(&cmd) { Intercept::Stop } else { Intercept::Queue };
+    }
+
+    // Typed answers to ask_user (in groups, only when addressed to us)
+    if msg.chat.is_group() && !group.triggered_by(msg) {
+        return Intercept::Queue;
+    }
+    let answer = match group.me.as_ref().and_then(|m| m.username.as_deref()) {
+        Some(username) => commands::strip_mention(text, username),
+        None => text.to_string(),
+    };
+    if ask::answer_text(origin, &answer) {
+        Intercept::Handled
+    } else {
+        Intercept::Queue
+    }
+}
+
+/// Longest reply we'll read out — voice notes of whole reports aren't useful
+const MAX_VOICE_REPLY_CHARS: usize = 2500;
+
+/// Speak a reply with the TTS backend and send it as a voice note
+async fn send_voice_reply(instance: &BotInstance, dest: ChatRef, reply: &str) {
+    let Some(engine) = instance.voice.as_ref().filter(|v| v.can_speak()) else {
+        return;
+    };
+
+    // Read the rendered text, not the Markdown markup
+    let plain = format::html_to_plain(&format::markdown_to_html(reply));
+    let spoken: String = if plain.chars().count() > MAX_VOICE_REPLY_CHARS {
+        let cut: String = plain.chars().take(MAX_VOICE_REPLY_CHARS).collect();
+        let end = cut.rfind(['.', '!', '?', '\n']).map_or(cut.len(), |p| p + 1);
+        cut[..end].to_string()
+    } else {
+        plain
+    };
+
+    match engine.tts(&spoken).await {
+        Ok(path) => {
+            if let Err(e) = instance.bot.send_voice(dest, &path, None).await {
+                tracing::error!("[{}] Failed to send voice reply: {e}", instance.name);
+            }
+        }
+        Err(e) => tracing::error!("[{}] TTS failed: {e}", instance.name),
     }
 }
 
@@ -202,32 +965,21 @@ async fn claude_code_available() -> bool {
 }
 
 /// Handle message via Claude Code (dev bot)
-async fn handle_dev_message(
-    instance: &mut BotInstance,
-    chat_id: i64,
-    text: &str,
-    api_key: &str,
-    cost_tracker: &Arc<RwLock<CostTracker>>,
-    dash: Option<&DashboardState>,
-) {
+async fn handle_dev_message(instance: &mut BotInstance, dest: ChatRef, text: &str, sh: &Shared<'_>) -> Option<String> {
+    let Shared { api_key, dash, .. } = *sh;
     let working_dir = match &instance.working_directory {
         Some(dir) => dir.clone(),
         None => {
-            let _ = instance.bot.send_message(chat_id,
+            let _ = instance.bot.send_message(dest,
                 "❌ Dev bot has no working_directory configured. Set it in config.toml.").await;
-            return;
+            return None;
         }
     };
 
     // Build context-aware prompt with system prompt + conversation history
-    let chats_dir = instance.chats_dir.clone();
-    let chat = instance.chat_states.entry(chat_id).or_insert_with(|| {
-        ChatState {
-            context: ContextManager::with_persistence(
-                chats_dir.join(format!("{chat_id}.json")),
-            ),
-        }
-    });
+    let default_model = instance.model.clone();
+    let chat = instance.chat(dest);
+    let model = chat.settings.model.clone().unwrap_or(default_model);
 
     // Save user message to history
     chat.context.add_user_message(text);
@@ -245,109 +997,106 @@ async fn handle_dev_message(
     eprintln!("{} [{}] Delegating to Claude Code → {}", "🔧".dimmed(), instance.name.yellow(), working_dir.dimmed());
 
     // Send "working on it" indicator
-    let _ = instance.bot.send_message(chat_id,
+    let _ = instance.bot.send_message(dest,
         &format!("🔧 Working on it via Claude Code...\n📁 `{}`", working_dir)).await;
 
-    match tools::claude_code::run_dev_task(
-        &full_prompt,
-        &working_dir,
-        &instance.model,
-        instance.max_budget_usd,
-        instance.dev_timeout_seconds,
-        Some(api_key),
-    ).await {
-        Ok(result) => {
+    let origin = instance.origin(dest);
+    let outcome = run_stoppable(
+        &instance.bot,
+        &mut instance.offset,
+        &mut instance.pending,
+        |u| intercept_for(&instance.bot, &instance.group, u, &origin),
+        tools::claude_code::run_dev_task(
+            &full_prompt,
+            &working_dir,
+            &model,
+            instance.max_budget_usd,
+            instance.dev_timeout_seconds,
+            Some(api_key),
+        ),
+    )
+    .await;
+
+    let name = instance.name.clone();
+    let bot = instance.bot.clone();
+    let chat = instance.chat(dest);
+    match outcome {
+        None => {
+            let _ = bot.send_message(dest, "⏹ Stopped.").await;
+            chat.context.add_assistant_message(vec![ContentBlock::Text { text: "[Stopped by user]".into() }]);
+            let _ = chat.context.save();
+            None
+        }
+        Some(Ok(result)) => {
             let reply = if result.output.is_empty() {
                 "[Claude Code returned no output]".to_string()
             } else {
                 result.output
             };
 
-            // Split long replies into chunks (Telegram 4096 char limit)
-            let chunks = split_telegram_message(&reply, 4000);
-            for chunk in &chunks {
-                if let Err(e) = instance.bot.send_message(chat_id, chunk).await {
-                    tracing::error!("[{}] Failed to send reply chunk: {e}", instance.name);
-                    break;
-                }
+            // send_message splits long replies into Telegram-sized chunks
+            if let Err(e) = bot.send_message(dest, &reply).await {
+                tracing::error!("[{name}] Failed to send reply: {e}");
             }
 
             // Save assistant response to history
-            chat.context.add_assistant_message(vec![crate::types::ContentBlock::Text { text: reply.clone() }]);
+            chat.context.add_assistant_message(vec![ContentBlock::Text { text: reply.clone() }]);
             let _ = chat.context.save();
 
             if let Some(d) = dash {
-                broadcast_log(d, format!("[{}] ✅ Claude Code task complete ({} chunks)", instance.name, chunks.len()));
+                broadcast_log(d, format!("[{name}] ✅ Claude Code task complete ({} chars)", reply.len()));
             }
+            Some(reply)
         }
-        Err(e) => {
-            tracing::error!("[{}] Claude Code error: {e}", instance.name);
+        Some(Err(e)) => {
+            tracing::error!("[{name}] Claude Code error: {e}");
             if let Some(d) = dash {
-                broadcast_log(d, format!("[{}] ❌ Claude Code error: {e}", instance.name));
+                broadcast_log(d, format!("[{name}] ❌ Claude Code error: {e}"));
             }
-            let _ = instance.bot.send_message(chat_id, &format!("❌ Claude Code error: {e}")).await;
+            let _ = bot.send_message(dest, &format!("❌ Claude Code error: {e}")).await;
 
             // Save error to history
-            chat.context.add_assistant_message(vec![crate::types::ContentBlock::Text {
+            chat.context.add_assistant_message(vec![ContentBlock::Text {
                 text: format!("[Error: {e}]"),
             }]);
             let _ = chat.context.save();
+            None
         }
     }
 }
 
-/// Split a message into Telegram-safe chunks
-fn split_telegram_message(text: &str, max_len: usize) -> Vec<String> {
-    if text.len() <= max_len {
-        return vec![text.to_string()];
-    }
-
-    let mut chunks = Vec::new();
-    let mut remaining = text;
-
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining.to_string());
-            break;
-        }
-
-        // Try to split at a newline
-        let split_at = remaining[..max_len]
-            .rfind('\n')
-            .unwrap_or(max_len);
-
-        chunks.push(remaining[..split_at].to_string());
-        remaining = &remaining[split_at..].trim_start_matches('\n');
-    }
-
-    chunks
-}
-
 /// Handle message via internal agent loop (standard bot)
-async fn handle_standard_message(
-    instance: &mut BotInstance,
-    chat_id: i64,
-    text: &str,
-    api_key: &str,
-    tool_defs: &[crate::types::ToolDefinition],
-    brave_api_key: &Option<String>,
-    github_token: &Option<String>,
-    cost_tracker: &Arc<RwLock<CostTracker>>,
-    dash: Option<&DashboardState>,
-) {
-    let storage = instance.task_storage();
-    let chats_dir = instance.chats_dir.clone();
-
-    let chat = instance.chat_states.entry(chat_id).or_insert_with(|| {
-        ChatState {
-            context: ContextManager::with_persistence(
-                chats_dir.join(format!("{chat_id}.json")),
-            ),
-        }
-    });
-
+async fn handle_standard_message(instance: &mut BotInstance, dest: ChatRef, content: Vec<ContentBlock>, sh: &Shared<'_>) -> Option<String> {
+    let Shared { api_key, tool_defs, custom_tools, brave_api_key, github_token, cost_tracker, config, dash } = *sh;
+    let chat = instance.chat(dest);
+    let model = chat.settings.model.clone();
+    let thinking = chat.settings.thinking.unwrap_or_default();
+    let task = chat.settings.task.clone();
+    let dry_run = chat.settings.dry_run;
+    let checkpoints = CheckpointStore::open(chat.checkpoints_dir.clone());
+    let processes = chat.processes.clone();
+    let shell = chat.shell.clone();
     let mut context = std::mem::replace(&mut chat.context, ContextManager::new());
 
+    let model = model.unwrap_or_else(|| instance.model.clone());
+    let storage = instance.task_storage(task.as_deref());
+    // Shared bots fail closed: no sandbox, no turn
+    let sandbox = match &instance.sandbox {
+        Some(cfg) => {
+            let writable = instance.working_directory.iter().map(PathBuf::from).chain(storage.iter().map(|s| s.root.clone()));
+            match Sandbox::new(cfg, writable) {
+                Ok(sandbox) => Some(sandbox),
+                Err(e) => {
+                    tracing::error!("[{}] Sandbox unavailable: {e}", instance.name);
+                    instance.chat(dest).context = context;
+                    let _ = instance.bot.send_message(dest, &format!("❌ Sandbox unavailable: {e}")).await;
+                    return None;
+                }
+            }
+        }
+        None => None,
+    };
+
     // Auto-compact if conversation is getting too long (by count or tokens)
     let max_history = instance.max_turns as usize * 2;
     let est_tokens = context.estimated_tokens();
@@ -359,29 +1108,53 @@ async fn handle_standard_message(
     }
 
     // For dev bots, enhance the system prompt with working directory context
-    let effective_prompt = if instance.bot_type == "dev" {
+    let mut effective_prompt = if instance.bot_type == "dev" {
         let wd = instance.working_directory.as_deref().unwrap_or(".");
-        format!(
+        let map = match config.tools.repo_map_tokens {
+            0 => None,
+            budget => crate::repomap::repo_map(Path::new(wd), budget)
+                .map_err(|e| tracing::warn!("Repo map for {wd} failed: {e}"))
+                .ok(),
+        };
+        let orient = if map.is_some() {
+            "The repository map below outlines the project; use repo_map, grep and glob to look closer before reading files."
+        } else {
+            "Start by examining the project directory if you haven't already."
+        };
+        let mut prompt = format!(
             "{}\n\n## Dev Bot Context\nYou are a software development bot. Your project directory is: {}\n\
             Always use shell, read_file, write_file, edit_file tools to actually write and modify code.\n\
             When asked to build something, DO IT — write real code, create files, run commands.\n\
-            Start by examining the project directory if you haven't already.\n\
+            {orient}\n\
             Be hands-on and proactive. Show what you did, not what you could do.",
             instance.system_prompt, wd
-        )
+        );
+        if let Some(map) = map {
+            prompt.push_str(&format!("\n\n## Repository Map\n{map}"));
+        }
+        prompt
     } else {
         instance.system_prompt.clone()
     };
 
+    // The task picked with /task
+    if let Some(ref t) = task {
+        effective_prompt.push_str(&format!("\n\n## Active Task\nThis chat is working on task `{t}`."));
+        let mm = MemoryManager::new(MemoryManager::default_root());
+        if let Ok(notes) = mm.read_file(&format!("tasks/{t}.md")) {
+            effective_prompt.push_str(&format!("\n\n{notes}"));
+        }
+    }
+
     let mut agent = AgentLoop::new(
         AnthropicClient::new(api_key.to_string()),
         context,
-        instance.model.clone(),
+        model.clone(),
         effective_prompt,
         tool_defs.to_vec(),
         instance.max_turns,
         instance.max_tokens,
-        Thinking::Off,
+        thinking,
         brave_api_key.clone(),
         github_token.clone(),
     );
@@ -389,22 +1162,71 @@ async fn handle_standard_message(
     if let Some(s) = storage {
         agent = agent.with_storage(s);
     }
+    if config.tools.shell_sessions {
+        agent = agent.with_shell_session(shell);
+    }
+    // Sub-agents it spawns report back to this chat
+    if let Some(manager) = &instance.manager {
+        agent = agent.with_manager(manager.clone()).with_origin(instance.origin(dest));
+    }
+    agent = agent.with_custom_tools(custom_tools.to_vec());
+    if let Some(sandbox) = sandbox {
+        agent = agent.with_sandbox(sandbox);
+    }
+    agent = agent.with_tool_policy(instance.tool_policy.clone()).with_dry_run(dry_run);
+    agent = agent.with_file_checkpoints(checkpoints).with_processes(processes).with_chat(ChatTarget {
+        bot: instance.bot.clone(),
+        name: instance.name.clone(),
+        chat_id: dest.chat_id,
+        thread_id: dest.thread_id,
+    });
 
-    match agent.run_turn(text).await {
-        Ok(result) => {
+    // Kept so a stopped turn can be rewritten as just the request
+    let history_len = agent.context.messages.len();
+    let request = content.clone();
+
+    let origin = instance.origin(dest);
+    let outcome = run_stoppable(
+        &instance.bot,
+        &mut instance.offset,
+        &mut instance.pending,
+        |u| intercept_for(&instance.bot, &instance.group, u, &origin),
+        agent.run_turn_with_content(content),
+    )
+    .await;
+
+    let reply = match outcome {
+        None => {
+            // Drop any half-finished tool calls so the history stays valid
+            agent.context.messages.truncate(history_len);
+            agent.context.add_user_content(request);
+            agent.context.add_assistant_message(vec![ContentBlock::Text { text: "[Stopped by user]".into() }]);
+            let _ = agent.context.save();
+            let _ = instance.bot.send_message(dest, "⏹ Stopped.").await;
+            None
+        }
+        Some(Ok(result)) => {
             let reply = if result.text.is_empty() {
                 "[No response]".to_string()
             } else {
                 result.text
             };
 
-            let reply = if reply.len() > 4000 {
-                format!("{}...\n\n_(truncated)_", &reply[..4000])
+            // Out of tool rounds — offer to keep going rather than just stopping
+            let sent = if result.hit_turn_limit {
+                // A dry run's list of held-back actions goes out before the prompt
+                if dry_run
+                    && let Err(e) = instance.bot.send_message(dest, &reply).await
+                {
+                    tracing::error!("[{}] Failed to send reply: {e}", instance.name);
+                }
+                let prompt = format!("⏸ I've used all {} tool rounds for this message. Continue?", instance.max_turns);
+                let button = vec![("▶️ Continue".to_string(), CONTINUE_CALLBACK.to_string())];
+                instance.bot.send_keyboard(dest, &prompt, &[button]).await
             } else {
-                reply
+                instance.bot.send_message(dest, &reply).await
             };
-
-            if let Err(e) = instance.bot.send_message(chat_id, &reply).await {
+            if let Err(e) = sent {
                 tracing::error!("[{}] Failed to send reply: {e}", instance.name);
             }
 
@@ -416,18 +1238,25 @@ async fn handle_standard_message(
             }
 
             let mut ct = cost_tracker.write().await;
-            ct.record(&instance.model, Some(&instance.name), result.usage.input_tokens, result.usage.output_tokens, 0, 0);
+            ct.record(&model, Some(&instance.name), result.usage.input_tokens, result.usage.output_tokens, 0, 0);
+
+            let chat = instance.chat(dest);
+            chat.settings.cost.add(&model, result.usage.input_tokens, result.usage.output_tokens);
+            chat.save_settings();
+            (!result.hit_turn_limit).then_some(reply)
         }
-        Err(e) => {
+        Some(Err(e)) => {
             tracing::error!("[{}] Agent error: {e}", instance.name);
             if let Some(d) = dash {
                 broadcast_log(d, format!("[{}] ❌ Agent error: {e}", instance.name));
             }
-            let _ = instance.bot.send_message(chat_id, &format!("❌ Error: {e}")).await;
+            let _ = instance.bot.send_message(dest, &format!("❌ Error: {e}")).await;
+            None
         }
-    }
+    };
 
-    chat.context = agent.context;
+    instance.chat(dest).context = agent.context;
+    reply
 }
 
 /// Re-exec the current process with new args (Unix exec, replaces process)
@@ -454,8 +1283,18 @@ pub async fn run(config: &Config) -> Result<()> {
     // Cron
     let mut cron = CronScheduler::new(state_dir.join("cron-jobs.json"));
 
+    // Voice (STT for incoming voice notes, TTS for voice replies)
+    let voice = match VoiceEngine::from_config(config, auth.elevenlabs_api_key()) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Voice disabled: {e}");
+            None
+        }
+    };
+
     // Tool definitions
     let tool_defs = tools::builtin_tool_definitions(config.tools.web_enabled, config.github.is_some());
+    let custom_tools = tools::custom::load_custom_tools(config);
 
     // Dashboard
     let dash_state: Option<DashboardState> = if config.dashboard.enabled {
@@ -476,6 +1315,17 @@ pub async fn run(config: &Config) -> Result<()> {
         None
     };
 
+    // Sub-agents: spawned by the manager bot, and whatever the last process left running
+    let manager = Manager::new(config.clone(), api_key.clone(), brave_api_key.clone(), github_token.clone());
+    match manager.recover().await {
+        Ok(interrupted) if !interrupted.is_empty() => {
+            eprintln!("{} {} sub-agent(s) interrupted by the last shutdown ({})",
+                "♻️".dimmed(), interrupted.len(), config.agents.recovery);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Sub-agent recovery skipped: {e}"),
+    }
+
     // --- Manager bot ---
     let manager_token = auth.telegram_bot_token().context(
         "Telegram bot token not configured. Set TELEGRAM_BOT_TOKEN or add to credentials.toml",
@@ -487,14 +1337,15 @@ pub async fn run(config: &Config) -> Result<()> {
     let manager_chats_dir = state_dir.join("chats").join("manager");
     std::fs::create_dir_all(&manager_chats_dir)?;
 
-    let mut manager = BotInstance {
+    let manager_bot = BotInstance {
         name: "manager".to_string(),
         bot: TelegramBot::new(manager_token, manager_users),
         offset: 0,
+        pending: VecDeque::new(),
         chat_states: HashMap::new(),
         chats_dir: manager_chats_dir,
         model: config.models.standard.clone(),
-        system_prompt: "You are DevMan, a helpful coding assistant. Be concise and use tools proactively.".to_string(),
+        system_prompt: manager::MANAGER_SYSTEM_PROMPT.to_string(),
         task_scope: vec!["*".to_string()],
         memory_access: "full".to_string(),
         max_tokens: 4096,
@@ -503,6 +1354,22 @@ pub async fn run(config: &Config) -> Result<()> {
         working_directory: None,
         max_budget_usd: 1.0,
         dev_timeout_seconds: 600,
+        voice_reply: config.telegram.as_ref()
+            .map(|t| t.voice_reply.clone())
+            .unwrap_or_else(|| "off".to_string()),
+        voice: voice.clone(),
+        group: GroupPolicy {
+            allowed_groups: config.telegram.as_ref()
+                .map(|t| t.allowed_groups.clone())
+                .unwrap_or_default(),
+            trigger: config.telegram.as_ref()
+                .map(|t| t.group_trigger.clone())
+                .unwrap_or_else(|| "mention".to_string()),
+            me: None,
+        },
+        sandbox: None,
+        tool_policy: ToolPolicy::default(),
+        manager: Some(manager.clone()),
     };
 
     // --- Scoped bots ---
@@ -527,12 +1394,8 @@ pub async fn run(config: &Config) -> Result<()> {
         };
 
         // Resolve model tier to actual model name
-        let model = match sc.default_model.as_str() {
-            "quick" => config.models.quick.clone(),
-            "complex" => config.models.complex.clone(),
-            "manager" => config.models.manager.clone(),
-            "standard" | _ => config.models.standard.clone(),
-        };
+        let model = model_for_tier(config, &sc.default_model)
+            .unwrap_or_else(|| config.models.standard.clone());
 
         let bot_type_label = if sc.bot_type == "dev" { "dev 🔧" } else { "standard" };
 
@@ -540,6 +1403,7 @@ pub async fn run(config: &Config) -> Result<()> {
             name: sc.name.clone(),
             bot: TelegramBot::new(sc.bot_token.clone(), sc.allowed_users.clone()),
             offset: 0,
+            pending: VecDeque::new(),
             chat_states: HashMap::new(),
             chats_dir: bot_chats_dir,
             model,
@@ -552,9 +1416,24 @@ pub async fn run(config: &Config) -> Result<()> {
             working_directory: sc.working_directory.clone(),
             max_budget_usd: sc.max_budget_usd,
             dev_timeout_seconds: sc.dev_timeout_seconds,
+            voice_reply: sc.voice_reply.clone(),
+            voice: voice.clone(),
+            group: GroupPolicy {
+                allowed_groups: sc.allowed_groups.clone(),
+                trigger: sc.group_trigger.clone(),
+                me: None,
+            },
+            sandbox: sc.sandbox.clone(),
+            tool_policy: sc.tools.clone(),
+            manager: None,
         });
 
         eprintln!("{} Scoped bot '{}' [{}] → tasks: {:?}", "🤖".dimmed(), sc.name.cyan(), bot_type_label, sc.tasks);
+        if let Some(sandbox) = &sc.sandbox
+            && let Err(e) = Sandbox::new(sandbox, std::iter::empty())
+        {
+            eprintln!("{} Scoped bot '{}' sandbox unavailable — its shell commands will fail: {e}", "⚠️".yellow(), sc.name);
+        }
     }
 
     // Disk space check
@@ -577,7 +1456,36 @@ pub async fn run(config: &Config) -> Result<()> {
         format!("(manager + {} scoped bots)", scoped_bots.len()).dimmed());
     eprintln!("{}", "Press Ctrl+C to stop".dimmed());
 
+    let shared = Shared {
+        api_key: &api_key,
+        tool_defs: &tool_defs,
+        custom_tools: &custom_tools,
+        brave_api_key: &brave_api_key,
+        github_token: &github_token,
+        cost_tracker: &cost_tracker,
+        config,
+        dash: dash_state.as_ref(),
+    };
+
+    let background = Background {
+        api_key: api_key.clone(),
+        tool_defs: tool_defs.clone(),
+        custom_tools: custom_tools.clone(),
+        brave_api_key: brave_api_key.clone(),
+        github_token: github_token.clone(),
+        cost_tracker: cost_tracker.clone(),
+        max_turns: config.agents.max_turns,
+        max_tokens: config.agents.max_tokens,
+        dash: dash_state.clone(),
+        runs_dir: state_dir.join("agents"),
+    };
+    // Questions from turns that died with the last process can't be answered;
+    // those from suspended background runs are picked up by questions_tick
+    let questions = QuestionStore::open();
+    questions.discard_stale();
+
     let mut cron_tick = tokio::time::interval(std::time::Duration::from_secs(30));
+    let mut questions_tick = tokio::time::interval(std::time::Duration::from_secs(5));
     let mut poll_tick = tokio::time::interval(std::time::Duration::from_millis(500));
     let mut consecutive_poll_errors: u32 = 0;
     let model = config.models.standard.clone();
@@ -585,9 +1493,21 @@ pub async fn run(config: &Config) -> Result<()> {
 
     // Collect all bots into a single vec for unified polling
     let mut all_bots: Vec<BotInstance> = Vec::new();
-    all_bots.push(manager);
+    all_bots.push(manager_bot);
     all_bots.append(&mut scoped_bots);
 
+    // Slash command menu, and our own username for group @mentions
+    for bot in &mut all_bots {
+        ask::register_bot(&bot.name, bot.bot.clone());
+        if let Err(e) = bot.bot.set_my_commands(commands::COMMANDS).await {
+            tracing::warn!("[{}] Failed to register commands: {e}", bot.name);
+        }
+        match bot.bot.get_me().await {
+            Ok(me) => bot.group.me = Some(me),
+            Err(e) => tracing::warn!("[{}] getMe failed — group mentions won't be detected: {e}", bot.name),
+        }
+    }
+
     loop {
         // Check restart flag (set by assign_bot/remove_bot tools)
         if RESTART_REQUESTED.load(Ordering::SeqCst) {
@@ -617,28 +1537,26 @@ pub async fn run(config: &Config) -> Result<()> {
                             eprintln!("  {}", text.dimmed());
                         }
                         crate::cron::CronAction::AgentTask { message, model: task_model } => {
+                            // In the background, so a job waiting in ask_user doesn't stall polling
                             let m = task_model.as_deref().unwrap_or(&model);
-                            let client = AnthropicClient::new(api_key.clone());
-                            let context = ContextManager::new();
-                            let mut agent = AgentLoop::new(
-                                client, context, m.to_string(), system_prompt.clone(),
-                                tool_defs.clone(), config.agents.max_turns, config.agents.max_tokens,
-                                Thinking::Off, brave_api_key.clone(), github_token.clone(),
-                            );
-                            match agent.run_turn(message).await {
-                                Ok(result) => {
-                                    eprintln!("  Cron result: {}", &result.text[..result.text.len().min(200)]);
-                                    let mut ct = cost_tracker.write().await;
-                                    ct.record(m, Some(&job.name), result.usage.input_tokens, result.usage.output_tokens, 0, 0);
-                                }
-                                Err(e) => eprintln!("  Cron error: {e}"),
-                            }
+                            background.run_cron(&job, message, m, &system_prompt);
                         }
                     }
                 }
                 cron.save()?;
             }
 
+            // Suspended runs that have been answered or timed out
+            _ = questions_tick.tick() => {
+                for (q, result) in questions.take_ready(chrono::Utc::now()) {
+                    // Sub-agents carry on in the pool, which collects their results
+                    match &q.run {
+                        Some(run) if manager.resume(run, &result).await => {}
+                        _ => background.resume(q, result),
+                    }
+                }
+            }
+
             // Unified Telegram polling — round-robin all bots
             _ = poll_tick.tick() => {
                 // Exponential backoff on consecutive errors (network outage)
@@ -653,8 +1571,14 @@ pub async fn run(config: &Config) -> Result<()> {
                         Ok(updates) => {
                             for update in updates {
                                 bot.offset = update.update_id + 1;
+                                bot.pending.push_back(update);
+                            }
+                            // Includes anything that arrived while a turn was running
+                            while let Some(update) = bot.pending.pop_front() {
                                 if let Some(msg) = update.message {
-                                    handle_message(bot, msg, &api_key, &tool_defs, &brave_api_key, &github_token, &cost_tracker, config, dash_state.as_ref()).await;
+                                    handle_message(bot, msg, &shared).await;
+                                } else if let Some(cb) = update.callback_query {
+                                    handle_callback(bot, cb, &shared).await;
                                 }
                             }
                         }
@@ -665,6 +1589,8 @@ pub async fn run(config: &Config) -> Result<()> {
                     }
                 }
 
+                deliver_reports(&manager, dash_state.as_ref()).await;
+
                 if had_error {
                     consecutive_poll_errors += 1;
                     if consecutive_poll_errors == 1 {
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::ask::Origin;
+use crate::auth::AuthStore;
+use crate::config::Config;
+use crate::orchestrator::{Orchestrator, Priority, SubAgentMessage};
+use crate::tools::policy::ToolPolicy;
+use crate::workflow::{Workflow, WorkflowRun};
+
+/// Run a workflow file to completion, printing step progress
+pub async fn run(config: &Config, path: &Path) -> Result<()> {
+    let workflow = Workflow::load(path)?;
+    let auth = AuthStore::load()?;
+    let mut orchestrator = Orchestrator::new(
+        config.clone(),
+        auth.anthropic_api_key()?,
+        auth.brave_api_key(),
+        auth.github_token(),
+    );
+
+    let id = orchestrator.start_workflow(workflow, Priority::Manager, Some(Origin::Cli), ToolPolicy::default())?;
+    eprintln!("{}", format!("▶️ Started workflow {id}").bold());
+
+    let mut last = String::new();
+    loop {
+        while let Some(msg) = orchestrator.try_recv() {
+            if let SubAgentMessage::WorkflowDone { workflow_id, success, summary, .. } = msg
+                && workflow_id == id
+            {
+                println!("{summary}");
+                if !success {
+                    anyhow::bail!("workflow {id} failed");
+                }
+                return Ok(());
+            }
+        }
+        // Reprint the step list whenever it changes
+        if let Some(run) = orchestrator.workflows().iter().find(|w| w.id == id) {
+            let summary = run.summary();
+            if summary != last {
+                eprintln!("{}", summary.dimmed());
+                last = summary;
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
+
+/// Check a workflow file and print the order its steps can run in
+pub fn validate(path: &Path) -> Result<()> {
+    let workflow = Workflow::load(path)?;
+    println!("✅ {} — {} steps", workflow.name, workflow.steps.len());
+    for i in workflow.order()? {
+        let step = &workflow.steps[i];
+        let after = if step.depends_on.is_empty() {
+            String::new()
+        } else {
+            format!(" (after {})", step.depends_on.join(", "))
+        };
+        println!("  {} [{:?}]{after}", step.id, step.tier);
+    }
+    Ok(())
+}
+
+/// Recent workflow runs and how they went
+pub fn list() -> Result<()> {
+    let runs = WorkflowRun::list(&WorkflowRun::default_dir());
+    if runs.is_empty() {
+        println!("No workflows have run yet.");
+    }
+    for run in runs.iter().take(10) {
+        let state = match (run.is_finished(), run.succeeded()) {
+            (false, _) => "🔄",
+            (true, true) => "✅",
+            (true, false) => "❌",
+        };
+        println!("{state} {} — {} ({} steps)", run.id, run.workflow.name, run.steps.len());
+    }
+    Ok(())
+}
@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::tools::policy::ToolPolicy;
+
 /// Top-level configuration (from config.toml + CLI args)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -9,9 +11,11 @@ pub struct Config {
     pub models: ModelConfig,
     pub tools: ToolsConfig,
     pub agents: AgentPoolConfig,
+    pub verify: VerifyConfig,
     pub telegram: Option<TelegramConfig>,
     pub brave: Option<BraveConfig>,
     pub elevenlabs: Option<ElevenLabsConfig>,
+    pub voice: VoiceConfig,
     pub github: Option<GitHubConfig>,
     pub secrets: SecretsConfig,
     pub vault: VaultConfig,
@@ -25,9 +29,11 @@ impl Default for Config {
             models: ModelConfig::default(),
             tools: ToolsConfig::default(),
             agents: AgentPoolConfig::default(),
+            verify: VerifyConfig::default(),
             telegram: None,
             brave: None,
             elevenlabs: None,
+            voice: VoiceConfig::default(),
             github: None,
             secrets: SecretsConfig::default(),
             vault: VaultConfig::default(),
@@ -61,7 +67,11 @@ impl Default for ModelConfig {
 #[serde(default)]
 pub struct ToolsConfig {
     pub shell_confirm: bool,
+    /// Run `shell` in a long-lived bash per chat or agent, so cwd and environment carry over
+    pub shell_sessions: bool,
     pub web_enabled: bool,
+    /// Size of the repository map put in dev bots' prompts, in tokens (0 = none)
+    pub repo_map_tokens: usize,
     pub custom: Vec<CustomToolConfig>,
 }
 
@@ -69,7 +79,9 @@ impl Default for ToolsConfig {
     fn default() -> Self {
         Self {
             shell_confirm: false,
+            shell_sessions: false,
             web_enabled: true,
+            repo_map_tokens: 2000,
             custom: Vec::new(),
         }
     }
@@ -88,20 +100,55 @@ pub struct CustomToolConfig {
 #[serde(default)]
 pub struct AgentPoolConfig {
     pub max_concurrent: u32,
+    /// Sub-agents allowed on the same task at once — more would edit its files concurrently
+    pub max_per_task: u32,
     pub max_turns: u32,
     pub max_tokens: u32,
     pub recovery: String,
     pub checkpoint_interval: u32,
+    /// Give each dev sub-agent its own git worktree and branch, and what to do
+    /// with the branch when it finishes: "off" (default), "merge", "pr", "keep" or "discard"
+    pub worktree: String,
 }
 
 impl Default for AgentPoolConfig {
     fn default() -> Self {
         Self {
             max_concurrent: 5,
+            max_per_task: 1,
             max_turns: 50,
             max_tokens: 16384,
             recovery: "report".into(),
             checkpoint_interval: 1,
+            worktree: "off".into(),
+        }
+    }
+}
+
+/// A second agent that reviews sub-agent results before the manager sees them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyConfig {
+    /// Review results from these tiers: "quick", "standard", "complex"
+    pub tiers: Vec<String>,
+    /// Review results on these tasks, whatever the tier. ["*"] = all tasks.
+    pub tasks: Vec<String>,
+    /// Reviewer model (default: the complex tier's)
+    pub model: Option<String>,
+    /// Reviews before a result goes to the manager approved or not
+    pub max_rounds: u32,
+    /// Timeout for a dev bot's test_command in seconds
+    pub test_timeout: u64,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            tiers: Vec::new(),
+            tasks: Vec::new(),
+            model: None,
+            max_rounds: 2,
+            test_timeout: 600,
         }
     }
 }
@@ -113,6 +160,15 @@ pub struct TelegramConfig {
     /// Scoped bots — each bound to specific tasks
     #[serde(default)]
     pub bots: Vec<ScopedBotConfig>,
+    /// Voice replies from the manager bot: "off" (default), "mirror" or "always"
+    #[serde(default = "default_voice_reply")]
+    pub voice_reply: String,
+    /// Group chats where any member may talk to the manager bot
+    #[serde(default)]
+    pub allowed_groups: Vec<i64>,
+    /// When to answer in groups: "mention" (default — @mention or reply) or "all"
+    #[serde(default = "default_group_trigger")]
+    pub group_trigger: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,12 +199,63 @@ pub struct ScopedBotConfig {
     pub bot_type: String,
     /// Working directory for dev bots (project root). Required when bot_type = "dev".
     pub working_directory: Option<String>,
+    /// Test command the verifier runs in working_directory (e.g. "cargo test")
+    #[serde(default)]
+    pub test_command: Option<String>,
     /// Max budget per Claude Code invocation in USD. Default: 1.00
     #[serde(default = "default_dev_budget")]
     pub max_budget_usd: f64,
     /// Timeout per Claude Code invocation in seconds. Default: 600
     #[serde(default = "default_dev_timeout")]
     pub dev_timeout_seconds: u64,
+    /// Reply with a voice note as well as text: "off" (default), "mirror"
+    /// (when the user sent a voice message) or "always"
+    #[serde(default = "default_voice_reply")]
+    pub voice_reply: String,
+    /// Group chats where any member may talk to this bot
+    #[serde(default)]
+    pub allowed_groups: Vec<i64>,
+    /// When to answer in groups: "mention" (default — @mention or reply) or "all"
+    #[serde(default = "default_group_trigger")]
+    pub group_trigger: String,
+    /// Run this bot's shell, process and custom tools in a sandbox
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+    /// Which tools the bot gets: `allow`/`deny` globs and `read_only`
+    #[serde(default)]
+    pub tools: ToolPolicy,
+}
+
+/// Namespace sandbox for a bot's commands: read-only root, writable working
+/// directory and storage, no network unless allowed, and resource limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// "auto" (bubblewrap when installed, else unshare), "bwrap" or "unshare"
+    pub backend: String,
+    /// Allow network access
+    pub network: bool,
+    /// Address-space limit per process in MB (0 = none)
+    pub memory_mb: u64,
+    /// CPU-time limit per process in seconds (0 = none)
+    pub cpu_seconds: u64,
+    /// Longest any one command may run, in seconds
+    pub timeout_seconds: u64,
+    /// Writable directories besides the bot's working directory and storage
+    pub writable: Vec<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            backend: "auto".into(),
+            network: false,
+            memory_mb: 2048,
+            cpu_seconds: 600,
+            timeout_seconds: 900,
+            writable: Vec::new(),
+        }
+    }
 }
 
 fn default_bot_max_tokens() -> u32 {
@@ -171,6 +278,14 @@ fn default_dev_timeout() -> u64 {
     600
 }
 
+fn default_voice_reply() -> String {
+    "off".into()
+}
+
+fn default_group_trigger() -> String {
+    "mention".into()
+}
+
 fn default_model_tier() -> String {
     "standard".into()
 }
@@ -190,6 +305,38 @@ pub struct ElevenLabsConfig {
     pub voice_id: Option<String>,
 }
 
+/// Speech backends. "elevenlabs" needs an ElevenLabs key; "command" runs a
+/// local binary (whisper.cpp, piper, ...) so voice works offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceConfig {
+    /// Speech-to-text: "elevenlabs" (default), "command" or "off"
+    pub stt: String,
+    /// Text-to-speech: "elevenlabs" (default), "command" or "off"
+    pub tts: String,
+    /// STT command; `{input}` is replaced with the audio file. Transcript is read from stdout.
+    pub stt_command: Vec<String>,
+    /// TTS command; text arrives on stdin, `{output}` is replaced with the file to write.
+    pub tts_command: Vec<String>,
+    /// Format written by tts_command — Telegram voice notes accept "ogg" (Opus), "mp3", "m4a"
+    pub tts_format: String,
+    /// Timeout for local commands in seconds
+    pub command_timeout: u64,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            stt: "elevenlabs".into(),
+            tts: "elevenlabs".into(),
+            stt_command: Vec::new(),
+            tts_command: Vec::new(),
+            tts_format: "ogg".into(),
+            command_timeout: 120,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubConfig {
     pub token: String,
@@ -262,6 +409,15 @@ impl Default for DashboardConfig {
 }
 
 impl Config {
+    /// The dev bot whose tasks include `task_id`
+    pub fn dev_bot(&self, task_id: &str) -> Option<&ScopedBotConfig> {
+        self.telegram
+            .as_ref()?
+            .bots
+            .iter()
+            .find(|b| b.bot_type == "dev" && b.tasks.iter().any(|t| t == "*" || t == task_id))
+    }
+
     /// Load config from default path (~/.config/devman/config.toml)
     pub fn load() -> Result<Self> {
         let config_path = Self::default_path();
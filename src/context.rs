@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-use crate::types::{ContentBlock, Message, Role};
+use crate::types::{ContentBlock, Message, Role, ToolResultContent};
 
 /// Manages conversation history with persistence and compaction
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,6 +41,11 @@ impl ContextManager {
         }
     }
 
+    /// Where this conversation is saved, if anywhere
+    pub fn persist_path(&self) -> Option<&PathBuf> {
+        self.persist_path.as_ref()
+    }
+
     pub fn add_user_message(&mut self, text: &str) {
         self.messages.push(Message {
             role: Role::User,
@@ -50,6 +55,14 @@ impl ContextManager {
         });
     }
 
+    /// Add a user message made of several blocks (text plus images/documents)
+    pub fn add_user_content(&mut self, content: Vec<ContentBlock>) {
+        self.messages.push(Message {
+            role: Role::User,
+            content,
+        });
+    }
+
     pub fn add_assistant_message(&mut self, content: Vec<ContentBlock>) {
         self.messages.push(Message {
             role: Role::Assistant,
@@ -58,11 +71,16 @@ impl ContextManager {
     }
 
     pub fn add_tool_result(&mut self, tool_use_id: &str, content: &str, is_error: bool) {
+        self.add_tool_result_content(tool_use_id, ToolResultContent::Text(content.to_string()), is_error);
+    }
+
+    /// Add a tool result that may carry image/document blocks
+    pub fn add_tool_result_content(&mut self, tool_use_id: &str, content: ToolResultContent, is_error: bool) {
         self.messages.push(Message {
             role: Role::User,
             content: vec![ContentBlock::ToolResult {
                 tool_use_id: tool_use_id.to_string(),
-                content: content.to_string(),
+                content,
                 is_error: if is_error { Some(true) } else { None },
             }],
         });
@@ -74,17 +92,19 @@ impl ContextManager {
             .messages
             .iter()
             .flat_map(|m| &m.content)
-            .map(|c| match c {
-                ContentBlock::Text { text } => text.len(),
-                ContentBlock::ToolUse { input, .. } => input.to_string().len(),
-                ContentBlock::ToolResult { content, .. } => content.len(),
-                ContentBlock::Thinking { thinking, .. } => thinking.len(),
-                ContentBlock::Image { .. } => 1000, // rough estimate
-            })
+            .map(block_chars)
             .sum();
         (chars / 4) as u64
     }
 
+    /// Drop the whole conversation (persisted immediately)
+    pub fn clear(&mut self) -> anyhow::Result<()> {
+        self.messages.clear();
+        self.total_input_tokens = 0;
+        self.total_output_tokens = 0;
+        self.save()
+    }
+
     /// Compact conversation when approaching context limit.
     /// Extracts text from recent messages as a summary, then starts fresh.
     /// This avoids orphaned tool_use/tool_result blocks that break the API.
@@ -112,6 +132,7 @@ impl ContextManager {
                         recent_text.push_str(&format!("{role}: [used tool: {name}]\n"));
                     }
                     ContentBlock::ToolResult { content, .. } => {
+                        let content = content.text();
                         let preview = if content.len() > 200 {
                             format!("{}...", &content[..200])
                         } else {
@@ -119,6 +140,12 @@ impl ContextManager {
                         };
                         recent_text.push_str(&format!("{role}: [tool result: {preview}]\n"));
                     }
+                    ContentBlock::Image { .. } => {
+                        recent_text.push_str(&format!("{role}: [image]\n"));
+                    }
+                    ContentBlock::Document { .. } => {
+                        recent_text.push_str(&format!("{role}: [document]\n"));
+                    }
                     _ => {}
                 }
             }
@@ -161,3 +188,18 @@ impl ContextManager {
         Ok(())
     }
 }
+
+/// Rough character count of a block, for token estimation
+fn block_chars(block: &ContentBlock) -> usize {
+    match block {
+        ContentBlock::Text { text } => text.len(),
+        ContentBlock::ToolUse { input, .. } => input.to_string().len(),
+        ContentBlock::ToolResult { content, .. } => match content {
+            ToolResultContent::Text(t) => t.len(),
+            ToolResultContent::Blocks(blocks) => blocks.iter().map(block_chars).sum(),
+        },
+        ContentBlock::Thinking { thinking, .. } => thinking.len(),
+        ContentBlock::Image { .. } => 1000, // rough estimate
+        ContentBlock::Document { .. } => 10_000, // rough estimate (a few pages)
+    }
+}
@@ -46,6 +46,31 @@ fn model_pricing(model: &str) -> (f64, f64) {
     }
 }
 
+/// Estimated USD cost of one request
+pub fn estimate_cost_usd(
+    model: &str,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_read_tokens: u64,
+    cache_creation_tokens: u64,
+) -> f64 {
+    let (input_price, output_price) = model_pricing(model);
+    // Cache reads are 90% cheaper than regular input; cache creation costs 25% more
+    (input_tokens as f64 * input_price / 1_000_000.0)
+        + (output_tokens as f64 * output_price / 1_000_000.0)
+        + (cache_read_tokens as f64 * input_price * 0.1 / 1_000_000.0)
+        + (cache_creation_tokens as f64 * input_price * 1.25 / 1_000_000.0)
+}
+
+impl Cost {
+    /// Add one request's usage to this total
+    pub fn add(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        self.input_tokens += input_tokens;
+        self.output_tokens += output_tokens;
+        self.estimated_cost_usd += estimate_cost_usd(model, input_tokens, output_tokens, 0, 0);
+    }
+}
+
 impl CostTracker {
     pub fn new() -> Self {
         Self::default()
@@ -61,12 +86,7 @@ impl CostTracker {
         cache_read_tokens: u64,
         cache_creation_tokens: u64,
     ) {
-        let (input_price, output_price) = model_pricing(model);
-        // Cache reads are 90% cheaper than regular input; cache creation costs 25% more
-        let cost_usd = (input_tokens as f64 * input_price / 1_000_000.0)
-            + (output_tokens as f64 * output_price / 1_000_000.0)
-            + (cache_read_tokens as f64 * input_price * 0.1 / 1_000_000.0)
-            + (cache_creation_tokens as f64 * input_price * 1.25 / 1_000_000.0);
+        let cost_usd = estimate_cost_usd(model, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens);
 
         // Session total
         self.session_total.input_tokens += input_tokens;
@@ -3,6 +3,9 @@ use chrono::{DateTime, Datelike, Duration, NaiveTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use crate::ask::Origin;
+use crate::tools::policy::ToolPolicy;
+
 // ---------------------------------------------------------------------------
 // Types
 // ---------------------------------------------------------------------------
@@ -17,6 +20,15 @@ pub struct CronJob {
     pub last_run: Option<DateTime<Utc>>,
     pub next_run: Option<DateTime<Utc>>,
     pub created: DateTime<Utc>,
+    /// Where the job's `ask_user` questions go (default: the dashboard)
+    #[serde(default)]
+    pub origin: Option<Origin>,
+    /// Tools an `AgentTask` run gets
+    #[serde(default)]
+    pub tools: ToolPolicy,
+    /// Preview an `AgentTask`'s changes instead of making them
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -324,6 +336,9 @@ mod tests {
             last_run: None,
             next_run: None,
             created: Utc::now(),
+            origin: None,
+            tools: ToolPolicy::default(),
+            dry_run: false,
         });
         assert_eq!(sched.list().len(), 1);
         sched.remove(&id).unwrap();
@@ -43,6 +43,10 @@ pub struct AgentInfo {
     pub model: String,
     pub status: String,
     pub cost_usd: f64,
+    /// "manager", "cron" or "background"
+    pub priority: String,
+    /// 1-based place in the pool's queue while waiting for a slot
+    pub queue_position: Option<usize>,
 }
 
 pub async fn agents_list(State(state): State<SharedState>) -> Json<Vec<AgentInfo>> {
@@ -50,6 +54,40 @@ pub async fn agents_list(State(state): State<SharedState>) -> Json<Vec<AgentInfo
     Json(agents.clone())
 }
 
+// ── Questions ───────────────────────────────────────────────────────
+
+/// Open `ask_user` questions. CLI questions are left out — only the
+/// terminal that asked can take their answer.
+pub async fn questions_list() -> Json<Vec<crate::ask::Question>> {
+    let questions = crate::ask::QuestionStore::open()
+        .list()
+        .into_iter()
+        .filter(|q| q.origin != crate::ask::Origin::Cli && q.answer.is_none())
+        .collect();
+    Json(questions)
+}
+
+#[derive(Deserialize)]
+pub struct AnswerRequest {
+    pub answer: String,
+}
+
+pub async fn question_answer(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Json(req): Json<AnswerRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let answer = req.answer.trim();
+    if answer.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !crate::ask::answer(&id, answer) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let _ = state.log_tx.send(format!("✅ Question {id} answered from the dashboard: {answer}"));
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
 // ── Bots ────────────────────────────────────────────────────────────
 
 #[derive(Serialize)]
@@ -182,6 +220,42 @@ pub async fn chat_history(
     Json(messages)
 }
 
+// ── File Changes ────────────────────────────────────────────────────
+
+/// One conversation's file checkpoints
+#[derive(Serialize)]
+pub struct ChangeSession {
+    /// Conversation file stem: `<chat_id>` or `<chat_id>_t<topic>`
+    pub conversation: String,
+    pub turns: Vec<crate::checkpoint::Turn>,
+    pub files: Vec<crate::checkpoint::FileChange>,
+}
+
+/// Files a bot's agent changed in each of its conversations, with diffs
+pub async fn file_changes(
+    State(state): State<SharedState>,
+    Path(bot_name): Path<String>,
+) -> Json<Vec<ChangeSession>> {
+    let Some(chats_dir) = &state.chats_dir else {
+        return Json(vec![]);
+    };
+    let Ok(entries) = std::fs::read_dir(chats_dir.join(&bot_name)) else {
+        return Json(vec![]);
+    };
+    let mut sessions: Vec<ChangeSession> = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let conversation = name.strip_suffix(".checkpoints")?.to_string();
+            let store = crate::checkpoint::CheckpointStore::open(e.path());
+            Some(ChangeSession { conversation, turns: store.turns().to_vec(), files: store.changes() })
+        })
+        .filter(|s| !s.turns.is_empty())
+        .collect();
+    sessions.sort_by(|a, b| a.conversation.cmp(&b.conversation));
+    Json(sessions)
+}
+
 // ── Cost ────────────────────────────────────────────────────────────
 
 pub async fn cost_summary(State(state): State<SharedState>) -> Json<serde_json::Value> {
@@ -227,12 +301,14 @@ pub struct ModelsUpdate {
 #[derive(Deserialize)]
 pub struct ToolsUpdate {
     pub shell_confirm: Option<bool>,
+    pub shell_sessions: Option<bool>,
     pub web_enabled: Option<bool>,
 }
 
 #[derive(Deserialize)]
 pub struct AgentsUpdate {
     pub max_concurrent: Option<u32>,
+    pub max_per_task: Option<u32>,
     pub max_turns: Option<u32>,
     pub max_tokens: Option<u32>,
 }
@@ -264,12 +340,14 @@ pub async fn config_update(
     // Apply tool updates
     if let Some(tools) = &update.tools {
         if let Some(v) = tools.shell_confirm { doc["tools"]["shell_confirm"] = toml_edit::value(v); }
+        if let Some(v) = tools.shell_sessions { doc["tools"]["shell_sessions"] = toml_edit::value(v); }
         if let Some(v) = tools.web_enabled { doc["tools"]["web_enabled"] = toml_edit::value(v); }
     }
 
     // Apply agent updates
     if let Some(agents) = &update.agents {
         if let Some(v) = agents.max_concurrent { doc["agents"]["max_concurrent"] = toml_edit::value(v as i64); }
+        if let Some(v) = agents.max_per_task { doc["agents"]["max_per_task"] = toml_edit::value(v as i64); }
         if let Some(v) = agents.max_turns { doc["agents"]["max_turns"] = toml_edit::value(v as i64); }
         if let Some(v) = agents.max_tokens { doc["agents"]["max_tokens"] = toml_edit::value(v as i64); }
     }
@@ -541,11 +619,48 @@ pub async fn docs() -> String {
 #[derive(Serialize)]
 pub struct OrgNode {
     pub name: String,
-    pub role: String,          // "manager" | "scoped" | "sub-agent"
+    pub role: String,          // "manager" | "scoped" | "sub-agent" | "workflow" | "step"
     pub model: String,
     pub tasks: Vec<String>,
     pub status: String,        // "online" | "idle"
     pub children: Vec<OrgNode>,
+    /// Sibling steps this one waits for (workflow steps only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+/// A workflow as a node whose children are its steps
+fn workflow_node(run: &crate::workflow::WorkflowRun) -> OrgNode {
+    use crate::workflow::StepStatus;
+    let steps = run.workflow.steps.iter().zip(&run.steps).map(|(step, state)| OrgNode {
+        name: step.id.clone(),
+        role: "step".into(),
+        model: format!("{:?}", state.tier).to_lowercase(),
+        tasks: step.outputs.clone(),
+        status: match &state.status {
+            StepStatus::Pending => "pending".into(),
+            StepStatus::Running => "running".into(),
+            StepStatus::Done => "done".into(),
+            StepStatus::Failed(_) => "failed".into(),
+            StepStatus::Skipped => "skipped".into(),
+        },
+        children: vec![],
+        depends_on: step.depends_on.clone(),
+    }).collect();
+    let status = match (run.is_finished(), run.succeeded()) {
+        (false, _) => "running",
+        (true, true) => "done",
+        (true, false) => "failed",
+    };
+    OrgNode {
+        name: run.workflow.name.clone(),
+        role: "workflow".into(),
+        model: String::new(),
+        tasks: vec![run.task()],
+        status: status.into(),
+        children: steps,
+        depends_on: vec![],
+    }
 }
 
 pub async fn org_chart(State(state): State<SharedState>) -> Json<OrgNode> {
@@ -563,6 +678,7 @@ pub async fn org_chart(State(state): State<SharedState>) -> Json<OrgNode> {
             tasks: bot.tasks.clone(),
             status: "online".into(),
             children: vec![],
+            depends_on: vec![],
         })
         .collect();
 
@@ -575,10 +691,20 @@ pub async fn org_chart(State(state): State<SharedState>) -> Json<OrgNode> {
         tasks: vec![a.task_id.clone()],
         status: a.status.clone(),
         children: vec![],
+        depends_on: vec![],
     }).collect();
 
+    // Workflows still running, or finished within the hour
+    let recent = chrono::Utc::now() - chrono::Duration::hours(1);
+    let workflows: Vec<OrgNode> = crate::workflow::WorkflowRun::list(&crate::workflow::WorkflowRun::default_dir())
+        .iter()
+        .filter(|w| w.finished.is_none_or(|f| f > recent))
+        .map(workflow_node)
+        .collect();
+
     let mut children = scoped_bots;
     children.extend(sub_agents);
+    children.extend(workflows);
 
     Json(OrgNode {
         name: "Manager".into(),
@@ -587,5 +713,6 @@ pub async fn org_chart(State(state): State<SharedState>) -> Json<OrgNode> {
         tasks: vec!["*".into()],
         status: "online".into(),
         children,
+        depends_on: vec![],
     })
 }
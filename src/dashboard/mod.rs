@@ -77,8 +77,11 @@ pub async fn start(
         // API endpoints
         .route("/api/status", get(api::status))
         .route("/api/agents", get(api::agents_list))
+        .route("/api/questions", get(api::questions_list))
+        .route("/api/questions/{id}/answer", post(api::question_answer))
         .route("/api/bots", get(api::bots_list))
         .route("/api/bots/{name}/history", get(api::chat_history))
+        .route("/api/bots/{name}/changes", get(api::file_changes))
         .route("/api/cost", get(api::cost_summary))
         .route("/api/config", get(api::config_get).post(api::config_update))
         .route("/api/logs", get(api::logs_buffer))
@@ -10,11 +10,14 @@ use serde::Deserialize;
 
 use super::SharedState;
 use crate::agent::AgentLoop;
+use crate::ask::Origin;
 use crate::auth::AuthStore;
 use crate::client::AnthropicClient;
 use crate::context::ContextManager;
 use crate::memory::{MemoryManager, TaskStorage};
+use crate::shell_session::ShellSession;
 use crate::tools;
+use crate::tools::policy::ToolPolicy;
 use crate::types::Thinking;
 
 #[derive(Deserialize)]
@@ -35,12 +38,13 @@ pub async fn chat_handler(
     ws.on_upgrade(move |socket| handle_chat(socket, state, query.bot))
 }
 
-/// Resolve bot name to (model, system_prompt, task_scope)
-fn resolve_bot(state: &SharedState, bot_name: Option<&str>) -> (String, String, Vec<String>) {
+/// Resolve bot name to (model, system_prompt, task_scope, tool policy)
+fn resolve_bot(state: &SharedState, bot_name: Option<&str>) -> (String, String, Vec<String>, ToolPolicy) {
     let default = (
         state.config.models.standard.clone(),
         "You are DevMan, a helpful coding assistant. Be concise and use tools proactively.".into(),
         vec!["*".into()],
+        ToolPolicy::default(),
     );
 
     let name = match bot_name {
@@ -66,7 +70,7 @@ fn resolve_bot(state: &SharedState, bot_name: Option<&str>) -> (String, String,
                 format!("You are a DevMan bot scoped to tasks: {:?}. Be helpful and concise.", bot.tasks)
             });
 
-            return (model, prompt, bot.tasks.clone());
+            return (model, prompt, bot.tasks.clone(), bot.tools.clone());
         }
     }
 
@@ -74,7 +78,7 @@ fn resolve_bot(state: &SharedState, bot_name: Option<&str>) -> (String, String,
 }
 
 async fn handle_chat(mut socket: WebSocket, state: SharedState, bot_name: Option<String>) {
-    let (model, system_prompt, task_scope) = resolve_bot(&state, bot_name.as_deref());
+    let (model, system_prompt, task_scope, tool_policy) = resolve_bot(&state, bot_name.as_deref());
     let display_name = bot_name.as_deref().unwrap_or("manager");
 
     // Send welcome
@@ -119,7 +123,12 @@ async fn handle_chat(mut socket: WebSocket, state: SharedState, bot_name: Option
         Thinking::Off,
         brave_key,
         github_token,
-    );
+    )
+    .with_origin(Origin::Dashboard)
+    .with_tool_policy(tool_policy);
+    if state.config.tools.shell_sessions {
+        agent = agent.with_shell_session(ShellSession::new());
+    }
 
     // Attach scoped storage if this is a scoped bot with a single task
     if task_scope.len() == 1 && task_scope[0] != "*" {
@@ -0,0 +1,110 @@
+/// Skip the line-by-line diff past this many cells and show the changed
+/// block whole — edits are small, whole-file rewrites aren't worth the memory
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Unified diff of two texts with `context` unchanged lines around each change.
+/// Empty if they're the same.
+pub fn unified_diff(path: &str, old: &str, new: &str, context: usize) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = line_ops(&a, &b);
+    if ops.iter().all(|(k, _)| *k == ' ') {
+        return String::new();
+    }
+
+    // Old and new line numbers (1-based) at each op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for (kind, _) in &ops {
+        positions.push((old_line, new_line));
+        match kind {
+            '-' => old_line += 1,
+            '+' => new_line += 1,
+            _ => {
+                old_line += 1;
+                new_line += 1;
+            }
+        }
+    }
+
+    let path = path.trim_start_matches('/');
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    let changed: Vec<usize> = ops.iter().enumerate().filter(|(_, (k, _))| *k != ' ').map(|(i, _)| i).collect();
+    let mut i = 0;
+    while i < changed.len() {
+        // Grow the hunk while the next change is close enough to share context
+        let start = changed[i].saturating_sub(context);
+        let mut end = changed[i];
+        while i + 1 < changed.len() && changed[i + 1] <= end + 2 * context + 1 {
+            i += 1;
+            end = changed[i];
+        }
+        let end = (end + context + 1).min(ops.len());
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|(k, _)| *k != '+').count();
+        let new_len = hunk.iter().filter(|(k, _)| *k != '-').count();
+        let (old_start, new_start) = positions[start];
+        // An empty side is numbered from the line before it, like diff -u
+        let old_start = if old_len == 0 { old_start - 1 } else { old_start };
+        let new_start = if new_len == 0 { new_start - 1 } else { new_start };
+        out.push_str(&format!("@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"));
+        for (kind, line) in hunk {
+            out.push_str(&format!("{kind}{line}\n"));
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `diff` cut to its first `max_lines` lines, noting how many were dropped
+pub fn clip(diff: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut out = lines[..lines.len().min(max_lines)].join("\n");
+    if lines.len() > max_lines {
+        out.push_str(&format!("\n... ({} more diff lines)", lines.len() - max_lines));
+    }
+    out
+}
+
+/// Line operations turning `a` into `b`: (' ' | '-' | '+', line)
+fn line_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(char, &'a str)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<(char, &str)> = a[..prefix].iter().map(|l| (' ', *l)).collect();
+    if am.len() * bm.len() > MAX_DIFF_CELLS {
+        ops.extend(am.iter().map(|l| ('-', *l)));
+        ops.extend(bm.iter().map(|l| ('+', *l)));
+    } else {
+        // Longest common subsequence, walked from the front
+        let (n, m) = (am.len(), bm.len());
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * (m + 1) + j] = if am[i] == bm[j] {
+                    lcs[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && am[i] == bm[j] {
+                ops.push((' ', am[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+                // Removals before additions, as diff -u prints them
+                ops.push(('-', am[i]));
+                i += 1;
+            } else {
+                ops.push(('+', bm[j]));
+                j += 1;
+            }
+        }
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|l| (' ', *l)));
+    ops
+}
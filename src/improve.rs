@@ -5,6 +5,9 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use crate::orchestrator::TaskComplexity;
+use crate::triage::{Effort, Triage};
+
 pub struct ImprovementEngine {
     learnings_path: PathBuf,
     stats_path: PathBuf,
@@ -32,7 +35,7 @@ pub enum LearningCategory {
 }
 
 impl LearningCategory {
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "mistake" => Some(Self::Mistake),
             "preference" => Some(Self::Preference),
@@ -59,8 +62,38 @@ pub struct ModelStats {
     pub total_requests: u64,
     pub by_model: HashMap<String, ModelUsage>,
     pub by_task_type: HashMap<String, u64>,
+    /// Triage decisions and how they turned out, oldest first
+    #[serde(default)]
+    pub routing: Vec<RoutingDecision>,
+}
+
+/// One triage decision, kept so routing accuracy can be measured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingDecision {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Start of the request that was routed
+    pub message: String,
+    pub tier: TaskComplexity,
+    pub effort: Effort,
+    pub model: String,
+    pub reason: String,
+    #[serde(default)]
+    pub outcome: Option<RoutingOutcome>,
+    /// The tier the user said it should have had
+    #[serde(default)]
+    pub corrected_to: Option<TaskComplexity>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingOutcome {
+    pub cost_usd: f64,
+    pub success: bool,
+}
+
+/// Keep this many routing decisions — enough to tune on, small enough to load
+const MAX_ROUTING_DECISIONS: usize = 500;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ModelUsage {
     pub requests: u64,
@@ -118,6 +151,73 @@ impl ImprovementEngine {
         }
     }
 
+    /// Log a triage decision; returns its id for [`Self::record_outcome`]
+    pub fn record_triage(&mut self, message: &str, triage: &Triage, model: &str) -> String {
+        let id = Uuid::new_v4().to_string()[..8].to_string();
+        self.stats.routing.push(RoutingDecision {
+            id: id.clone(),
+            timestamp: Utc::now(),
+            message: message.chars().take(200).collect(),
+            tier: triage.tier,
+            effort: triage.effort,
+            model: model.to_string(),
+            reason: triage.reason.clone(),
+            outcome: None,
+            corrected_to: None,
+        });
+        let excess = self.stats.routing.len().saturating_sub(MAX_ROUTING_DECISIONS);
+        self.stats.routing.drain(..excess);
+        id
+    }
+
+    /// How the routed run went
+    pub fn record_outcome(&mut self, decision_id: &str, cost_usd: f64, success: bool) {
+        if let Some(d) = self.stats.routing.iter_mut().find(|d| d.id == decision_id) {
+            d.outcome = Some(RoutingOutcome { cost_usd, success });
+        }
+    }
+
+    /// The user says the request needed a different tier
+    pub fn record_correction(&mut self, decision_id: &str, tier: TaskComplexity) {
+        if let Some(d) = self.stats.routing.iter_mut().find(|d| d.id == decision_id) {
+            d.corrected_to = Some(tier);
+        }
+    }
+
+    /// Per-tier success rate, cost and corrections for finished routing decisions
+    pub fn routing_summary(&self) -> String {
+        let finished: Vec<&RoutingDecision> = self.stats.routing.iter().filter(|d| d.outcome.is_some()).collect();
+        if finished.is_empty() {
+            return "No routed runs yet.".into();
+        }
+        let mut lines = Vec::new();
+        for tier in [TaskComplexity::Quick, TaskComplexity::Standard, TaskComplexity::Complex] {
+            let runs: Vec<&&RoutingDecision> = finished.iter().filter(|d| d.tier == tier).collect();
+            if runs.is_empty() {
+                continue;
+            }
+            let outcomes = runs.iter().filter_map(|d| d.outcome.as_ref());
+            let succeeded = outcomes.clone().filter(|o| o.success).count();
+            let cost: f64 = outcomes.map(|o| o.cost_usd).sum();
+            let corrected = runs.iter().filter(|d| d.corrected_to.is_some_and(|t| t != tier)).count();
+            lines.push(format!(
+                "- {tier:?}: {} runs, {succeeded} succeeded, {corrected} corrected, ${:.2} avg",
+                runs.len(),
+                cost / runs.len() as f64
+            ));
+        }
+        let correct = finished
+            .iter()
+            .filter(|d| d.outcome.as_ref().is_some_and(|o| o.success) && d.corrected_to.is_none_or(|t| t == d.tier))
+            .count();
+        lines.push(format!(
+            "Routing accuracy: {:.0}% ({correct}/{})",
+            correct as f64 * 100.0 / finished.len() as f64,
+            finished.len()
+        ));
+        lines.join("\n")
+    }
+
     pub fn get_stats(&self) -> &ModelStats {
         &self.stats
     }
@@ -174,6 +274,13 @@ impl ImprovementEngine {
             ));
         }
 
+        // Routing accuracy
+        if !self.stats.routing.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("## Routing ({} decisions)", self.stats.routing.len()));
+            lines.push(self.routing_summary());
+        }
+
         lines.join("\n")
     }
 
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::orchestrator::SubAgentRecord;
+
+/// A note for a running sub-agent — new instructions from the manager, or
+/// findings from a sibling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxMessage {
+    /// "manager" or the sender's run id
+    pub from: String,
+    pub text: String,
+    pub sent: DateTime<Utc>,
+}
+
+impl InboxMessage {
+    pub fn new(from: &str, text: &str) -> Self {
+        Self { from: from.to_string(), text: text.to_string(), sent: Utc::now() }
+    }
+}
+
+/// A sub-agent's inbox: `inbox.jsonl` in its run directory. Anyone can append;
+/// the agent drains it between API calls.
+pub struct Inbox {
+    agents_dir: PathBuf,
+    run_id: String,
+}
+
+impl Inbox {
+    pub fn new(agents_dir: PathBuf, run_id: &str) -> Self {
+        Self { agents_dir, run_id: run_id.to_string() }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    fn path(agents_dir: &Path, run_id: &str) -> PathBuf {
+        agents_dir.join(run_id).join("inbox.jsonl")
+    }
+
+    /// Append a message to a run's inbox
+    pub fn send(agents_dir: &Path, run_id: &str, message: &InboxMessage) -> Result<()> {
+        let path = Self::path(agents_dir, run_id);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening inbox for {run_id}"))?;
+        writeln!(file, "{}", serde_json::to_string(message)?)?;
+        Ok(())
+    }
+
+    /// Send to another agent from this one
+    pub fn send_to(&self, run_id: &str, text: &str) -> Result<()> {
+        Self::send(&self.agents_dir, run_id, &InboxMessage::new(&self.run_id, text))
+    }
+
+    /// Whether anything is waiting
+    pub fn has_mail(&self) -> bool {
+        Self::path(&self.agents_dir, &self.run_id).exists()
+    }
+
+    /// Take every waiting message. The file is moved aside first, so a message
+    /// sent while we read lands in a fresh inbox rather than being lost.
+    pub fn drain(&self) -> Vec<InboxMessage> {
+        let path = Self::path(&self.agents_dir, &self.run_id);
+        let taken = path.with_extension("jsonl.reading");
+        if std::fs::rename(&path, &taken).is_err() {
+            return Vec::new();
+        }
+        let content = std::fs::read_to_string(&taken).unwrap_or_default();
+        let _ = std::fs::remove_file(&taken);
+        content.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()
+    }
+
+    /// Run ids of the active agents `to` names — a run id, or a task id for
+    /// every agent on that task — other than this one
+    pub fn resolve(&self, to: &str) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.agents_dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|e| std::fs::read_to_string(e.path().join("record.json")).ok())
+            .filter_map(|c| serde_json::from_str::<SubAgentRecord>(&c).ok())
+            .filter(|r| r.run_id != self.run_id && (r.run_id == to || r.task_id == to))
+            .filter(|r| r.status.is_active())
+            .map(|r| r.run_id)
+            .collect()
+    }
+}
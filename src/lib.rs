@@ -1,5 +1,7 @@
 pub mod agent;
+pub mod ask;
 pub mod auth;
+pub mod checkpoint;
 pub mod client;
 pub mod cli;
 pub mod config;
@@ -7,12 +9,25 @@ pub mod context;
 pub mod cost;
 pub mod cron;
 pub mod dashboard;
+pub mod diff;
+pub mod improve;
+pub mod inbox;
 pub mod logging;
 pub mod manager;
+pub mod media;
 pub mod memory;
 pub mod orchestrator;
+pub mod process;
 pub mod telegram;
 pub mod render;
+pub mod repomap;
+pub mod sandbox;
+pub mod search;
+pub mod shell_session;
 pub mod tools;
+pub mod triage;
 pub mod types;
+pub mod verify;
 pub mod voice;
+pub mod workflow;
+pub mod worktree;
@@ -4,7 +4,9 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 
 mod agent;
+mod ask;
 mod auth;
+mod checkpoint;
 mod client;
 mod cli;
 mod config;
@@ -12,15 +14,27 @@ mod context;
 mod cost;
 mod cron;
 mod dashboard;
+mod diff;
 mod improve;
+mod inbox;
 mod manager;
+mod media;
 mod memory;
 mod orchestrator;
+mod process;
 mod render;
+mod repomap;
+mod sandbox;
+mod search;
+mod shell_session;
 mod telegram;
 mod tools;
+mod triage;
 mod types;
+mod verify;
 mod voice;
+mod workflow;
+mod worktree;
 mod logging;
 
 #[derive(Parser)]
@@ -33,12 +47,19 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Interactive chat REPL
-    Chat,
+    Chat {
+        /// Preview file, git, GitHub, storage and bot changes instead of making them
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Run a single task
     Run {
         /// Task message
         #[arg(short, long)]
         message: String,
+        /// Preview file, git, GitHub, storage and bot changes instead of making them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Guided first-run setup
     Init,
@@ -53,6 +74,27 @@ enum Commands {
         #[command(subcommand)]
         action: CronAction,
     },
+    /// Run and inspect multi-step workflows
+    Workflow {
+        #[command(subcommand)]
+        action: WorkflowAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkflowAction {
+    /// Run a workflow file until every step is done
+    Run {
+        /// Workflow TOML file
+        file: std::path::PathBuf,
+    },
+    /// Check a workflow file and show its step order
+    Validate {
+        /// Workflow TOML file
+        file: std::path::PathBuf,
+    },
+    /// List recent workflow runs
+    List,
 }
 
 #[derive(Subcommand)]
@@ -70,6 +112,18 @@ enum CronAction {
         /// Agent message to run
         #[arg(short, long)]
         message: String,
+        /// Only these tools (globs like "git_*"; repeatable)
+        #[arg(long)]
+        allow: Vec<String>,
+        /// Never these tools (globs; repeatable)
+        #[arg(long)]
+        deny: Vec<String>,
+        /// Only tools that don't change anything
+        #[arg(long)]
+        read_only: bool,
+        /// Preview changes instead of making them, every run
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Remove a job
     Remove {
@@ -90,10 +144,16 @@ async fn main() -> Result<()> {
     );
 
     match cli.command {
-        Some(Commands::Chat) | None => cli::chat::run(&config).await,
-        Some(Commands::Run { message }) => cli::run::run(&config, &message).await,
+        Some(Commands::Chat { dry_run }) => cli::chat::run(&config, dry_run).await,
+        None => cli::chat::run(&config, false).await,
+        Some(Commands::Run { message, dry_run }) => cli::run::run(&config, &message, dry_run).await,
         Some(Commands::Init) => cli::init::run().await,
         Some(Commands::Serve) => cli::serve::run(&config).await,
+        Some(Commands::Workflow { action }) => match action {
+            WorkflowAction::Run { file } => cli::workflow::run(&config, &file).await,
+            WorkflowAction::Validate { file } => cli::workflow::validate(&file),
+            WorkflowAction::List => cli::workflow::list(),
+        },
         Some(Commands::Auth) => {
             let auth = auth::AuthStore::load()?;
             match auth.anthropic_api_key() {
@@ -163,7 +223,7 @@ async fn main() -> Result<()> {
                         }
                     }
                 }
-                CronAction::Add { name, schedule, message } => {
+                CronAction::Add { name, schedule, message, allow, deny, read_only, dry_run } => {
                     let job = cron::CronJob {
                         id: uuid::Uuid::new_v4().to_string(),
                         name: name.clone(),
@@ -173,6 +233,9 @@ async fn main() -> Result<()> {
                         last_run: None,
                         next_run: None,
                         created: chrono::Utc::now(),
+                        origin: None,
+                        tools: tools::policy::ToolPolicy { allow, deny, read_only },
+                        dry_run,
                     };
                     let id = scheduler.add(job);
                     scheduler.save()?;
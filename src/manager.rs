@@ -1,16 +1,16 @@
 use anyhow::Result;
-use colored::Colorize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
-use crate::agent::AgentLoop;
-use crate::client::AnthropicClient;
+use crate::ask::{Origin, SuspendedRun};
 use crate::config::Config;
-use crate::context::ContextManager;
-use crate::cost::CostTracker;
-use crate::orchestrator::{Orchestrator, SubAgentMessage, TaskComplexity};
-use crate::tools;
-use crate::types::{Thinking, ToolDefinition};
+use crate::dashboard::api::AgentInfo;
+use crate::orchestrator::{Orchestrator, Priority, SpawnOptions, SubAgentMessage, SubAgentRecord, TaskComplexity};
+use crate::tools::policy::ToolPolicy;
+use crate::types::ToolDefinition;
 
-/// Manager-only tool definitions (spawn, steer, kill, list agents)
+/// Manager-only tool definitions (bots, and spawn, steer, kill, list agents)
 fn manager_tool_definitions() -> Vec<ToolDefinition> {
     use serde_json::json;
     vec![
@@ -86,7 +86,7 @@ fn manager_tool_definitions() -> Vec<ToolDefinition> {
                 "properties": {
                     "task_id": {
                         "type": "string",
-                        "description": "Task identifier"
+                        "description": "Task identifier (default: the known task triage finds it's about, or a new one)"
                     },
                     "message": {
                         "type": "string",
@@ -95,15 +95,69 @@ fn manager_tool_definitions() -> Vec<ToolDefinition> {
                     "model_tier": {
                         "type": "string",
                         "enum": ["quick", "standard", "complex"],
-                        "description": "Model tier: quick (Haiku), standard (Sonnet), complex (Opus). Default: auto-detect."
+                        "description": "Model tier: quick (Haiku), standard (Sonnet), complex (Opus). Default: picked by triage."
+                    },
+                    "priority": {
+                        "type": "string",
+                        "enum": ["manager", "cron", "background"],
+                        "description": "Queue priority when the pool is full: manager (someone is waiting), cron, or background. Default: manager."
+                    },
+                    "tools": {
+                        "type": "object",
+                        "description": "Limit the sub-agent's tools (default: all)",
+                        "properties": {
+                            "allow": { "type": "array", "items": { "type": "string" }, "description": "Only these tools; globs like git_* work" },
+                            "deny": { "type": "array", "items": { "type": "string" }, "description": "Never these tools" },
+                            "read_only": { "type": "boolean", "description": "Only tools that don't change anything" }
+                        }
+                    }
+                },
+                "required": ["message"]
+            }),
+        },
+        ToolDefinition {
+            name: "plan_workflow".into(),
+            description: "Break a goal into steps run by sub-agents. Steps start as soon as the steps they depend on are done, so independent steps run in parallel. Steps pass data through task storage: list what each one reads (inputs) and writes (outputs). Failed steps are retried, then moved up a model tier.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Short name for the workflow"
+                    },
+                    "task": {
+                        "type": "string",
+                        "description": "Task whose storage the steps share (default: a new one per run)"
+                    },
+                    "priority": {
+                        "type": "string",
+                        "enum": ["manager", "cron", "background"],
+                        "description": "Queue priority for the steps (default: manager)"
+                    },
+                    "steps": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "string", "description": "Step id (letters, digits, - or _)" },
+                                "instructions": { "type": "string", "description": "What the step's sub-agent should do" },
+                                "depends_on": { "type": "array", "items": { "type": "string" }, "description": "Steps that must finish first" },
+                                "tier": { "type": "string", "enum": ["quick", "standard", "complex"], "description": "Model tier (default: standard)" },
+                                "tools": { "type": "array", "items": { "type": "string" }, "description": "Tools the step may use (default: all)" },
+                                "inputs": { "type": "array", "items": { "type": "string" }, "description": "Storage files written by earlier steps" },
+                                "outputs": { "type": "array", "items": { "type": "string" }, "description": "Storage files this step must write" },
+                                "retries": { "type": "integer", "description": "Retries per tier before escalating (default: 1)" }
+                            },
+                            "required": ["id", "instructions"]
+                        }
                     }
                 },
-                "required": ["task_id", "message"]
+                "required": ["name", "steps"]
             }),
         },
         ToolDefinition {
             name: "list_agents".into(),
-            description: "List active and recent sub-agents with their status and cost.".into(),
+            description: "List active and queued sub-agents with their status, priority and queue position.".into(),
             input_schema: json!({
                 "type": "object",
                 "properties": {}
@@ -123,115 +177,214 @@ fn manager_tool_definitions() -> Vec<ToolDefinition> {
                 "required": ["run_id"]
             }),
         },
+        ToolDefinition {
+            name: "steer_agent".into(),
+            description: "Send new instructions to a running or queued sub-agent. It reads them before its next step, so use it to correct course, add a requirement or point it at something — not to start new work.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "run_id": {
+                        "type": "string",
+                        "description": "Run ID of the agent to steer"
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "The new instructions"
+                    }
+                },
+                "required": ["run_id", "message"]
+            }),
+        },
+        ToolDefinition {
+            name: "correct_tier".into(),
+            description: "Tell triage it picked the wrong model tier for a sub-agent, e.g. when the user says it was overkill or not up to the job. Future requests like it are routed accordingly.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "run_id": {
+                        "type": "string",
+                        "description": "Run ID of a sub-agent whose tier triage picked"
+                    },
+                    "model_tier": {
+                        "type": "string",
+                        "enum": ["quick", "standard", "complex"],
+                        "description": "The tier it should have had"
+                    }
+                },
+                "required": ["run_id", "model_tier"]
+            }),
+        },
     ]
 }
 
-/// The Manager — triage, routing, sub-agent orchestration
+/// The manager's hold on the sub-agent pool: dispatches its agent tools and
+/// collects what the sub-agents report. Clones share one orchestrator.
+#[derive(Clone)]
 pub struct Manager {
-    config: Config,
-    orchestrator: Orchestrator,
-    agent: AgentLoop,
+    orchestrator: Arc<Mutex<Orchestrator>>,
+}
+
+/// Something a sub-agent or workflow has to say, and who to say it to
+#[derive(Debug)]
+pub struct Report {
+    pub origin: Option<Origin>,
+    pub text: String,
 }
 
 impl Manager {
     pub fn new(config: Config, api_key: String, brave_api_key: Option<String>, github_token: Option<String>) -> Self {
-        let client = AnthropicClient::new(api_key.clone());
-
-        let state_dir = dirs::data_dir()
-            .unwrap_or_else(|| std::path::PathBuf::from("."))
-            .join("devman");
-        let _ = std::fs::create_dir_all(&state_dir);
-        let context = ContextManager::with_persistence(state_dir.join("manager-conversation.json"));
-
-        // Combine built-in tools + manager-only tools
-        let mut tool_defs = tools::builtin_tool_definitions(config.tools.web_enabled, config.github.is_some());
-        tool_defs.extend(manager_tool_definitions());
-
-        let system_prompt = MANAGER_SYSTEM_PROMPT.to_string();
-
-        // Manager gets global storage (can see all task storage)
-        let mm = crate::memory::MemoryManager::new(crate::memory::MemoryManager::default_root());
-        let global_storage = mm.global_storage();
-
-        let agent = AgentLoop::new(
-            client,
-            context,
-            config.models.manager.clone(),
-            system_prompt,
-            tool_defs,
-            config.agents.max_turns,
-            config.agents.max_tokens,
-            Thinking::Off,
-            brave_api_key.clone(),
-            github_token.clone(),
-        ).with_storage(global_storage);
-
-        let orchestrator = Orchestrator::new(config.clone(), api_key, brave_api_key, github_token);
-
-        Self {
-            config,
-            orchestrator,
-            agent,
+        Self::with_orchestrator(Orchestrator::new(config, api_key, brave_api_key, github_token))
+    }
+
+    pub fn with_orchestrator(orchestrator: Orchestrator) -> Self {
+        Self { orchestrator: Arc::new(Mutex::new(orchestrator)) }
+    }
+
+    /// Manager tools: bot management plus spawning and steering sub-agents
+    pub fn tool_definitions() -> Vec<ToolDefinition> {
+        manager_tool_definitions()
+    }
+
+    /// Whether `name` is a tool this handle runs (the bot tools are built-ins)
+    pub fn handles(name: &str) -> bool {
+        ORCHESTRATOR_TOOLS.contains(&name)
+    }
+
+    /// Deal with sub-agents the last process left running, per `agents.recovery`
+    pub async fn recover(&self) -> Result<Vec<SubAgentRecord>> {
+        self.orchestrator.lock().await.recover()
+    }
+
+    /// Give a sub-agent the last process left waiting in `ask_user` its
+    /// answer. Returns false if the run isn't a sub-agent.
+    pub async fn resume(&self, run: &SuspendedRun, result: &str) -> bool {
+        self.orchestrator.lock().await.resume_answered(run, result)
+    }
+
+    /// Run an orchestrator tool for an agent at `origin` under `policy`.
+    /// Sub-agents and workflow steps never get tools their caller lacks.
+    pub async fn execute(&self, name: &str, input: &Value, origin: Option<&Origin>, policy: &ToolPolicy) -> Result<String> {
+        let mut orch = self.orchestrator.lock().await;
+        match name {
+            "spawn_agent" => {
+                let message = required(input, "message")?;
+                let priority = match input["priority"].as_str() {
+                    Some(p) => Priority::parse(p).ok_or_else(|| anyhow::anyhow!("unknown priority: {p}"))?,
+                    None => Priority::Manager,
+                };
+                let opts = SpawnOptions {
+                    task_id: input["task_id"].as_str().unwrap_or_default().trim().to_string(),
+                    message: message.to_string(),
+                    system_prompt: SUB_AGENT_PROMPT.to_string(),
+                    origin: origin.cloned(),
+                    priority,
+                    policy: requested_policy(policy, &input["tools"]),
+                    ..Default::default()
+                };
+                let run_id = match input["model_tier"].as_str() {
+                    Some(t) => {
+                        let tier = TaskComplexity::parse(t).ok_or_else(|| anyhow::anyhow!("unknown model_tier: {t}"))?;
+                        let model = orch.model_for_complexity(tier).to_string();
+                        orch.spawn(&model, opts).await?
+                    }
+                    None => orch.spawn_triaged(opts).await?,
+                };
+                let model = &orch.agents[&run_id].model;
+                Ok(match orch.queue_position(&run_id) {
+                    Some(n) => format!("Queued {run_id} on {model} (#{n} in line)"),
+                    None => format!("Spawned {run_id} on {model}"),
+                })
+            }
+            "plan_workflow" => orch.plan_workflow(input, origin.cloned(), policy.clone()),
+            "list_agents" => Ok(orch.describe_agents()),
+            "kill_agent" => {
+                let run_id = required(input, "run_id")?;
+                orch.kill(run_id)?;
+                Ok(format!("Killed {run_id}"))
+            }
+            "steer_agent" => {
+                let run_id = required(input, "run_id")?;
+                orch.steer(run_id, required(input, "message")?)?;
+                Ok(format!("Sent to {run_id} — it reads it before its next step"))
+            }
+            "correct_tier" => {
+                let run_id = required(input, "run_id")?;
+                let tier = required(input, "model_tier")?;
+                let tier = TaskComplexity::parse(tier).ok_or_else(|| anyhow::anyhow!("unknown model_tier: {tier}"))?;
+                orch.correct_tier(run_id, tier)?;
+                let tier = format!("{tier:?}").to_lowercase();
+                Ok(format!("Noted — triage will send requests like {run_id}'s to {tier}"))
+            }
+            _ => anyhow::bail!("Unknown manager tool: {name}"),
         }
     }
 
-    /// Process a user message through the manager
-    pub async fn handle_message(&mut self, message: &str) -> Result<String> {
-        // Check for completed sub-agents first
-        while let Some(msg) = self.orchestrator.try_recv() {
-            match msg {
-                SubAgentMessage::Done {
-                    run_id, output, ..
-                } => {
-                    eprintln!("{}", format!("✅ Sub-agent {run_id} completed").green());
-                    // Inject result into manager context
-                    let summary = if output.len() > 2000 {
-                        format!("{}...\n(truncated)", &output[..2000])
-                    } else {
-                        output
-                    };
-                    self.agent
-                        .context
-                        .add_user_message(&format!("[Sub-agent {run_id} result]\n{summary}"));
+    /// What finished, failed or needs attention since the last poll. Also
+    /// starts queued agents as slots free up.
+    pub async fn poll(&self) -> Vec<Report> {
+        let mut orch = self.orchestrator.lock().await;
+        let mut reports = Vec::new();
+        while let Some(msg) = orch.try_recv() {
+            let origin_of = |run_id: &str| orch.agents.get(run_id).and_then(|r| r.origin.clone());
+            let (origin, text) = match msg {
+                SubAgentMessage::Done { run_id, output, .. } => (origin_of(&run_id), format!("✅ {run_id} finished\n\n{output}")),
+                SubAgentMessage::Error { run_id, error } => (origin_of(&run_id), format!("❌ {run_id} failed: {error}")),
+                SubAgentMessage::Progress { run_id, text } => (origin_of(&run_id), format!("ℹ️ {run_id}: {text}")),
+                SubAgentMessage::Escalate { run_id, reason } => {
+                    (origin_of(&run_id), format!("⬆️ {run_id} moved up a model tier: {reason}"))
                 }
-                SubAgentMessage::Error { run_id, error } => {
-                    eprintln!("{}", format!("❌ Sub-agent {run_id} failed: {error}").red());
-                    self.agent
-                        .context
-                        .add_user_message(&format!("[Sub-agent {run_id} error: {error}]"));
+                SubAgentMessage::Interrupted { run_id, task_id } => (
+                    origin_of(&run_id),
+                    format!("⚠️ {run_id} ({task_id}) was interrupted by a restart and did not finish"),
+                ),
+                SubAgentMessage::WorkflowDone { success, summary, origin, .. } => {
+                    (origin, format!("{} {summary}", if success { "✅" } else { "❌" }))
                 }
-                _ => {}
-            }
+                SubAgentMessage::Verdict { .. } => continue,
+            };
+            reports.push(Report { origin, text });
         }
+        reports
+    }
 
-        // Run through the manager agent
-        let result = self.agent.run_turn(message).await?;
+    /// Rows for the dashboard's agents table
+    pub async fn agent_infos(&self) -> Vec<AgentInfo> {
+        self.orchestrator.lock().await.agent_infos()
+    }
+}
 
-        // Track manager's own cost
-        self.orchestrator.cost_tracker.record(
-            &self.config.models.manager,
-            None,
-            result.usage.input_tokens,
-            result.usage.output_tokens,
-            0,
-            0,
-        );
+/// Tools `Manager::execute` runs
+const ORCHESTRATOR_TOOLS: &[&str] = &["spawn_agent", "plan_workflow", "list_agents", "kill_agent", "steer_agent", "correct_tier"];
 
-        Ok(result.text)
-    }
+fn required<'a>(input: &'a Value, key: &str) -> Result<&'a str> {
+    input[key]
+        .as_str()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("{key} is required"))
+}
 
-    /// Get cost summary
-    pub fn cost_summary(&self) -> String {
-        self.orchestrator.cost_tracker.summary()
-    }
+/// `caller` narrowed by spawn_agent's `tools` argument — it can take tools away, never add them
+fn requested_policy(caller: &ToolPolicy, tools: &Value) -> ToolPolicy {
+    let names = |key: &str| -> Vec<String> {
+        tools[key]
+            .as_array()
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    let mut policy = caller.within(&names("allow"));
+    policy.deny.extend(names("deny"));
+    policy.read_only |= tools["read_only"].as_bool().unwrap_or(false);
+    policy
 }
 
-const MANAGER_SYSTEM_PROMPT: &str = r#"You are DevMan, an AI assistant running as a Telegram bot. You ARE the system — you have tools to manage yourself.
+const SUB_AGENT_PROMPT: &str = "You are a DevMan sub-agent. The manager handed you one piece of work — do it with your tools, then finish with a short summary of what you did and anything left open.";
+
+pub const MANAGER_SYSTEM_PROMPT: &str = r#"You are DevMan, an AI assistant running as a Telegram bot. You ARE the system — you have tools to manage yourself.
 
 RULES:
 1. For quick questions — answer directly using your tools.
 2. For substantial work — spawn a sub-agent.
-3. Pick the right model tier:
+3. Pick the right model tier, or leave it out and let triage pick:
    - quick (Haiku): simple lookups, file searches, status checks
    - standard (Sonnet): code changes, debugging, writing
    - complex (Opus): architecture decisions, complex refactors
@@ -247,9 +400,9 @@ When the user asks you to assign/add/create a bot for a task:
 
 TOOLS:
 - Standard: shell, read/write/edit files, web search/fetch, storage
-- Agents: spawn_agent, list_agents, kill_agent
+- Agents: spawn_agent, plan_workflow, list_agents, steer_agent, kill_agent, correct_tier (when the user says triage picked the wrong tier)
 - Bot management: assign_bot (add a scoped Telegram bot), list_bots, remove_bot
 - Memory: memory_search, memory_read, memory_write, memory_load_task, memory_create_task
-- Storage: storage_write, storage_read, storage_list, storage_delete
+- Storage: storage_write, storage_read, storage_list, storage_delete, send_file (deliver a stored file to the chat)
 
 Be concise. Use tools proactively. You ARE DevMan — act, don't instruct."#;
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::telegram::types::PhotoSize;
+use crate::types::{ContentBlock, ImageSource};
+
+/// API limit for a single base64 image
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+/// API rejects images with either side above this
+pub const MAX_IMAGE_DIMENSION: u32 = 8000;
+/// Keep PDFs well under the 32 MB request limit
+pub const MAX_PDF_BYTES: u64 = 20 * 1024 * 1024;
+/// Longer images get downscaled by the API anyway — no point sending more
+pub const PREFERRED_IMAGE_EDGE: u32 = 1568;
+
+/// Media types Claude accepts as content blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Pdf,
+}
+
+impl MediaType {
+    /// Detect from magic bytes — extensions and Telegram mime types lie
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else if bytes.starts_with(b"%PDF-") {
+            Some(Self::Pdf)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+            Self::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn is_image(self) -> bool {
+        self != Self::Pdf
+    }
+}
+
+/// Sniff a file's media type from its first bytes
+pub fn sniff_file(path: &Path) -> Option<MediaType> {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    let mut f = std::fs::File::open(path).ok()?;
+    let n = f.read(&mut header).ok()?;
+    MediaType::sniff(&header[..n])
+}
+
+/// Build an image or document block from a local file.
+/// Returns `Ok(None)` for file types Claude can't take as a block.
+pub fn file_block(path: &Path) -> Result<Option<(MediaType, ContentBlock)>> {
+    let Some(media_type) = sniff_file(path) else {
+        return Ok(None);
+    };
+
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .len();
+    let limit = if media_type.is_image() { MAX_IMAGE_BYTES } else { MAX_PDF_BYTES };
+    if size > limit {
+        anyhow::bail!(
+            "{} is {:.1} MB — the limit for {} is {} MB",
+            path.display(),
+            size as f64 / (1024.0 * 1024.0),
+            media_type.as_str(),
+            limit / (1024 * 1024)
+        );
+    }
+
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+    if media_type.is_image()
+        && let Some((w, h)) = image_dimensions(&bytes)
+        && (w > MAX_IMAGE_DIMENSION || h > MAX_IMAGE_DIMENSION)
+    {
+        anyhow::bail!(
+            "{} is {w}×{h} — images must be at most {MAX_IMAGE_DIMENSION}px per side",
+            path.display()
+        );
+    }
+
+    use base64::Engine;
+    let source = ImageSource {
+        source_type: "base64".into(),
+        media_type: media_type.as_str().into(),
+        data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+    };
+    let block = if media_type.is_image() {
+        ContentBlock::Image { source }
+    } else {
+        ContentBlock::Document { source }
+    };
+    Ok(Some((media_type, block)))
+}
+
+/// Read pixel dimensions from PNG, GIF or JPEG headers
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    let be16 = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]) as u32;
+    let le16 = |b: &[u8]| u16::from_le_bytes([b[0], b[1]]) as u32;
+
+    match MediaType::sniff(bytes)? {
+        MediaType::Png if bytes.len() >= 24 => Some((be32(&bytes[16..20]), be32(&bytes[20..24]))),
+        MediaType::Gif if bytes.len() >= 10 => Some((le16(&bytes[6..8]), le16(&bytes[8..10]))),
+        MediaType::Jpeg => {
+            // Walk segments until a start-of-frame marker
+            let mut i = 2;
+            while i + 9 < bytes.len() {
+                if bytes[i] != 0xFF {
+                    return None;
+                }
+                let marker = bytes[i + 1];
+                let len = be16(&bytes[i + 2..i + 4]) as usize;
+                let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+                if is_sof {
+                    return Some((be16(&bytes[i + 7..i + 9]), be16(&bytes[i + 5..i + 7])));
+                }
+                i += 2 + len;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Pick the largest Telegram photo size that stays within the preferred
+/// edge length and upload limit (sizes are ordered smallest first)
+pub fn pick_photo_size(sizes: &[PhotoSize]) -> Option<&PhotoSize> {
+    sizes
+        .iter()
+        .rev()
+        .find(|p| {
+            p.width.max(p.height) <= PREFERRED_IMAGE_EDGE
+                && p.file_size.unwrap_or(0) <= MAX_IMAGE_BYTES
+        })
+        .or_else(|| sizes.first())
+}
@@ -1,8 +1,10 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
-/// Search result from grep
+use crate::search;
+
+/// A line of a memory search
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub file: String,
@@ -25,32 +27,18 @@ impl MemoryManager {
         PathBuf::from(".devman/memory")
     }
 
-    /// Search memory files using grep -rni
+    /// Case-insensitive search of memory files for `query` as plain text
     pub fn search(&self, query: &str) -> Vec<SearchResult> {
-        let output = Command::new("grep")
-            .args(["-rni", query])
-            .arg(&self.root)
-            .output();
-
-        let output = match output {
-            Ok(o) => o,
-            Err(_) => return vec![],
+        let Ok(regex) = regex::RegexBuilder::new(&regex::escape(query)).case_insensitive(true).build() else {
+            return vec![];
         };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        stdout
-            .lines()
-            .filter_map(|line| {
-                // Format: file:line:text
-                let mut parts = line.splitn(3, ':');
-                let file = parts.next()?.to_string();
-                let line_num: u32 = parts.next()?.parse().ok()?;
-                let text = parts.next()?.to_string();
-                Some(SearchResult {
-                    file,
-                    line: line_num,
-                    text,
-                })
+        let filter = search::FileFilter::default();
+        let files = search::grep(&self.root, &regex, &filter, 0, search::Sort::Relevance).unwrap_or_default();
+        files
+            .into_iter()
+            .flat_map(|f| {
+                let file = f.path.display().to_string();
+                f.lines.into_iter().map(move |l| SearchResult { file: file.clone(), line: l.number as u32, text: l.text })
             })
             .collect()
     }
@@ -178,6 +166,28 @@ impl MemoryManager {
         Ok(format!("Created task: {filename}"))
     }
 
+    /// Slugs of all known tasks (tasks/<slug>.md or tasks/<slug>/)
+    pub fn list_tasks(&self) -> Vec<String> {
+        let mut slugs: Vec<String> = std::fs::read_dir(self.root.join("tasks"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| {
+                let path = e.path();
+                if path.is_dir() {
+                    path.file_name().map(|n| n.to_string_lossy().to_string())
+                } else if path.extension().is_some_and(|x| x == "md") {
+                    path.file_stem().map(|n| n.to_string_lossy().to_string())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        slugs.sort();
+        slugs.dedup();
+        slugs
+    }
+
     /// Get storage for a specific task (scoped to tasks/<slug>/storage/)
     pub fn task_storage(&self, task_slug: &str) -> TaskStorage {
         let storage_root = self.root.join("tasks").join(task_slug).join("storage");
@@ -235,6 +245,7 @@ impl MemoryManager {
 /// Scoped file storage for tasks.
 /// - Sub-agents get a TaskStorage rooted at their task's storage dir (can only see own files)
 /// - Manager gets a global TaskStorage that can traverse all task storage dirs
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskStorage {
     pub root: PathBuf,
     /// If true, this is manager-level (can access tasks/*/storage/ via task prefix)
@@ -304,6 +315,15 @@ impl TaskStorage {
         }
     }
 
+    /// Resolve a storage path to an existing file on disk (for uploads)
+    pub fn file_path(&self, path: &str) -> Result<PathBuf> {
+        let full = self.resolve(path)?;
+        if !full.is_file() {
+            anyhow::bail!("not found in storage: {path}");
+        }
+        Ok(full)
+    }
+
     /// List files in storage (optionally under a subdirectory)
     pub fn list_files(&self, subdir: Option<&str>) -> Result<Vec<String>> {
         let dir = match subdir {
@@ -1,31 +1,54 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use tokio::sync::mpsc;
 
 use crate::agent::{AgentLoop, TurnResult};
+use crate::ask::{Origin, QuestionStore, SuspendedRun};
+use crate::checkpoint::CheckpointStore;
 use crate::client::AnthropicClient;
 use crate::config::Config;
 use crate::context::ContextManager;
-use crate::cost::CostTracker;
+use crate::cost::{estimate_cost_usd, CostTracker};
+use crate::dashboard::api::AgentInfo;
+use crate::improve::ImprovementEngine;
+use crate::inbox::{Inbox, InboxMessage};
 use crate::memory::{MemoryManager, TaskStorage};
+use crate::shell_session::ShellSession;
 use crate::tools;
+use crate::tools::policy::ToolPolicy;
+use crate::triage::Triage;
 use crate::types::{Thinking, ToolDefinition, Usage};
+use crate::verify::{self, Verdict};
+use crate::worktree::{Worktree, WorktreeMode};
+use crate::workflow::{StepOutcome, Workflow, WorkflowRun};
 
 /// Sub-agent status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SubAgentStatus {
+    /// Waiting for a free slot in the pool
+    Queued,
     Running,
     WaitingForUser,
+    /// Finished, and its result is with the reviewer
+    Verifying,
     Complete,
     Failed(String),
     Killed,
 }
 
-/// A running or completed sub-agent
-#[derive(Debug)]
+impl SubAgentStatus {
+    /// Not finished yet: queued, working, or waiting on a user or a reviewer
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Queued | Self::Running | Self::WaitingForUser | Self::Verifying)
+    }
+}
+
+/// A running or completed sub-agent. Saved as `record.json` in its run
+/// directory, alongside the `conversation.json` checkpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubAgentRecord {
     pub run_id: String,
     pub task_id: String,
@@ -34,6 +57,112 @@ pub struct SubAgentRecord {
     pub started: chrono::DateTime<Utc>,
     pub output: Option<String>,
     pub usage: Usage,
+    /// What it was asked to do — rerun from scratch if it died before its first checkpoint
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub system_prompt: String,
+    #[serde(default)]
+    pub thinking: Thinking,
+    #[serde(default)]
+    pub origin: Option<Origin>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Tool allowlist (empty: every built-in tool)
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Allow/deny globs and read-only, on top of `tools`
+    #[serde(default)]
+    pub policy: ToolPolicy,
+    /// Storage to use instead of the task's own (workflow steps share one)
+    #[serde(default)]
+    pub storage: Option<TaskStorage>,
+    /// Triage decision that picked the model, if triage did
+    #[serde(default)]
+    pub decision_id: Option<String>,
+    /// Reviews its result has had
+    #[serde(default)]
+    pub verify_rounds: u32,
+    /// Its own git worktree, when `agents.worktree` isolates dev runs
+    #[serde(default)]
+    pub worktree: Option<Worktree>,
+    /// The workflow step it runs, if any — `task_id` is then `task:step`
+    #[serde(default)]
+    pub step: Option<String>,
+}
+
+impl SubAgentRecord {
+    /// The task it works on, which for a workflow step is the workflow's
+    pub fn task(&self) -> &str {
+        self.step
+            .as_ref()
+            .and_then(|step| self.task_id.strip_suffix(step.as_str()))
+            .and_then(|rest| rest.strip_suffix(':'))
+            .unwrap_or(&self.task_id)
+    }
+}
+
+/// What a sub-agent is asked to do, for `spawn` and `spawn_triaged`
+#[derive(Debug, Clone, Default)]
+pub struct SpawnOptions {
+    /// The task it works on — `spawn_triaged` picks one when empty, and
+    /// anything still untagged gets a task of its own
+    pub task_id: String,
+    pub message: String,
+    pub system_prompt: String,
+    pub thinking: Thinking,
+    /// Where its `ask_user` questions go
+    pub origin: Option<Origin>,
+    pub priority: Priority,
+    /// Tool allowlist (empty: every built-in tool)
+    pub tools: Vec<String>,
+    pub policy: ToolPolicy,
+}
+
+/// Who asked for a sub-agent. Queued agents start in this order, most urgent first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// A manager request — someone is waiting on it
+    Manager,
+    /// A scheduled cron job
+    Cron,
+    /// Anything else that can wait
+    #[default]
+    Background,
+}
+
+impl Priority {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "manager" => Some(Self::Manager),
+            "cron" => Some(Self::Cron),
+            "background" => Some(Self::Background),
+            _ => None,
+        }
+    }
+}
+
+/// What to do on startup with sub-agents the last process left running (`agents.recovery`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recovery {
+    /// Carry on from the last checkpointed context
+    Resume,
+    /// Mark them failed and tell the manager
+    Report,
+    /// Mark them killed and say nothing
+    Discard,
+}
+
+impl Recovery {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "resume" => Ok(Self::Resume),
+            "report" => Ok(Self::Report),
+            "discard" => Ok(Self::Discard),
+            other => anyhow::bail!("unknown agents.recovery mode: {other} (expected resume, report or discard)"),
+        }
+    }
 }
 
 /// Messages from sub-agent → orchestrator
@@ -50,11 +179,34 @@ pub enum SubAgentMessage {
         run_id: String,
         error: String,
     },
+    /// Sub-agent asked to be handed to a stronger model
+    Escalate {
+        run_id: String,
+        reason: String,
+    },
     /// Progress update
     Progress {
         run_id: String,
         text: String,
     },
+    /// Sub-agent was cut off by a restart and won't be resumed
+    Interrupted {
+        run_id: String,
+        task_id: String,
+    },
+    /// A reviewer has judged a sub-agent's result
+    Verdict {
+        run_id: String,
+        verdict: Verdict,
+    },
+    /// Every step of a workflow has finished, failed or been skipped
+    WorkflowDone {
+        workflow_id: String,
+        success: bool,
+        summary: String,
+        /// Who started it
+        origin: Option<Origin>,
+    },
 }
 
 /// Sub-agent pool — spawn, track, and manage worker agents
@@ -65,7 +217,22 @@ pub struct Orchestrator {
     github_token: Option<String>,
     pub cost_tracker: CostTracker,
     pub agents: HashMap<String, SubAgentRecord>,
+    /// Run ids waiting for a slot, most urgent first, and whether each resumes from a checkpoint
+    queue: Vec<(String, bool)>,
+    /// Workflows with steps still to run
+    workflows: Vec<WorkflowRun>,
+    workflows_dir: PathBuf,
+    /// Where per-run git worktrees go
+    worktrees_dir: PathBuf,
     state_dir: PathBuf,
+    /// Routing decisions and outcomes (`model-stats.json`)
+    improve: Option<ImprovementEngine>,
+    /// Launched loops, so `kill` can stop them
+    running: HashMap<String, tokio::task::AbortHandle>,
+    /// Answers for runs the last process left waiting in `ask_user`: the tool use id and its result
+    answers: HashMap<String, (String, String)>,
+    /// Its own news — recovery and finished workflows — delivered ahead of the channel
+    reports: VecDeque<SubAgentMessage>,
     result_rx: mpsc::Receiver<SubAgentMessage>,
     result_tx: mpsc::Sender<SubAgentMessage>,
 }
@@ -77,6 +244,7 @@ impl Orchestrator {
             .join("devman")
             .join("agents");
         let (result_tx, result_rx) = mpsc::channel(32);
+        let improve = state_dir.parent().and_then(Self::load_improve);
         Self {
             config,
             api_key,
@@ -84,79 +252,338 @@ impl Orchestrator {
             github_token,
             cost_tracker: CostTracker::new(),
             agents: HashMap::new(),
+            queue: Vec::new(),
+            workflows: Vec::new(),
+            workflows_dir: WorkflowRun::default_dir(),
+            worktrees_dir: state_dir.parent().unwrap_or(&state_dir).join("worktrees"),
             state_dir,
+            improve,
+            running: HashMap::new(),
+            answers: HashMap::new(),
+            reports: VecDeque::new(),
             result_rx,
             result_tx,
         }
     }
 
-    /// Spawn a sub-agent for a task
-    pub async fn spawn(
-        &mut self,
-        task_id: &str,
-        message: &str,
-        model: &str,
-        system_prompt: &str,
-        thinking: Thinking,
-    ) -> Result<String> {
-        let run_id = format!(
-            "run-{}-{}",
-            Utc::now().format("%Y%m%d-%H%M%S"),
-            &task_id[..task_id.len().min(20)]
-        );
+    /// Keep run directories and routing stats somewhere other than the DevMan data directory
+    pub fn with_state_dir(mut self, state_dir: PathBuf) -> Self {
+        self.workflows_dir = state_dir.join("workflows");
+        self.worktrees_dir = state_dir.join("worktrees");
+        self.improve = Self::load_improve(&state_dir);
+        self.state_dir = state_dir;
+        self
+    }
 
-        // Create state directory
-        let run_dir = self.state_dir.join(&run_id);
-        std::fs::create_dir_all(&run_dir)?;
+    fn load_improve(dir: &std::path::Path) -> Option<ImprovementEngine> {
+        ImprovementEngine::load(dir)
+            .map_err(|e| tracing::warn!("Routing stats unavailable: {e}"))
+            .ok()
+    }
 
+    /// Spawn a sub-agent on `model`, or queue it if the pool is full or another
+    /// agent is already working on the task
+    pub async fn spawn(&mut self, model: &str, mut opts: SpawnOptions) -> Result<String> {
+        if opts.task_id.is_empty() {
+            // Its own task, so it isn't held up behind every other untagged run
+            let id = uuid::Uuid::new_v4().simple().to_string();
+            opts.task_id = format!("adhoc-{}", &id[..8]);
+        }
+        // Path-safe, and unique even for two spawns on one task in the same second
+        let slug: String = opts
+            .task_id
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .take(20)
+            .collect();
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let run_id = format!("run-{}-{slug}-{}", Utc::now().format("%Y%m%d-%H%M%S"), &suffix[..8]);
         let record = SubAgentRecord {
             run_id: run_id.clone(),
-            task_id: task_id.to_string(),
+            task_id: opts.task_id,
             model: model.to_string(),
-            status: SubAgentStatus::Running,
+            status: SubAgentStatus::Queued,
             started: Utc::now(),
             output: None,
             usage: Usage::default(),
+            message: opts.message,
+            system_prompt: opts.system_prompt,
+            thinking: opts.thinking,
+            origin: opts.origin,
+            priority: opts.priority,
+            tools: opts.tools,
+            policy: opts.policy,
+            storage: None,
+            decision_id: None,
+            verify_rounds: 0,
+            worktree: None,
+            step: None,
+        };
+        self.submit(record)?;
+
+        Ok(run_id)
+    }
+
+    /// Decide the model tier, effort and relevant tasks and memory for a request,
+    /// using the manager model
+    pub async fn triage(&self, message: &str) -> Triage {
+        let known_tasks = MemoryManager::new(MemoryManager::default_root()).list_tasks();
+        let mut client = AnthropicClient::new(self.api_key.clone());
+        Triage::classify(&mut client, &self.config.models.manager, message, &known_tasks).await
+    }
+
+    /// Spawn a sub-agent with the model triage picks, briefed with the tasks
+    /// and memory triage found. Triage also picks the task when `opts` has none.
+    /// The decision is logged so its outcome can be scored.
+    pub async fn spawn_triaged(&mut self, mut opts: SpawnOptions) -> Result<String> {
+        let triage = self.triage(&opts.message).await;
+        let model = self.model_for_complexity(triage.tier).to_string();
+        if opts.task_id.is_empty()
+            && let Some(task) = triage.tasks.first()
+        {
+            opts.task_id = task.clone();
+        }
+        let usage = &triage.usage;
+        let task = Some(opts.task_id.as_str()).filter(|t| !t.is_empty());
+        self.cost_tracker.record(&self.config.models.manager, task, usage.input_tokens, usage.output_tokens, 0, 0);
+        let briefing = triage.briefing(&MemoryManager::new(MemoryManager::default_root()));
+        tracing::info!("Triage: {:?}/{:?} → {model} ({})", triage.tier, triage.effort, triage.reason);
+
+        let request = opts.message.clone();
+        opts.message = format!("{briefing}{request}");
+        let run_id = self.spawn(&model, opts).await?;
+        let decision_id = self.improve.as_mut().map(|engine| engine.record_triage(&request, &triage, &model));
+        self.save_improve();
+        if let Some(record) = self.agents.get_mut(&run_id) {
+            record.decision_id = decision_id;
+            let record = record.clone();
+            self.save_record(&record);
+        }
+        Ok(run_id)
+    }
+
+    /// The user says a triaged run needed a different tier
+    pub fn correct_tier(&mut self, run_id: &str, tier: TaskComplexity) -> Result<()> {
+        let decision_id = self
+            .agents
+            .get(run_id)
+            .and_then(|r| r.decision_id.clone())
+            .ok_or_else(|| anyhow::anyhow!("{run_id} wasn't routed by triage"))?;
+        if let Some(engine) = self.improve.as_mut() {
+            engine.record_correction(&decision_id, tier);
+        }
+        self.save_improve();
+        Ok(())
+    }
+
+    /// Score the triage decision behind a finished run
+    fn record_routing_outcome(&mut self, record: &SubAgentRecord, success: bool) {
+        let (Some(id), Some(engine)) = (&record.decision_id, self.improve.as_mut()) else {
+            return;
         };
+        let cost = estimate_cost_usd(&record.model, record.usage.input_tokens, record.usage.output_tokens, 0, 0);
+        engine.record_outcome(id, cost, success);
+        self.save_improve();
+    }
+
+    fn save_improve(&self) {
+        if let Some(Err(e)) = self.improve.as_ref().map(ImprovementEngine::save) {
+            tracing::warn!("Failed to save routing stats: {e}");
+        }
+    }
+
+    /// Routing accuracy so far
+    pub fn routing_summary(&self) -> String {
+        self.improve
+            .as_ref()
+            .map(ImprovementEngine::routing_summary)
+            .unwrap_or_else(|| "Routing stats unavailable.".into())
+    }
+
+    /// Create the run directory (and worktree, for isolated dev runs) and queue the run
+    fn submit(&mut self, mut record: SubAgentRecord) -> Result<()> {
+        std::fs::create_dir_all(self.state_dir.join(&record.run_id))?;
+        if WorktreeMode::parse(&self.config.agents.worktree)? != WorktreeMode::Off
+            && let Some(repo) = self.config.dev_bot(record.task()).and_then(|b| b.working_directory.clone())
+        {
+            let worktree = Worktree::create(std::path::Path::new(&repo), &self.worktrees_dir, &record.run_id)
+                .with_context(|| format!("creating a worktree of {repo}"))?;
+            record.worktree = Some(worktree);
+        }
+        let run_id = record.run_id.clone();
         self.agents.insert(run_id.clone(), record);
+        self.enqueue(&run_id, false);
+        Ok(())
+    }
+
+    /// Add a run to the queue behind everything at least as urgent, then start what fits
+    fn enqueue(&mut self, run_id: &str, resume: bool) {
+        let Some(record) = self.agents.get_mut(run_id) else { return };
+        record.status = SubAgentStatus::Queued;
+        let priority = record.priority;
+        let at = self
+            .queue
+            .iter()
+            .position(|(id, _)| self.agents.get(id).is_some_and(|r| r.priority > priority))
+            .unwrap_or(self.queue.len());
+        self.queue.insert(at, (run_id.to_string(), resume));
+        self.drain_queue();
+    }
 
-        // Spawn the agent loop in a background task
+    /// Start queued agents while there are free slots. Agents waiting on
+    /// `ask_user` don't hold a slot, but still count against their task.
+    fn drain_queue(&mut self) {
+        let max = self.config.agents.max_concurrent.max(1) as usize;
+        let per_task = self.config.agents.max_per_task.max(1) as usize;
+        let mut i = 0;
+        while i < self.queue.len() {
+            let running = self.agents.values().filter(|a| a.status == SubAgentStatus::Running).count();
+            if running >= max {
+                break;
+            }
+            let (run_id, resume) = self.queue[i].clone();
+            let Some(record) = self.agents.get(&run_id) else {
+                self.queue.remove(i);
+                continue;
+            };
+            // Workflow steps count against their workflow's task: they share its
+            // storage. Steps of the same run don't hold each other up — their
+            // outputs are disjoint, and independent ones are meant to run in parallel.
+            let workflow = self.workflow_step(&run_id).map(|(w, _)| w);
+            let on_task = self
+                .agents
+                .values()
+                .filter(|a| a.task() == record.task())
+                .filter(|a| workflow.is_none() || self.workflow_step(&a.run_id).map(|(w, _)| w) != workflow)
+                .filter(|a| matches!(a.status, SubAgentStatus::Running | SubAgentStatus::WaitingForUser | SubAgentStatus::Verifying))
+                .count();
+            if on_task >= per_task {
+                // Blocked on its task — let later runs for other tasks go ahead
+                i += 1;
+                continue;
+            }
+            self.queue.remove(i);
+            let record = self.agents.get_mut(&run_id).expect("checked above");
+            record.status = SubAgentStatus::Running;
+            let record = record.clone();
+            self.launch(&record, resume);
+        }
+        for (run_id, _) in &self.queue {
+            if let Some(record) = self.agents.get(run_id) {
+                self.save_record(record);
+            }
+        }
+    }
+
+    /// 1-based place in the queue, if the run is waiting for a slot
+    pub fn queue_position(&self, run_id: &str) -> Option<usize> {
+        self.queue.iter().position(|(id, _)| id == run_id).map(|i| i + 1)
+    }
+
+    /// Run a sub-agent's loop in a background task — from its message, or
+    /// with `resume`, from its last checkpoint or the answer to its question
+    fn launch(&mut self, record: &SubAgentRecord, resume: bool) {
+        self.save_record(record);
+        self.running.retain(|_, task| !task.is_finished());
+        let answer = self.answers.remove(&record.run_id).filter(|_| resume);
+        let run_dir = self.state_dir.join(&record.run_id);
         let client = AnthropicClient::new(self.api_key.clone());
         let context = ContextManager::with_persistence(run_dir.join("conversation.json"));
-        let tool_defs = tools::builtin_tool_definitions(self.config.tools.web_enabled, self.config.github.is_some());
+        let resume = resume && !context.messages.is_empty();
+        let mut tool_defs = tools::builtin_tool_definitions(self.config.tools.web_enabled, self.config.github.is_some());
+        tool_defs.push(tools::agents::message_agent_definition());
+        if self.tier_for_model(&record.model).and_then(TaskComplexity::escalate).is_some() {
+            tool_defs.push(tools::agents::request_escalation_definition());
+        }
+        // The allowlist rides on the loop's policy, so a run resumed after
+        // `ask_user` gets the same tools; the agent tools come with any allowlist
+        let mut allowlist = record.tools.clone();
+        if !allowlist.is_empty() {
+            allowlist.extend(["message_agent".to_string(), "request_escalation".to_string()]);
+        }
+        let policy = record.policy.within(&allowlist);
+        let custom_tools = tools::custom::load_custom_tools(&self.config);
+        let inbox = Inbox::new(self.state_dir.clone(), &record.run_id);
+        let mut system_prompt = match &record.worktree {
+            Some(worktree) => format!("{}\n\n{}", record.system_prompt, worktree.prompt()),
+            None => record.system_prompt.clone(),
+        };
+        // Dev work starts with a map of the project instead of a round of ls
+        let project = match &record.worktree {
+            Some(worktree) => Some(worktree.path.clone()),
+            None => self.config.dev_bot(record.task()).and_then(|b| b.working_directory.as_ref()).map(PathBuf::from),
+        };
+        if let Some(dir) = project.filter(|_| self.config.tools.repo_map_tokens > 0)
+            && let Ok(map) = crate::repomap::repo_map(&dir, self.config.tools.repo_map_tokens)
+        {
+            system_prompt.push_str(&format!("\n\n## Repository Map\n{map}"));
+        }
         let brave_key = self.brave_api_key.clone();
         let gh_token = self.github_token.clone();
         let max_turns = self.config.agents.max_turns;
         let max_tokens = self.config.agents.max_tokens;
-        let model_owned = model.to_string();
-        let system_owned = system_prompt.to_string();
-        let message_owned = message.to_string();
-        let run_id_clone = run_id.clone();
+        let checkpoint_interval = self.config.agents.checkpoint_interval;
+        let shell_sessions = self.config.tools.shell_sessions;
+        let record = record.clone();
         let tx = self.result_tx.clone();
 
         // Create scoped storage for this sub-agent (task_id as slug)
-        let task_slug = task_id
+        let task_slug = record
+            .task_id
             .to_lowercase()
             .replace(' ', "-")
             .replace(|c: char| !c.is_alphanumeric() && c != '-', "");
-        let mm = MemoryManager::new(MemoryManager::default_root());
-        let task_storage = mm.task_storage(&task_slug);
+        let task_storage = record
+            .storage
+            .clone()
+            .unwrap_or_else(|| MemoryManager::new(MemoryManager::default_root()).task_storage(&task_slug));
 
-        tokio::spawn(async move {
+        let run_id = record.run_id.clone();
+        let task = tokio::spawn(async move {
             let mut agent = AgentLoop::new(
                 client,
                 context,
-                model_owned,
-                system_owned,
+                record.model.clone(),
+                system_prompt,
                 tool_defs,
                 max_turns,
                 max_tokens,
-                thinking,
+                record.thinking,
                 brave_key,
                 gh_token,
-            ).with_storage(task_storage);
+            )
+            .with_custom_tools(custom_tools)
+            .with_tool_policy(policy)
+            .with_storage(task_storage)
+            .with_run_id(record.run_id.clone())
+            .with_checkpoint_interval(checkpoint_interval)
+            .with_inbox(inbox)
+            .with_file_checkpoints(CheckpointStore::open(run_dir.join("checkpoints")));
+            if let Some(origin) = record.origin.clone() {
+                agent = agent.with_origin(origin);
+            }
+            if let Some(worktree) = record.worktree.clone() {
+                agent = agent.with_worktree(worktree);
+            }
+            if shell_sessions {
+                agent = agent.with_shell_session(ShellSession::new());
+            }
 
-            match agent.run_turn(&message_owned).await {
+            let result = match answer {
+                Some((tool_use_id, answer)) => agent.resume(&tool_use_id, &answer).await,
+                None if resume => agent.continue_run().await,
+                None => agent.run_turn(&record.message).await,
+            };
+            match result {
+                Ok(result) if result.escalation.is_some() => {
+                    let _ = tx
+                        .send(SubAgentMessage::Escalate {
+                            run_id: record.run_id,
+                            reason: result.escalation.unwrap_or_default(),
+                        })
+                        .await;
+                }
                 Ok(result) => {
                     // Save output
                     let output_path = run_dir.join("output.md");
@@ -164,7 +591,7 @@ impl Orchestrator {
 
                     let _ = tx
                         .send(SubAgentMessage::Done {
-                            run_id: run_id_clone,
+                            run_id: record.run_id,
                             output: result.text,
                             usage: result.usage,
                         })
@@ -173,104 +600,695 @@ impl Orchestrator {
                 Err(e) => {
                     let _ = tx
                         .send(SubAgentMessage::Error {
-                            run_id: run_id_clone,
+                            run_id: record.run_id,
                             error: e.to_string(),
                         })
                         .await;
                 }
             }
         });
+        self.running.insert(run_id, task.abort_handle());
+    }
 
-        Ok(run_id)
+    fn save_record(&self, record: &SubAgentRecord) {
+        let path = self.state_dir.join(&record.run_id).join("record.json");
+        let result = serde_json::to_string_pretty(record)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&path, json)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save sub-agent record {}: {e}", record.run_id);
+        }
     }
 
-    /// Check for completed sub-agents (non-blocking)
+    /// Records of every run in the state directory, oldest first
+    pub fn load_records(&self) -> Vec<SubAgentRecord> {
+        let Ok(entries) = std::fs::read_dir(&self.state_dir) else {
+            return Vec::new();
+        };
+        let mut records: Vec<SubAgentRecord> = entries
+            .flatten()
+            .filter_map(|e| std::fs::read_to_string(e.path().join("record.json")).ok())
+            .filter_map(|c| serde_json::from_str(&c).ok())
+            .collect();
+        records.sort_by_key(|r| r.started);
+        records
+    }
+
+    /// Deal with sub-agents the last process left running, per `agents.recovery`.
+    /// Runs suspended in `ask_user` are left to the question store, which
+    /// resumes them once answered. Returns the runs that were interrupted.
+    pub fn recover(&mut self) -> Result<Vec<SubAgentRecord>> {
+        let mode = Recovery::parse(&self.config.agents.recovery)?;
+        let suspended: Vec<String> = QuestionStore::open()
+            .list()
+            .into_iter()
+            .filter_map(|q| q.run.map(|r| r.run_id))
+            .collect();
+
+        let mut interrupted = Vec::new();
+        for mut record in self.load_records() {
+            if !record.status.is_active() {
+                continue;
+            }
+            if suspended.contains(&record.run_id) {
+                record.status = SubAgentStatus::WaitingForUser;
+                self.agents.insert(record.run_id.clone(), record);
+                continue;
+            }
+            match mode {
+                Recovery::Resume => {
+                    tracing::info!("Resuming sub-agent {}", record.run_id);
+                    let run_id = record.run_id.clone();
+                    interrupted.push(record.clone());
+                    self.agents.insert(run_id.clone(), record);
+                    self.enqueue(&run_id, true);
+                    continue;
+                }
+                Recovery::Report => {
+                    record.status = SubAgentStatus::Failed("Interrupted by a restart".into());
+                    self.reports.push_back(SubAgentMessage::Interrupted {
+                        run_id: record.run_id.clone(),
+                        task_id: record.task_id.clone(),
+                    });
+                }
+                Recovery::Discard => record.status = SubAgentStatus::Killed,
+            }
+            self.save_record(&record);
+            interrupted.push(record.clone());
+            self.agents.insert(record.run_id.clone(), record);
+        }
+
+        // Unfinished workflows carry on when their steps do, and are written off otherwise
+        for mut run in WorkflowRun::list(&self.workflows_dir).into_iter().filter(|r| !r.is_finished()) {
+            if mode == Recovery::Resume {
+                self.workflows.push(run);
+                let w = self.workflows.len() - 1;
+                // Steps whose agent isn't coming back count as failed attempts
+                let lost: Vec<(usize, String)> = self.workflows[w]
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.status == crate::workflow::StepStatus::Running)
+                    .filter_map(|(i, s)| s.run_id.clone().map(|id| (i, id)))
+                    .filter(|(_, id)| !self.agents.contains_key(id))
+                    .collect();
+                for (i, _) in lost {
+                    self.workflows[w].step_finished(i, Err("Interrupted by a restart".into()));
+                }
+                self.advance_workflow(w);
+                continue;
+            }
+            run.abandon("Interrupted by a restart");
+            if let Err(e) = run.save(&self.workflows_dir) {
+                tracing::warn!("Failed to save workflow {}: {e}", run.id);
+            }
+            if mode == Recovery::Report {
+                self.reports.push_back(SubAgentMessage::WorkflowDone {
+                    workflow_id: run.id.clone(),
+                    success: false,
+                    summary: run.summary(),
+                    origin: run.origin.clone(),
+                });
+            }
+        }
+        self.prune_worktrees();
+        Ok(interrupted)
+    }
+
+    /// Check for completed sub-agents (non-blocking). Workflow steps are
+    /// handled here; the manager hears about the workflow as a whole.
     pub fn try_recv(&mut self) -> Option<SubAgentMessage> {
-        match self.result_rx.try_recv() {
-            Ok(msg) => {
-                // Update agent record
-                match &msg {
-                    SubAgentMessage::Done {
-                        run_id,
-                        output,
-                        usage,
-                    } => {
-                        if let Some(record) = self.agents.get_mut(run_id) {
-                            record.status = SubAgentStatus::Complete;
-                            record.output = Some(output.clone());
-                            record.usage = usage.clone();
-                            // Track cost
-                            self.cost_tracker.record(
-                                &record.model,
-                                Some(&record.task_id),
-                                usage.input_tokens,
-                                usage.output_tokens,
-                                0,
-                                0,
-                            );
-                        }
+        self.sync_waiting();
+        loop {
+            let next = match self.reports.pop_front() {
+                Some(report) => report,
+                None => self.result_rx.try_recv().ok()?,
+            };
+            let msg = match next {
+                SubAgentMessage::Done { run_id, output, usage } => match self.agent_done(run_id, output, usage) {
+                    Some(done) => done,
+                    None => {
+                        self.drain_queue();
+                        continue;
+                    }
+                },
+                SubAgentMessage::Verdict { run_id, verdict } => match self.verdict(&run_id, verdict) {
+                    Some(done) => done,
+                    None => {
+                        self.drain_queue();
+                        continue;
                     }
-                    SubAgentMessage::Error { run_id, error } => {
-                        if let Some(record) = self.agents.get_mut(run_id) {
-                            record.status = SubAgentStatus::Failed(error.clone());
-                        }
+                },
+                msg => msg,
+            };
+            // Update agent record
+            match &msg {
+                // Whatever a killed run got out before it was stopped
+                SubAgentMessage::Error { run_id, .. }
+                | SubAgentMessage::Escalate { run_id, .. }
+                | SubAgentMessage::Progress { run_id, .. }
+                    if self.is_killed(run_id) =>
+                {
+                    continue;
+                }
+                SubAgentMessage::Error { run_id, error } => {
+                    if let Some(record) = self.agents.get_mut(run_id) {
+                        record.status = SubAgentStatus::Failed(error.clone());
+                        let record = record.clone();
+                        self.save_record(&record);
+                        self.record_routing_outcome(&record, false);
+                    }
+                }
+                SubAgentMessage::Escalate { run_id, reason } => self.escalate(run_id, reason),
+                _ => {}
+            }
+            let step = self.workflow_step_finished(&msg);
+            self.drain_queue();
+            if !step {
+                return Some(msg);
+            }
+        }
+    }
+
+    /// A sub-agent finished a turn. Returns the `Done` to report, or `None`
+    /// if its result went to the reviewer first.
+    fn agent_done(&mut self, run_id: String, output: String, usage: Usage) -> Option<SubAgentMessage> {
+        let Some(record) = self.agents.get_mut(&run_id) else {
+            return Some(SubAgentMessage::Done { run_id, output, usage });
+        };
+        if record.status == SubAgentStatus::Killed {
+            return None;
+        }
+        // Revision rounds add to what the run has already used
+        record.usage.input_tokens += usage.input_tokens;
+        record.usage.output_tokens += usage.output_tokens;
+        record.output = Some(output.clone());
+        let record = record.clone();
+        self.cost_tracker.record(&record.model, Some(&record.task_id), usage.input_tokens, usage.output_tokens, 0, 0);
+
+        let verify = &self.config.verify;
+        if verify::applies(verify, record.task(), self.tier_for_model(&record.model)) && record.verify_rounds < verify.max_rounds {
+            self.start_review(&run_id);
+            return None;
+        }
+        Some(self.complete(&run_id, output, true))
+    }
+
+    /// Mark a run complete with its final output
+    fn complete(&mut self, run_id: &str, output: String, success: bool) -> SubAgentMessage {
+        let Some(record) = self.agents.get_mut(run_id) else {
+            return SubAgentMessage::Done { run_id: run_id.to_string(), output, usage: Usage::default() };
+        };
+        record.status = SubAgentStatus::Complete;
+        let record = record.clone();
+        let output = match self.finish_worktree(&record) {
+            Some(note) => format!("{output}\n\n[{note}]"),
+            None => output,
+        };
+        if let Some(record) = self.agents.get_mut(run_id) {
+            record.output = Some(output.clone());
+        }
+        let record = self.agents[run_id].clone();
+        self.save_record(&record);
+        self.record_routing_outcome(&record, success);
+        SubAgentMessage::Done { run_id: run_id.to_string(), output, usage: record.usage }
+    }
+
+    /// Merge, push for a PR, keep or discard a finished run's worktree branch,
+    /// per `agents.worktree`. Returns a note on what happened.
+    fn finish_worktree(&self, record: &SubAgentRecord) -> Option<String> {
+        let worktree = record.worktree.as_ref()?;
+        let mode = WorktreeMode::parse(&self.config.agents.worktree).unwrap_or(WorktreeMode::Keep);
+        let branch = &worktree.branch;
+        let result = worktree
+            .commit_all(&format!("Uncommitted work from {}", record.run_id))
+            .and_then(|_| worktree.commits_ahead());
+        let note = match (result, mode) {
+            (Err(e), _) => {
+                let _ = worktree.remove(false);
+                format!("Couldn't wrap up {branch}: {e} — the branch is kept")
+            }
+            (Ok(0), _) => {
+                let _ = worktree.remove(true);
+                "No changes were made".into()
+            }
+            (Ok(_), WorktreeMode::Merge) => match worktree.merge() {
+                Ok(()) => {
+                    let _ = worktree.remove(true);
+                    format!("Merged {branch} into {}", worktree.base_branch)
+                }
+                Err(e) => {
+                    let _ = worktree.remove(false);
+                    format!("Couldn't merge {branch}: {e} — the branch is kept")
+                }
+            },
+            (Ok(_), WorktreeMode::Pr) => {
+                let _ = worktree.remove(false);
+                match worktree.push() {
+                    Ok(()) => {
+                        self.open_pull_request(record);
+                        format!("Pushed {branch}; opening a pull request into {}", worktree.base_branch)
                     }
-                    _ => {}
+                    Err(e) => format!("Couldn't push {branch}: {e} — the branch is kept"),
+                }
+            }
+            (Ok(_), WorktreeMode::Discard) => {
+                let _ = worktree.remove(true);
+                format!("Discarded {branch}")
+            }
+            (Ok(_), WorktreeMode::Keep | WorktreeMode::Off) => {
+                let _ = worktree.remove(false);
+                format!("The work is on branch {branch}")
+            }
+        };
+        tracing::info!("{}: {note}", record.run_id);
+        Some(note)
+    }
+
+    /// Open a PR for a pushed worktree branch; the link comes back as progress
+    fn open_pull_request(&self, record: &SubAgentRecord) {
+        let Some(worktree) = record.worktree.clone() else { return };
+        let Some(repo) = tools::github::detect_repo_at(Some(&worktree.repo)) else {
+            tracing::warn!("{}: no GitHub remote to open a PR on", record.run_id);
+            return;
+        };
+        let title: String = record.message.lines().last().unwrap_or_default().chars().take(70).collect();
+        let input = serde_json::json!({
+            "repo": format!("{}/{}", repo.0, repo.1),
+            "title": title,
+            "head": worktree.branch,
+            "base": worktree.base_branch,
+            "body": record.output.clone().unwrap_or_default(),
+        });
+        let token = self.github_token.clone();
+        let run_id = record.run_id.clone();
+        let tx = self.result_tx.clone();
+        tokio::spawn(async move {
+            let text = match tools::github::github_pr_create_execute(&input, token.as_deref()).await {
+                Ok(created) => created,
+                Err(e) => format!("Couldn't open a PR for {}: {e}", worktree.branch),
+            };
+            let _ = tx.send(SubAgentMessage::Progress { run_id, text }).await;
+        });
+    }
+
+    /// Remove worktrees left by runs that are no longer active. Branches with
+    /// commits are kept; empty ones go too.
+    pub fn prune_worktrees(&self) {
+        for record in self.load_records() {
+            let Some(worktree) = record.worktree.as_ref().filter(|w| w.path.exists()) else { continue };
+            if self.agents.get(&record.run_id).is_some_and(|r| r.status.is_active()) {
+                continue;
+            }
+            let empty = worktree.commits_ahead().is_ok_and(|n| n == 0);
+            match worktree.remove(empty) {
+                Ok(()) => tracing::info!("Removed stale worktree {}", worktree.path.display()),
+                Err(e) => tracing::warn!("Failed to remove worktree {}: {e}", worktree.path.display()),
+            }
+        }
+    }
+
+    /// Hand a finished run's result to the reviewer, running the task's tests first if it has any
+    fn start_review(&mut self, run_id: &str) {
+        let Some(record) = self.agents.get_mut(run_id) else { return };
+        record.status = SubAgentStatus::Verifying;
+        record.verify_rounds += 1;
+        let record = record.clone();
+        self.save_record(&record);
+
+        let mut spec = verify::test_spec(&self.config, record.task());
+        if let (Some(spec), Some(worktree)) = (spec.as_mut(), &record.worktree) {
+            spec.dir = worktree.path.clone();
+        }
+        let timeout = self.config.verify.test_timeout;
+        let model = self.config.verify.model.clone().unwrap_or_else(|| self.config.models.complex.clone());
+        let api_key = self.api_key.clone();
+        let tx = self.result_tx.clone();
+        tokio::spawn(async move {
+            let tests = match &spec {
+                Some(spec) => Some(verify::run_tests(spec, timeout).await),
+                None => None,
+            };
+            let output = record.output.unwrap_or_default();
+            let verdict = Verdict::review(&api_key, &model, &record.message, &output, tests, record.verify_rounds).await;
+            let _ = tx.send(SubAgentMessage::Verdict { run_id: record.run_id, verdict }).await;
+        });
+    }
+
+    /// A review came back: report the result, or send it back to its agent with
+    /// the feedback. Returns the `Done` to report, if any.
+    fn verdict(&mut self, run_id: &str, verdict: Verdict) -> Option<SubAgentMessage> {
+        if self.is_killed(run_id) {
+            return None;
+        }
+        let run_dir = self.state_dir.join(run_id);
+        if let Err(e) = verdict.record(&run_dir) {
+            tracing::warn!("Failed to record verdict for {run_id}: {e}");
+        }
+        let record = self.agents.get(run_id)?.clone();
+        self.cost_tracker.record(&verdict.model, Some(&record.task_id), verdict.usage.input_tokens, verdict.usage.output_tokens, 0, 0);
+        if record.status != SubAgentStatus::Verifying {
+            return None;
+        }
+
+        let output = record.output.clone().unwrap_or_default();
+        if verdict.approved {
+            return Some(self.complete(run_id, output, true));
+        }
+        if record.verify_rounds >= self.config.verify.max_rounds {
+            let output = format!(
+                "{output}\n\n[Not approved after {} review round(s): {}]",
+                record.verify_rounds, verdict.feedback
+            );
+            return Some(self.complete(run_id, output, false));
+        }
+
+        // Another round: the feedback goes on the end of its conversation
+        let mut context = ContextManager::with_persistence(run_dir.join("conversation.json"));
+        context.add_user_message(&verdict.revision_request());
+        if let Err(e) = context.save() {
+            tracing::warn!("Couldn't send {run_id} back for revision: {e}");
+            return Some(self.complete(run_id, output, false));
+        }
+        tracing::info!("Reviewer sent {run_id} back (round {})", verdict.round);
+        self.enqueue(run_id, true);
+        None
+    }
+
+    // ── Workflows ───────────────────────────────────────────────────
+
+    /// Start a workflow: validate it, then spawn every step that has no
+    /// dependencies. Returns the workflow run id. Steps run under `policy`, the tool policy of whoever started it.
+    pub fn start_workflow(&mut self, workflow: Workflow, priority: Priority, origin: Option<Origin>, policy: ToolPolicy) -> Result<String> {
+        workflow.validate()?;
+        let mut run = WorkflowRun::new(workflow);
+        run.priority = priority;
+        run.origin = origin;
+        run.policy = policy;
+        let id = run.id.clone();
+        self.workflows.push(run);
+        self.advance_workflow(self.workflows.len() - 1);
+        Ok(id)
+    }
+
+    /// Handle the `plan_workflow` tool for an agent at `origin` running under `policy`
+    pub fn plan_workflow(&mut self, input: &serde_json::Value, origin: Option<Origin>, policy: ToolPolicy) -> Result<String> {
+        let workflow: Workflow = serde_json::from_value(input.clone())?;
+        let priority = input["priority"].as_str().and_then(Priority::parse).unwrap_or(Priority::Manager);
+        let id = self.start_workflow(workflow, priority, origin, policy)?;
+        let run = self.workflows.iter().find(|w| w.id == id);
+        Ok(run.map_or_else(|| format!("Workflow {id} finished immediately."), |r| r.summary()))
+    }
+
+    /// Active and recent workflows, for `list_agents` and the dashboard
+    pub fn workflows(&self) -> &[WorkflowRun] {
+        &self.workflows
+    }
+
+    /// Storage shared by a workflow's steps
+    fn workflow_storage(run: &WorkflowRun) -> TaskStorage {
+        let slug: String = run
+            .task()
+            .to_lowercase()
+            .replace(' ', "-")
+            .replace(|c: char| !c.is_alphanumeric() && c != '-', "");
+        MemoryManager::new(MemoryManager::default_root()).task_storage(&slug)
+    }
+
+    /// Spawn the steps that are ready, save progress, and report the workflow once it's over
+    fn advance_workflow(&mut self, w: usize) {
+        let storage = Self::workflow_storage(&self.workflows[w]);
+        for i in self.workflows[w].ready() {
+            let run = &self.workflows[w];
+            let (step, state) = (&run.workflow.steps[i], &run.steps[i]);
+            let record = SubAgentRecord {
+                run_id: format!("{}-{}-{}{}", run.id, step.id, format!("{:?}", state.tier).to_lowercase(), state.attempts + 1),
+                task_id: format!("{}:{}", run.task(), step.id),
+                model: self.model_for_complexity(state.tier).to_string(),
+                status: SubAgentStatus::Queued,
+                started: Utc::now(),
+                output: None,
+                usage: Usage::default(),
+                message: step.message(&run.workflow.name),
+                system_prompt: WORKFLOW_STEP_PROMPT.to_string(),
+                thinking: Thinking::Off,
+                origin: run.origin.clone(),
+                priority: run.priority,
+                tools: step.allowed_tools(),
+                policy: run.policy.clone(),
+                storage: Some(storage.clone()),
+                decision_id: None,
+                verify_rounds: 0,
+                worktree: None,
+                step: Some(step.id.clone()),
+            };
+            let run_id = record.run_id.clone();
+            // Marked first so a step that starts straight away is already tracked
+            self.workflows[w].step_started(i, &run_id);
+            if let Err(e) = self.submit(record) {
+                self.workflows[w].step_finished(i, Err(e.to_string()));
+            }
+        }
+
+        let run = &self.workflows[w];
+        if let Err(e) = run.save(&self.workflows_dir) {
+            tracing::warn!("Failed to save workflow {}: {e}", run.id);
+        }
+        if run.is_finished() {
+            let run = self.workflows.remove(w);
+            self.reports.push_back(SubAgentMessage::WorkflowDone {
+                workflow_id: run.id.clone(),
+                success: run.succeeded(),
+                summary: run.summary(),
+                origin: run.origin,
+            });
+        }
+    }
+
+    /// If `msg` ends a workflow step, record the outcome and move the workflow on
+    fn workflow_step_finished(&mut self, msg: &SubAgentMessage) -> bool {
+        let (run_id, result) = match msg {
+            SubAgentMessage::Done { run_id, .. } => (run_id, Ok(())),
+            SubAgentMessage::Error { run_id, error } => (run_id, Err(error.clone())),
+            _ => return false,
+        };
+        let Some((w, i)) = self.workflow_step(run_id) else {
+            return false;
+        };
+
+        // A step that didn't write its declared outputs hasn't done its job
+        let storage = Self::workflow_storage(&self.workflows[w]);
+        let result = result.and_then(|()| {
+            match self.workflows[w].workflow.steps[i].outputs.iter().find(|o| storage.file_path(o).is_err()) {
+                Some(missing) => Err(format!("didn't write its output '{missing}'")),
+                None => Ok(()),
+            }
+        });
+
+        let run = &mut self.workflows[w];
+        let step_id = run.workflow.steps[i].id.clone();
+        match run.step_finished(i, result) {
+            StepOutcome::Done => tracing::info!("[{}] step {step_id} done", run.id),
+            StepOutcome::Retry => tracing::info!("[{}] step {step_id} failed — retrying", run.id),
+            StepOutcome::Escalated(tier) => tracing::info!("[{}] step {step_id} failed — escalating to {tier:?}", run.id),
+            StepOutcome::Failed => tracing::warn!("[{}] step {step_id} failed — giving up", run.id),
+        }
+        self.advance_workflow(w);
+        true
+    }
+
+    /// Flag running agents that are blocked on an `ask_user` question
+    fn sync_waiting(&mut self) {
+        let asking: Vec<String> = QuestionStore::open()
+            .list()
+            .into_iter()
+            .filter_map(|q| q.run.map(|r| r.run_id))
+            .collect();
+        let mut changed = Vec::new();
+        for record in self.agents.values_mut() {
+            match record.status {
+                SubAgentStatus::Running if asking.contains(&record.run_id) => {
+                    record.status = SubAgentStatus::WaitingForUser;
                 }
-                Some(msg)
+                SubAgentStatus::WaitingForUser if !asking.contains(&record.run_id) => {
+                    record.status = SubAgentStatus::Running;
+                }
+                _ => continue,
             }
-            Err(_) => None,
+            changed.push(record.clone());
+        }
+        for record in &changed {
+            self.save_record(record);
+        }
+        if !changed.is_empty() {
+            self.drain_queue();
         }
     }
 
-    /// List active agents
+    /// List active agents, queued ones last in queue order
     pub fn list_active(&self) -> Vec<&SubAgentRecord> {
-        self.agents
+        let mut active: Vec<&SubAgentRecord> = self
+            .agents
             .values()
-            .filter(|a| a.status == SubAgentStatus::Running)
+            .filter(|a| matches!(a.status, SubAgentStatus::Running | SubAgentStatus::WaitingForUser | SubAgentStatus::Verifying))
+            .collect();
+        active.sort_by_key(|a| a.started);
+        active.extend(self.queue.iter().filter_map(|(id, _)| self.agents.get(id)));
+        active
+    }
+
+    /// Output for the `list_agents` tool
+    pub fn describe_agents(&self) -> String {
+        let active = self.list_active();
+        if active.is_empty() && self.workflows.is_empty() {
+            return "No sub-agents running or queued.".into();
+        }
+        let workflows = self.workflows.iter().map(WorkflowRun::summary);
+        active
+            .iter()
+            .map(|a| {
+                let status = match self.queue_position(&a.run_id) {
+                    Some(n) => format!("queued #{n} ({:?} priority)", a.priority).to_lowercase(),
+                    None => format!("{:?}", a.status),
+                };
+                format!("- {} [{}] {} — {status}", a.run_id, a.task_id, a.model)
+            })
+            .chain(workflows)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Rows for the dashboard's agents table
+    pub fn agent_infos(&self) -> Vec<AgentInfo> {
+        let mut records: Vec<&SubAgentRecord> = self.agents.values().collect();
+        records.sort_by_key(|a| a.started);
+        records
+            .into_iter()
+            .map(|a| AgentInfo {
+                run_id: a.run_id.clone(),
+                task_id: a.task_id.clone(),
+                model: a.model.clone(),
+                status: match &a.status {
+                    SubAgentStatus::Failed(_) => "Failed".into(),
+                    other => format!("{other:?}"),
+                },
+                cost_usd: estimate_cost_usd(&a.model, a.usage.input_tokens, a.usage.output_tokens, 0, 0),
+                priority: format!("{:?}", a.priority).to_lowercase(),
+                queue_position: self.queue_position(&a.run_id),
+            })
             .collect()
     }
 
-    /// Kill a sub-agent (marks as killed — the tokio task will finish its current API call)
-    pub fn kill(&mut self, run_id: &str) -> Result<()> {
-        if let Some(record) = self.agents.get_mut(run_id) {
-            record.status = SubAgentStatus::Killed;
-            Ok(())
-        } else {
-            anyhow::bail!("No agent with run_id: {run_id}")
+    /// Give a running or queued sub-agent new instructions. It sees them
+    /// before its next API call.
+    pub fn steer(&mut self, run_id: &str, message: &str) -> Result<()> {
+        let record = self
+            .agents
+            .get(run_id)
+            .ok_or_else(|| anyhow::anyhow!("No agent with run_id: {run_id}"))?;
+        if !record.status.is_active() {
+            anyhow::bail!("{run_id} isn't running ({:?})", record.status);
         }
+        Inbox::send(&self.state_dir, run_id, &InboxMessage::new("manager", message))
     }
 
-    /// Assess task complexity for model selection
-    pub fn assess_complexity(message: &str) -> TaskComplexity {
-        let lower = message.to_lowercase();
+    /// Relaunch a sub-agent that asked for a stronger model on the next tier up,
+    /// carrying on from its checkpoint
+    fn escalate(&mut self, run_id: &str, reason: &str) {
+        if self.is_killed(run_id) {
+            return;
+        }
+        let next = self
+            .agents
+            .get(run_id)
+            .and_then(|r| self.tier_for_model(&r.model))
+            .and_then(TaskComplexity::escalate);
+        let Some(tier) = next else {
+            // The tool isn't offered on the top tier, so this is a config change mid-run
+            if let Some(record) = self.agents.get_mut(run_id) {
+                record.status = SubAgentStatus::Failed(format!("Asked to escalate with no higher tier: {reason}"));
+                let record = record.clone();
+                self.save_record(&record);
+            }
+            return;
+        };
+        let model = self.model_for_complexity(tier).to_string();
+        tracing::info!("Escalating {run_id} to {model}: {reason}");
+        let Some(record) = self.agents.get_mut(run_id) else { return };
+        record.model = model;
+        let decision_id = record.decision_id.clone();
+        if let Some((engine, id)) = self.improve.as_mut().zip(decision_id) {
+            engine.record_correction(&id, tier);
+            self.save_improve();
+        }
+        // A workflow step keeps its run; later retries start from the new tier
+        if let Some(run) = self.workflows.iter_mut().find(|w| w.step_for_run(run_id).is_some()) {
+            if let Some(i) = run.step_for_run(run_id) {
+                run.steps[i].tier = tier;
+                run.steps[i].attempts = 0;
+            }
+            if let Err(e) = run.save(&self.workflows_dir) {
+                tracing::warn!("Failed to save workflow {}: {e}", run.id);
+            }
+        }
+        self.enqueue(run_id, true);
+    }
 
-        // Quick signals
-        let quick_words = [
-            "status", "list", "find", "show", "check", "what is", "where is", "how many",
-        ];
-        if quick_words.iter().any(|w| lower.contains(w)) && message.len() < 100 {
-            return TaskComplexity::Quick;
+    /// Kill a sub-agent: stop its loop, or take it off the queue. A workflow
+    /// step fails for good, along with the steps waiting on it.
+    pub fn kill(&mut self, run_id: &str) -> Result<()> {
+        let record = self
+            .agents
+            .get_mut(run_id)
+            .ok_or_else(|| anyhow::anyhow!("No agent with run_id: {run_id}"))?;
+        record.status = SubAgentStatus::Killed;
+        let record = record.clone();
+        self.save_record(&record);
+        if let Some(task) = self.running.remove(run_id) {
+            task.abort();
+        }
+        self.queue.retain(|(id, _)| id != run_id);
+        if let Some((w, i)) = self.workflow_step(run_id) {
+            self.workflows[w].step_failed(i, "Killed");
+            self.advance_workflow(w);
         }
+        self.drain_queue();
+        Ok(())
+    }
 
-        // Complex signals
-        let complex_words = [
-            "redesign",
-            "architect",
-            "refactor",
-            "review",
-            "design",
-            "plan",
-            "strategy",
-            "why is",
-            "debug",
-            "investigate",
-        ];
-        if complex_words.iter().any(|w| lower.contains(w)) {
-            return TaskComplexity::Complex;
+    fn is_killed(&self, run_id: &str) -> bool {
+        self.agents.get(run_id).is_some_and(|r| r.status == SubAgentStatus::Killed)
+    }
+
+    /// The workflow and step a sub-agent is running, if it's a step
+    fn workflow_step(&self, run_id: &str) -> Option<(usize, usize)> {
+        self.workflows
+            .iter()
+            .enumerate()
+            .find_map(|(w, run)| run.step_for_run(run_id).map(|i| (w, i)))
+    }
+
+    /// Carry on a run the last process left waiting in `ask_user`, now its
+    /// question is answered or has timed out. It goes back through the queue
+    /// like any other run. Returns false if the run isn't one of this pool's.
+    pub fn resume_answered(&mut self, run: &SuspendedRun, result: &str) -> bool {
+        let Some(record) = self.agents.get(&run.run_id) else {
+            return false;
+        };
+        if record.status != SubAgentStatus::WaitingForUser {
+            tracing::info!("Not resuming {}: it's {:?}", run.run_id, record.status);
+            return true;
         }
+        self.answers.insert(run.run_id.clone(), (run.tool_use_id.clone(), result.to_string()));
+        self.enqueue(&run.run_id, true);
+        true
+    }
 
-        TaskComplexity::Standard
+    /// The tier a model is configured for, if any
+    pub fn tier_for_model(&self, model: &str) -> Option<TaskComplexity> {
+        [TaskComplexity::Quick, TaskComplexity::Standard, TaskComplexity::Complex]
+            .into_iter()
+            .find(|&t| self.model_for_complexity(t) == model)
     }
 
     /// Get model name for a complexity tier
@@ -283,9 +1301,34 @@ impl Orchestrator {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TaskComplexity {
     Quick,
+    #[default]
     Standard,
     Complex,
 }
+
+impl TaskComplexity {
+    /// Parse a model tier name ("quick", "standard", "complex")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "quick" => Some(Self::Quick),
+            "standard" => Some(Self::Standard),
+            "complex" => Some(Self::Complex),
+            _ => None,
+        }
+    }
+
+    /// The next tier up, if there is one
+    pub fn escalate(self) -> Option<Self> {
+        match self {
+            Self::Quick => Some(Self::Standard),
+            Self::Standard => Some(Self::Complex),
+            Self::Complex => None,
+        }
+    }
+}
+
+const WORKFLOW_STEP_PROMPT: &str = "You are a DevMan sub-agent running one step of a larger workflow. Do exactly what your step asks, using your tools. Other steps may run at the same time — only write the storage files your step names. Finish with a short summary of what you did.";
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::watch;
+
+use crate::sandbox::{self, Sandbox};
+
+/// Output kept per process; older bytes are dropped first
+const OUTPUT_CAP: usize = 1024 * 1024;
+
+/// Processes one table may have running at once
+const MAX_RUNNING: usize = 10;
+
+/// Grace period between SIGTERM and SIGKILL
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// The last `OUTPUT_CAP` bytes of a process's stdout and stderr, interleaved,
+/// addressed by offset from the start of everything it ever wrote
+#[derive(Default)]
+struct OutputBuffer {
+    data: VecDeque<u8>,
+    /// Offset of `data[0]`
+    start: u64,
+}
+
+impl OutputBuffer {
+    fn push(&mut self, bytes: &[u8]) {
+        self.data.extend(bytes);
+        let excess = self.data.len().saturating_sub(OUTPUT_CAP);
+        self.data.drain(..excess);
+        self.start += excess as u64;
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.data.len() as u64
+    }
+}
+
+/// A slice of a process's output
+pub struct Output {
+    pub text: String,
+    /// Where this read started — later than asked if that part was dropped
+    pub from: u64,
+    /// Offset to read from next
+    pub next: u64,
+    /// Bytes between the requested offset and `from` that are gone
+    pub dropped: u64,
+}
+
+/// One background process
+pub struct Process {
+    pub id: u32,
+    pub command: String,
+    pub workdir: Option<String>,
+    pub pid: u32,
+    pub started: DateTime<Utc>,
+    output: Mutex<OutputBuffer>,
+    /// Exit code once it's finished (-1 if killed by a signal)
+    exit: watch::Receiver<Option<i32>>,
+    /// Where `process_output` left off
+    cursor: Mutex<u64>,
+}
+
+impl Process {
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit.borrow()
+    }
+
+    pub fn output_len(&self) -> u64 {
+        self.output.lock().unwrap_or_else(|e| e.into_inner()).end()
+    }
+
+    /// Up to `max` bytes of output from `offset` (default: where the last read
+    /// stopped), moving the read cursor past them
+    pub fn read(&self, offset: Option<u64>, max: usize) -> Output {
+        let buf = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cursor = self.cursor.lock().unwrap_or_else(|e| e.into_inner());
+        let wanted = offset.unwrap_or(*cursor);
+        let from = wanted.clamp(buf.start, buf.end());
+        let skip = (from - buf.start) as usize;
+        let bytes: Vec<u8> = buf.data.iter().skip(skip).take(max).copied().collect();
+        let next = from + bytes.len() as u64;
+        *cursor = next;
+        Output {
+            text: String::from_utf8_lossy(&bytes).into_owned(),
+            from,
+            next,
+            dropped: from.saturating_sub(wanted),
+        }
+    }
+
+    /// Wait for it to exit, for at most `timeout`. Returns the exit code if it did.
+    pub async fn wait(&self, timeout: Duration) -> Option<i32> {
+        let mut exit = self.exit.clone();
+        let _ = tokio::time::timeout(timeout, exit.wait_for(Option::is_some)).await;
+        self.exit_code()
+    }
+
+    /// Signal the whole process group — `bash -c` children included
+    fn signal(&self, signal: &str) {
+        let _ = std::process::Command::new("kill")
+            .args([&format!("-{signal}"), "--", &format!("-{}", self.pid)])
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+
+    /// SIGTERM, then SIGKILL if it hasn't gone after a few seconds
+    pub async fn kill(&self) -> Option<i32> {
+        if self.exit_code().is_some() {
+            return self.exit_code();
+        }
+        self.signal("TERM");
+        if let Some(code) = self.wait(KILL_GRACE).await {
+            return Some(code);
+        }
+        self.signal("KILL");
+        self.wait(Duration::from_secs(1)).await
+    }
+}
+
+/// An agent's (or a chat's) background processes. Clones share the table;
+/// when the last one is dropped, anything still running is killed.
+#[derive(Clone, Default)]
+pub struct ProcessTable {
+    inner: Arc<Mutex<Table>>,
+}
+
+#[derive(Default)]
+struct Table {
+    procs: BTreeMap<u32, Arc<Process>>,
+    next_id: u32,
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        for p in self.procs.values().filter(|p| p.exit_code().is_none()) {
+            p.signal("KILL");
+        }
+    }
+}
+
+impl ProcessTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn table(&self) -> std::sync::MutexGuard<'_, Table> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start `command` under bash in its own process group (and `sandbox`, if
+    /// given), collecting its output
+    pub fn start(&self, command: &str, workdir: Option<&str>, sandbox: Option<&Sandbox>) -> Result<Arc<Process>> {
+        let running = self.table().procs.values().filter(|p| p.exit_code().is_none()).count();
+        if running >= MAX_RUNNING {
+            anyhow::bail!("{running} processes are already running — kill one first");
+        }
+
+        let mut cmd = sandbox::command(sandbox, "bash", &["-c", command], workdir);
+        cmd.stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        let mut child = cmd.spawn().with_context(|| format!("failed to start: {command}"))?;
+        let pid = child.id().context("process exited before it started")?;
+
+        let (exit_tx, exit_rx) = watch::channel(None);
+        let process = {
+            let mut table = self.table();
+            table.next_id += 1;
+            let process = Arc::new(Process {
+                id: table.next_id,
+                command: command.to_string(),
+                workdir: workdir.map(String::from),
+                pid,
+                started: Utc::now(),
+                output: Mutex::new(OutputBuffer::default()),
+                exit: exit_rx,
+                cursor: Mutex::new(0),
+            });
+            table.procs.insert(process.id, process.clone());
+            process
+        };
+
+        let readers: Vec<_> = [
+            child.stdout.take().map(|s| Box::new(s) as Box<dyn tokio::io::AsyncRead + Send + Unpin>),
+            child.stderr.take().map(|s| Box::new(s) as Box<dyn tokio::io::AsyncRead + Send + Unpin>),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|mut stream| {
+            let process = process.clone();
+            tokio::spawn(async move {
+                let mut chunk = [0u8; 8192];
+                while let Ok(n) = stream.read(&mut chunk).await {
+                    if n == 0 {
+                        break;
+                    }
+                    process.output.lock().unwrap_or_else(|e| e.into_inner()).push(&chunk[..n]);
+                }
+            })
+        })
+        .collect();
+
+        tokio::spawn(async move {
+            let code = child.wait().await.ok().and_then(|s| s.code()).unwrap_or(-1);
+            // Let the readers drain what it wrote before it exited
+            for reader in readers {
+                let _ = tokio::time::timeout(Duration::from_secs(2), reader).await;
+            }
+            let _ = exit_tx.send(Some(code));
+        });
+        Ok(process)
+    }
+
+    pub fn get(&self, id: u32) -> Result<Arc<Process>> {
+        self.table()
+            .procs
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no process {id} — process_list shows them"))
+    }
+
+    /// Every process, oldest first
+    pub fn list(&self) -> Vec<Arc<Process>> {
+        self.table().procs.values().cloned().collect()
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::Result;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use crate::search::{self, FileFilter};
+
+/// Rough characters per token, as in `ContextManager::estimated_tokens`
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Source files bigger than this are listed but not outlined
+const MAX_PARSE_BYTES: u64 = 512 * 1024;
+
+/// Lines of each manifest shown
+const MANIFEST_LINES: usize = 40;
+
+/// Directory levels shown in the tree
+const TREE_DEPTH: usize = 3;
+
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "requirements.txt",
+    "go.mod",
+    "Gemfile",
+    "pom.xml",
+    "build.gradle",
+    "Makefile",
+    "docker-compose.yml",
+];
+
+/// A top-level definition in a source file
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+struct Language {
+    extensions: &'static [&'static str],
+    /// Each pattern captures the kind (or a fixed kind) and the name
+    patterns: Vec<(Regex, Option<&'static str>)>,
+    /// Stop outlining at this line — test modules are noise in a map
+    stop: Option<&'static str>,
+}
+
+static LANGUAGES: LazyLock<Vec<Language>> = LazyLock::new(|| {
+    let re = |p: &str| Regex::new(p).expect("valid symbol regex");
+    vec![
+        Language {
+            extensions: &["rs"],
+            patterns: vec![
+                (re(r"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+\x22[^\x22]*\x22)\s+)*(fn|struct|enum|trait|type|union)\s+([A-Za-z_]\w*)"), None),
+                (re(r"^ {4}pub(?:\([^)]*\))?\s+(?:(?:async|const|unsafe)\s+)*(fn)\s+([A-Za-z_]\w*)"), None),
+                (re(r"^macro_rules!\s+([A-Za-z_]\w*)"), Some("macro")),
+            ],
+            stop: Some("#[cfg(test)]"),
+        },
+        Language {
+            extensions: &["py"],
+            patterns: vec![
+                (re(r"^(class)\s+([A-Za-z_]\w*)"), None),
+                (re(r"^(?:async\s+)?def\s+([A-Za-z_]\w*)"), Some("def")),
+                (re(r"^ {4}(?:async\s+)?def\s+([A-Za-z]\w*)"), Some("def")),
+            ],
+            stop: None,
+        },
+        Language {
+            extensions: &["js", "jsx", "ts", "tsx", "mjs", "cjs"],
+            patterns: vec![
+                (re(r"^(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function|class|interface|type|enum)\*?\s+([A-Za-z_$][\w$]*)"), None),
+                (re(r"^export\s+(?:const|let|var)\s+([A-Za-z_$][\w$]*)"), Some("const")),
+            ],
+            stop: None,
+        },
+        Language {
+            extensions: &["go"],
+            patterns: vec![
+                (re(r"^func\s+(?:\([^)]*\)\s*)?([A-Za-z_]\w*)"), Some("func")),
+                (re(r"^type\s+([A-Za-z_]\w*)"), Some("type")),
+            ],
+            stop: None,
+        },
+        Language {
+            extensions: &["rb"],
+            patterns: vec![(re(r"^\s{0,2}(class|module|def)\s+([A-Za-z_][\w:.?!]*)"), None)],
+            stop: None,
+        },
+    ]
+});
+
+/// Top-level symbols in `content`, if `path` is in a language we outline
+pub fn outline(path: &Path, content: &str) -> Option<Vec<Symbol>> {
+    let ext = path.extension()?.to_str()?;
+    let lang = LANGUAGES.iter().find(|l| l.extensions.contains(&ext))?;
+    let mut symbols = Vec::new();
+    for line in content.lines() {
+        if lang.stop.is_some_and(|s| line.trim_start().starts_with(s)) {
+            break;
+        }
+        for (re, fixed) in &lang.patterns {
+            if let Some(c) = re.captures(line) {
+                let (kind, name) = match fixed {
+                    Some(kind) => (*kind, &c[1]),
+                    None => (kind_name(&c[1]), &c[2]),
+                };
+                symbols.push(Symbol { kind, name: name.to_string() });
+                break;
+            }
+        }
+    }
+    Some(symbols)
+}
+
+fn kind_name(s: &str) -> &'static str {
+    match s {
+        "fn" => "fn",
+        "struct" => "struct",
+        "enum" => "enum",
+        "trait" => "trait",
+        "type" => "type",
+        "union" => "union",
+        "class" => "class",
+        "interface" => "interface",
+        "function" => "function",
+        "module" => "module",
+        "def" => "def",
+        _ => "item",
+    }
+}
+
+struct Cached {
+    fingerprint: u64,
+    /// Outlines by file, with the mtime they were read at
+    outlines: HashMap<PathBuf, (SystemTime, Vec<Symbol>)>,
+    /// Rendered maps by token budget
+    rendered: HashMap<usize, String>,
+}
+
+/// Maps by project root. A file added, removed or modified changes the
+/// fingerprint; only files whose mtime moved are re-outlined.
+static CACHE: LazyLock<Mutex<HashMap<PathBuf, Cached>>> = LazyLock::new(Default::default);
+
+/// A summary of the project at `root` in about `max_tokens` tokens: manifest
+/// files, the directory tree and each source file's top-level symbols
+pub fn repo_map(root: &Path, max_tokens: usize) -> Result<String> {
+    if !root.is_dir() {
+        anyhow::bail!("not a directory: {}", root.display());
+    }
+    let root = root.canonicalize()?;
+    let files = search::files(&root, &FileFilter::default())?;
+    let mut hasher = DefaultHasher::new();
+    for f in &files {
+        f.path.hash(&mut hasher);
+        f.modified.hash(&mut hasher);
+    }
+    let fingerprint = hasher.finish();
+
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cached = cache.entry(root.clone()).or_insert_with(|| Cached {
+        fingerprint: 0,
+        outlines: HashMap::new(),
+        rendered: HashMap::new(),
+    });
+    if cached.fingerprint == fingerprint
+        && let Some(map) = cached.rendered.get(&max_tokens)
+    {
+        return Ok(map.clone());
+    }
+    if cached.fingerprint != fingerprint {
+        cached.rendered.clear();
+    }
+
+    let mut outlines = HashMap::new();
+    for f in &files {
+        let symbols = match cached.outlines.remove(&f.path) {
+            Some((mtime, symbols)) if mtime == f.modified => symbols,
+            _ => read_outline(&f.path),
+        };
+        outlines.insert(f.path.clone(), (f.modified, symbols));
+    }
+    cached.outlines = outlines;
+    cached.fingerprint = fingerprint;
+
+    let paths: Vec<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+    let map = render(&root, &paths, &cached.outlines, max_tokens);
+    cached.rendered.insert(max_tokens, map.clone());
+    Ok(map)
+}
+
+fn read_outline(path: &Path) -> Vec<Symbol> {
+    if std::fs::metadata(path).map(|m| m.len() > MAX_PARSE_BYTES).unwrap_or(true) {
+        return Vec::new();
+    }
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| outline(path, &content))
+        .unwrap_or_default()
+}
+
+fn render(
+    root: &Path,
+    files: &[&Path],
+    outlines: &HashMap<PathBuf, (SystemTime, Vec<Symbol>)>,
+    max_tokens: usize,
+) -> String {
+    let budget = max_tokens * CHARS_PER_TOKEN;
+    let rel = |p: &Path| p.strip_prefix(root).unwrap_or(p).to_path_buf();
+    let mut out = format!("# {}\n", root.display());
+
+    // Manifests at the root and one level down (workspace members)
+    let mut manifests: Vec<PathBuf> = files
+        .iter()
+        .map(|p| rel(p))
+        .filter(|p| p.components().count() <= 2)
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| MANIFESTS.contains(&n)))
+        .collect();
+    manifests.sort_by_key(|p| (p.components().count(), p.clone()));
+    for manifest in manifests {
+        let Ok(content) = std::fs::read_to_string(root.join(&manifest)) else { continue };
+        let lines: Vec<&str> = content.lines().collect();
+        let mut section = format!("\n## {}\n```\n{}\n", manifest.display(), lines[..lines.len().min(MANIFEST_LINES)].join("\n"));
+        if lines.len() > MANIFEST_LINES {
+            section.push_str(&format!("... ({} more lines)\n", lines.len() - MANIFEST_LINES));
+        }
+        section.push_str("```\n");
+        if out.len() + section.len() > budget / 2 {
+            break;
+        }
+        out.push_str(&section);
+    }
+
+    // Directories with their file counts, and the files at the root
+    let mut dirs: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    let mut top_files = Vec::new();
+    for path in files.iter().map(|p| rel(p)) {
+        let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        if parent.as_os_str().is_empty() {
+            top_files.push(path.display().to_string());
+        }
+        for ancestor in parent.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            if ancestor.components().count() <= TREE_DEPTH {
+                *dirs.entry(ancestor.to_path_buf()).or_default() += 1;
+            }
+        }
+    }
+    let mut tree = String::from("\n## Tree\n");
+    for (dir, count) in &dirs {
+        let depth = dir.components().count() - 1;
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let line = format!("{}{name}/ ({count} files)\n", "  ".repeat(depth));
+        if out.len() + tree.len() + line.len() > budget * 2 / 3 {
+            tree.push_str("...\n");
+            break;
+        }
+        tree.push_str(&line);
+    }
+    if !top_files.is_empty() {
+        tree.push_str(&top_files.join("  "));
+        tree.push('\n');
+    }
+    out.push_str(&tree);
+
+    // Symbols, keeping the files with the most of them when space runs out
+    let mut outlined: Vec<(PathBuf, String)> = files
+        .iter()
+        .filter_map(|p| {
+            let (_, symbols) = outlines.get(*p)?;
+            (!symbols.is_empty()).then(|| (rel(p), format_symbols(symbols)))
+        })
+        .collect();
+    if outlined.is_empty() {
+        return out;
+    }
+    let total = outlined.len();
+    outlined.sort_by_key(|(_, s)| std::cmp::Reverse(s.len()));
+    let mut room = budget.saturating_sub(out.len() + 20);
+    let mut kept: Vec<(PathBuf, String)> = Vec::new();
+    for (path, symbols) in outlined {
+        let size = path.as_os_str().len() + symbols.len() + 4;
+        if size <= room {
+            room -= size;
+            kept.push((path, symbols));
+        }
+    }
+    kept.sort();
+    out.push_str("\n## Symbols\n");
+    for (path, symbols) in &kept {
+        out.push_str(&format!("{}\n  {symbols}\n", path.display()));
+    }
+    if kept.len() < total {
+        out.push_str(&format!(
+            "... {} more files not shown — call repo_map on a subdirectory for them\n",
+            total - kept.len()
+        ));
+    }
+    out
+}
+
+/// `struct A, B; fn c, d` — kinds in the order they first appear
+fn format_symbols(symbols: &[Symbol]) -> String {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for s in symbols {
+        match groups.iter_mut().find(|(k, _)| *k == s.kind) {
+            Some((_, names)) => names.push(&s.name),
+            None => groups.push((s.kind, vec![&s.name])),
+        }
+    }
+    groups
+        .iter()
+        .map(|(kind, names)| format!("{kind} {}", names.join(", ")))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::SandboxConfig;
+use crate::shell_session::quote;
+
+/// Tools that run outside any sandbox — a sandboxed agent isn't offered them
+pub const UNSANDBOXED_TOOLS: &[&str] = &["claude_code"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// bubblewrap
+    Bwrap,
+    /// util-linux unshare, with the mounts set up by a bash script inside
+    Unshare,
+}
+
+/// Runs a bot's commands in unprivileged user, mount, PID and (unless allowed)
+/// network namespaces: everything read-only except its working directory,
+/// storage and a private /tmp, under CPU and memory rlimits
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub backend: Backend,
+    config: SandboxConfig,
+    /// Bound read-write; commands start in the first unless told otherwise
+    writable: Vec<PathBuf>,
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+impl Sandbox {
+    /// A sandbox with `writable` (plus the config's own list) mounted read-write.
+    /// Missing directories are created.
+    pub fn new(config: &SandboxConfig, writable: impl IntoIterator<Item = PathBuf>) -> Result<Self> {
+        let backend = match config.backend.as_str() {
+            "auto" if on_path("bwrap") => Backend::Bwrap,
+            "auto" | "unshare" => Backend::Unshare,
+            "bwrap" => Backend::Bwrap,
+            other => anyhow::bail!("unknown sandbox backend '{other}' — use auto, bwrap or unshare"),
+        };
+        let program = match backend {
+            Backend::Bwrap => "bwrap",
+            Backend::Unshare => "unshare",
+        };
+        if !on_path(program) {
+            anyhow::bail!("sandbox backend '{program}' isn't installed");
+        }
+
+        let mut dirs = Vec::new();
+        for dir in writable.into_iter().chain(config.writable.iter().map(PathBuf::from)) {
+            std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+            let dir = dir.canonicalize().with_context(|| format!("resolving {}", dir.display()))?;
+            if dir == Path::new("/") {
+                anyhow::bail!("the sandbox can't make / writable");
+            }
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        Ok(Self { backend, config: config.clone(), writable: dirs })
+    }
+
+    /// `requested`, capped at the sandbox's longest command
+    pub fn timeout(&self, requested: Duration) -> Duration {
+        requested.min(Duration::from_secs(self.config.timeout_seconds))
+    }
+
+    /// `prlimit` prefix for the CPU and memory limits, if there are any
+    fn rlimits(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.config.memory_mb > 0 {
+            args.push(format!("--as={}", self.config.memory_mb * 1024 * 1024));
+        }
+        if self.config.cpu_seconds > 0 {
+            args.push(format!("--cpu={}", self.config.cpu_seconds));
+        }
+        if !args.is_empty() {
+            args.insert(0, "prlimit".into());
+            args.push("--".into());
+        }
+        args
+    }
+
+    /// `program args` inside the sandbox, starting in `workdir`
+    /// (default: the first writable directory, else /)
+    pub fn command(&self, program: &str, args: &[&str], workdir: Option<&str>) -> Command {
+        let home = self.writable.first().map_or("/".into(), |d| d.to_string_lossy().into_owned());
+        let workdir = workdir.unwrap_or(&home);
+        let inner: Vec<String> = self
+            .rlimits()
+            .into_iter()
+            .chain(std::iter::once(program.to_string()))
+            .chain(args.iter().map(|a| a.to_string()))
+            .collect();
+
+        match self.backend {
+            Backend::Bwrap => {
+                let mut cmd = Command::new("bwrap");
+                cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]);
+                for dir in &self.writable {
+                    cmd.arg("--bind").arg(dir).arg(dir);
+                }
+                cmd.args(["--unshare-user", "--unshare-pid", "--unshare-ipc", "--unshare-uts"]);
+                if !self.config.network {
+                    cmd.arg("--unshare-net");
+                }
+                cmd.args(["--die-with-parent", "--chdir", workdir, "--"]).args(&inner);
+                cmd
+            }
+            Backend::Unshare => {
+                let mut cmd = Command::new("unshare");
+                cmd.args(["--user", "--map-root-user", "--mount", "--pid", "--fork", "--kill-child", "--mount-proc"]);
+                if !self.config.network {
+                    cmd.arg("--net");
+                }
+                cmd.args(["--", "bash", "-c", &self.mount_script(workdir), "sandbox"]).args(&inner);
+                cmd
+            }
+        }
+    }
+
+    /// Inside the new mount namespace: a private /tmp, the writable directories
+    /// bound back in, every other mount remounted read-only, then exec "$@"
+    fn mount_script(&self, workdir: &str) -> String {
+        let dirs: Vec<String> = self.writable.iter().map(|d| quote(&d.to_string_lossy())).collect();
+        let mut script = String::from("set -e\n");
+        // Hold the writable directories open: ones under /tmp vanish behind the tmpfs
+        for (i, dir) in dirs.iter().enumerate() {
+            script.push_str(&format!("exec {}< {dir}\n", i + 3));
+        }
+        script.push_str("mount -t tmpfs -o mode=1777 tmpfs /tmp\n");
+        // --no-canonicalize: resolving the fd's path would land inside the new tmpfs
+        for (i, dir) in dirs.iter().enumerate() {
+            script.push_str(&format!("mkdir -p {dir}\nmount --no-canonicalize --bind /proc/self/fd/{} {dir}\nexec {}<&-\n", i + 3, i + 3));
+        }
+        let skip = dirs.iter().map(String::as_str).chain(["/tmp", "/proc", "/proc/*", "/dev", "/dev/*"]).collect::<Vec<_>>().join("|");
+        // Remount with the flags each mount already has — locked ones can't be dropped
+        script.push_str(&format!(
+            "while read -r _ _ _ _ mnt opts _; do\n\
+             case \"$mnt\" in {skip}) continue ;; esac\n\
+             opts=${{opts#rw}}; opts=${{opts#ro}}\n\
+             mount -o \"remount,bind,ro$opts\" \"$mnt\" || {{ echo \"sandbox: can't make $mnt read-only\" >&2; exit 126; }}\n\
+             done < /proc/self/mountinfo\n\
+             cd -- {}\n\
+             exec \"$@\"\n",
+            quote(workdir)
+        ));
+        script
+    }
+}
+
+/// `program args` in `sandbox` if there is one, else as-is in `workdir`
+pub fn command(sandbox: Option<&Sandbox>, program: &str, args: &[&str], workdir: Option<&str>) -> Command {
+    match sandbox {
+        Some(sandbox) => sandbox.command(program, args, workdir),
+        None => {
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            if let Some(dir) = workdir {
+                cmd.current_dir(dir);
+            }
+            cmd
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::WalkBuilder;
+use ignore::types::TypesBuilder;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Files bigger than this aren't searched — they're data, not code
+const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// A file is treated as binary if this much of its start holds a NUL byte
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Which files a walk visits. `.gitignore`, `.ignore` and the global git
+/// excludes always apply, in or out of a git repository.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Glob matched against the path relative to the root; without a `/` it
+    /// matches file names at any depth
+    pub glob: Option<String>,
+    /// File types as ripgrep names them: `rust`, `py`, `ts`, `md`, ...
+    pub types: Vec<String>,
+    /// Include dot-files and dot-directories (never `.git` itself)
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+/// A line in a grep result: a match, or context around one
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+    pub is_match: bool,
+}
+
+/// Everything that matched in one file
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    /// Matching lines
+    pub count: usize,
+    /// Matches with their context lines, in file order
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    /// Most matches first (glob: shallowest path first)
+    Relevance,
+    /// Most recently modified first
+    Modified,
+    Path,
+}
+
+impl Sort {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "relevance" => Ok(Self::Relevance),
+            "modified" | "mtime" => Ok(Self::Modified),
+            "path" => Ok(Self::Path),
+            other => anyhow::bail!("unknown sort: {other} (expected relevance, modified or path)"),
+        }
+    }
+}
+
+fn matcher(glob: &str) -> Result<GlobMatcher> {
+    // `*.rs` means any .rs file; `src/*.rs` means exactly that
+    let glob = if glob.contains('/') { glob.to_string() } else { format!("**/{glob}") };
+    Ok(GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("invalid glob: {glob}"))?
+        .compile_matcher())
+}
+
+/// Every file under `root` the filter lets through, in walk order
+pub fn files(root: &Path, filter: &FileFilter) -> Result<Vec<FileEntry>> {
+    let glob = filter.glob.as_deref().map(matcher).transpose()?;
+    let mut walk = WalkBuilder::new(root);
+    walk.hidden(!filter.hidden)
+        .require_git(false)
+        .filter_entry(|e| e.file_name() != ".git");
+    if !filter.types.is_empty() {
+        let mut types = TypesBuilder::new();
+        types.add_defaults();
+        for t in &filter.types {
+            types.select(t);
+        }
+        walk.types(types.build().context("unknown file type")?);
+    }
+
+    let mut entries = Vec::new();
+    for entry in walk.build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        if let Some(glob) = &glob {
+            let rel = path.strip_prefix(root).unwrap_or(path);
+            if !glob.is_match(rel) {
+                continue;
+            }
+        }
+        let modified = entry
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push(FileEntry { path: path.to_path_buf(), modified });
+    }
+    Ok(entries)
+}
+
+/// Files whose path matches `pattern`, sorted
+pub fn glob(root: &Path, pattern: &str, hidden: bool, sort: Sort) -> Result<Vec<FileEntry>> {
+    let filter = FileFilter { glob: Some(pattern.to_string()), hidden, ..Default::default() };
+    let mut entries = files(root, &filter)?;
+    match sort {
+        Sort::Relevance => entries.sort_by(|a, b| {
+            (a.path.components().count(), &a.path).cmp(&(b.path.components().count(), &b.path))
+        }),
+        Sort::Modified => entries.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.path.cmp(&b.path))),
+        Sort::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+    Ok(entries)
+}
+
+/// Search text files under `root` for `regex`, with `context` lines either
+/// side of each match. Binary and oversized files are skipped.
+pub fn grep(root: &Path, regex: &Regex, filter: &FileFilter, context: usize, sort: Sort) -> Result<Vec<FileMatches>> {
+    let mut results = Vec::new();
+    for entry in files(root, filter)? {
+        if std::fs::metadata(&entry.path).map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&entry.path) else { continue };
+        if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = text.lines().collect();
+        let hits: Vec<usize> = (0..lines.len()).filter(|&i| regex.is_match(lines[i])).collect();
+        if hits.is_empty() {
+            continue;
+        }
+
+        let mut shown = Vec::new();
+        let mut next = 0;
+        for &hit in &hits {
+            let start = hit.saturating_sub(context).max(next);
+            let end = (hit + context + 1).min(lines.len());
+            for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+                shown.push(Line { number: i + 1, text: line.to_string(), is_match: regex.is_match(line) });
+            }
+            next = next.max(end);
+        }
+        results.push(FileMatches { path: entry.path, modified: entry.modified, count: hits.len(), lines: shown });
+    }
+
+    match sort {
+        Sort::Relevance => results.sort_by(|a, b| b.count.cmp(&a.count).then(a.path.cmp(&b.path))),
+        Sort::Modified => results.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.path.cmp(&b.path))),
+        Sort::Path => results.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+    Ok(results)
+}
@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::{Mutex, Notify};
+
+use crate::sandbox::{self, Sandbox};
+
+/// Output kept from the start and the end of one command's stream; the middle
+/// of anything longer is dropped (the tool truncates at 50KB anyway)
+const KEEP_BYTES: usize = 64 * 1024;
+
+/// How long an interrupted command gets to wind down before it's killed
+const INTERRUPT_GRACE: Duration = Duration::from_secs(3);
+
+/// What one command in a session did
+pub struct SessionOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// None if it never finished
+    pub exit_code: Option<i32>,
+    /// Hit its timeout and was interrupted
+    pub timed_out: bool,
+    /// The shell itself is gone — `exit`, or a command it couldn't interrupt
+    pub ended: bool,
+}
+
+/// One of the shell's output streams, as read so far
+#[derive(Default)]
+struct Stream {
+    buf: StdMutex<Vec<u8>>,
+    /// More was read, or the stream closed
+    changed: Notify,
+    closed: AtomicBool,
+}
+
+impl Stream {
+    fn push(&self, bytes: &[u8]) {
+        let mut buf = self.buf.lock().unwrap_or_else(|e| e.into_inner());
+        buf.extend_from_slice(bytes);
+        if buf.len() > 4 * KEEP_BYTES {
+            let end = buf.len() - KEEP_BYTES;
+            buf.drain(KEEP_BYTES..end);
+        }
+    }
+
+    /// Everything before `marker` and what follows it on its line, consuming both
+    fn take_until(&self, marker: &str) -> Option<(String, String)> {
+        let mut buf = self.buf.lock().unwrap_or_else(|e| e.into_inner());
+        let at = buf.windows(marker.len()).position(|w| w == marker.as_bytes())?;
+        let rest = &buf[at + marker.len()..];
+        let eol = rest.iter().position(|&b| b == b'\n')?;
+        let output = String::from_utf8_lossy(&buf[..at]).into_owned();
+        let trailer = String::from_utf8_lossy(&rest[..eol]).trim().to_string();
+        buf.drain(..at + marker.len() + eol + 1);
+        Some((output, trailer))
+    }
+
+    fn take_all(&self) -> String {
+        let mut buf = self.buf.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&std::mem::take(&mut *buf)).into_owned()
+    }
+}
+
+/// A running `bash` reading commands from a pipe
+struct Bash {
+    child: Child,
+    stdin: ChildStdin,
+    pid: u32,
+    stdout: Arc<Stream>,
+    stderr: Arc<Stream>,
+    /// The `workdir` the last command asked for
+    workdir: Option<String>,
+    /// Commands run so far — numbers each one's end marker
+    count: u64,
+    /// Unguessable part of the end markers, so output can't fake one
+    nonce: String,
+}
+
+impl Bash {
+    fn spawn(workdir: Option<&str>, sandbox: Option<&Sandbox>) -> Result<Self> {
+        let mut cmd = sandbox::command(sandbox, "bash", &["--noprofile", "--norc"], workdir);
+        cmd.stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        let mut child = cmd.spawn().context("failed to start bash")?;
+        let pid = child.id().context("bash exited before it started")?;
+        let stdin = child.stdin.take().context("no stdin")?;
+        let stdout = Arc::new(Stream::default());
+        let stderr = Arc::new(Stream::default());
+        if let Some(out) = child.stdout.take() {
+            tokio::spawn(pump(out, stdout.clone()));
+        }
+        if let Some(err) = child.stderr.take() {
+            tokio::spawn(pump(err, stderr.clone()));
+        }
+        Ok(Self {
+            child,
+            stdin,
+            pid,
+            stdout,
+            stderr,
+            workdir: workdir.map(String::from),
+            count: 0,
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+        })
+    }
+
+    /// The shell's own pid — in a sandbox, a few processes below the one we started
+    fn shell_pid(&self) -> u32 {
+        let mut queue = VecDeque::from([self.pid]);
+        while let Some(pid) = queue.pop_front() {
+            let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).unwrap_or_default();
+            if cmdline.starts_with(b"bash\0--noprofile\0--norc") {
+                return pid;
+            }
+            let children = std::fs::read_to_string(format!("/proc/{pid}/task/{pid}/children")).unwrap_or_default();
+            queue.extend(children.split_whitespace().filter_map(|c| c.parse::<u32>().ok()));
+        }
+        self.pid
+    }
+
+    /// Signal whatever the shell is running, leaving the shell itself
+    fn signal_children(&self, signal: &str) {
+        let _ = std::process::Command::new("pkill")
+            .args([&format!("-{signal}"), "-P", &self.shell_pid().to_string()])
+            .status();
+    }
+
+    /// Wait for both end markers. Returns the output and exit code once they're in,
+    /// or None at the deadline or if the shell's output closed first.
+    async fn collect(&self, marker: &str, deadline: tokio::time::Instant) -> Option<(String, String, i32)> {
+        let mut stdout_part = None;
+        loop {
+            let out_changed = self.stdout.changed.notified();
+            let err_changed = self.stderr.changed.notified();
+            tokio::pin!(out_changed, err_changed);
+            out_changed.as_mut().enable();
+            err_changed.as_mut().enable();
+
+            if stdout_part.is_none() {
+                stdout_part = self.stdout.take_until(marker);
+            }
+            if let Some((stdout, code)) = &stdout_part
+                && let Some((stderr, _)) = self.stderr.take_until(marker)
+            {
+                return Some((stdout.clone(), stderr, code.parse().unwrap_or(-1)));
+            }
+            if self.stdout.closed.load(Ordering::Acquire) || self.stderr.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            tokio::select! {
+                _ = out_changed => {}
+                _ = err_changed => {}
+                _ = tokio::time::sleep_until(deadline) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Bash {
+    fn drop(&mut self) {
+        // The whole group, so background jobs it started go too
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", self.pid)])
+            .stderr(std::process::Stdio::null())
+            .status();
+        let _ = self.child.start_kill();
+    }
+}
+
+async fn pump(mut from: impl AsyncRead + Unpin, to: Arc<Stream>) {
+    let mut chunk = [0u8; 8192];
+    while let Ok(n) = from.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        to.push(&chunk[..n]);
+        to.changed.notify_waiters();
+    }
+    to.closed.store(true, Ordering::Release);
+    to.changed.notify_waiters();
+}
+
+/// Single-quote `s` for bash
+pub(crate) fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// A bash session that keeps its working directory, environment and shell
+/// variables from one command to the next. Clones share the session; it starts
+/// on first use and is killed when the last clone is dropped.
+#[derive(Clone, Default)]
+pub struct ShellSession {
+    inner: Arc<Mutex<Option<Bash>>>,
+}
+
+impl ShellSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `command` in the session. `workdir` starts the session there, and
+    /// moves it there when it differs from the last one asked for. A command that
+    /// runs past `timeout` is interrupted; the shell carries on. A new shell
+    /// starts in `sandbox`, when there is one.
+    pub async fn run(&self, command: &str, workdir: Option<&str>, timeout: Duration, sandbox: Option<&Sandbox>) -> Result<SessionOutput> {
+        let mut guard = self.inner.lock().await;
+        if guard.is_none() {
+            *guard = Some(Bash::spawn(workdir, sandbox)?);
+        }
+        let bash = guard.as_mut().expect("session was just started");
+
+        let mut script = String::new();
+        if let Some(dir) = workdir.filter(|dir| bash.workdir.as_deref() != Some(*dir)) {
+            script.push_str(&format!("cd -- {}\n", quote(dir)));
+            bash.workdir = Some(dir.to_string());
+        }
+        bash.count += 1;
+        let marker = format!("__DEVMAN_DONE_{}_{}__", bash.nonce, bash.count);
+        // The command goes through a quoted heredoc and eval so it needs no escaping,
+        // a syntax error doesn't take the shell down, and it can't read the pipe
+        // the next commands come in on
+        script.push_str(&format!(
+            "__devman_cmd=$(cat <<'{marker}'\n{command}\n{marker}\n)\n\
+             eval \"$__devman_cmd\" < /dev/null\n\
+             __devman_rc=$?\n\
+             printf '%s %d\\n' '{marker}' \"$__devman_rc\"\n\
+             printf '%s\\n' '{marker}' >&2\n"
+        ));
+        if let Err(e) = bash.stdin.write_all(script.as_bytes()).await {
+            *guard = None;
+            anyhow::bail!("the shell session had ended ({e}); run the command again to start a new one");
+        }
+        let _ = bash.stdin.flush().await;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        if let Some((stdout, stderr, code)) = bash.collect(&marker, deadline).await {
+            return Ok(SessionOutput { stdout, stderr, exit_code: Some(code), timed_out: false, ended: false });
+        }
+
+        let timed_out = !bash.stdout.closed.load(Ordering::Acquire) && !bash.stderr.closed.load(Ordering::Acquire);
+        if timed_out {
+            // Stop the command, not the shell: TERM, then KILL
+            for signal in ["TERM", "KILL"] {
+                bash.signal_children(signal);
+                let deadline = tokio::time::Instant::now() + INTERRUPT_GRACE;
+                if let Some((stdout, stderr, code)) = bash.collect(&marker, deadline).await {
+                    return Ok(SessionOutput { stdout, stderr, exit_code: Some(code), timed_out: true, ended: false });
+                }
+            }
+        }
+
+        // The shell exited, or is stuck in a builtin loop signals to children can't stop
+        let output = SessionOutput {
+            stdout: bash.stdout.take_all(),
+            stderr: bash.stderr.take_all(),
+            exit_code: None,
+            timed_out,
+            ended: true,
+        };
+        *guard = None;
+        Ok(output)
+    }
+
+    /// Kill the shell; the next command starts a fresh one
+    pub async fn reset(&self) -> bool {
+        self.inner.lock().await.take().is_some()
+    }
+}
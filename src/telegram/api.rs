@@ -3,14 +3,141 @@ use reqwest::Client;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use super::types::{ApiResponse, RateLimitError, TgFile, TgMessage, Update};
+use super::format;
+use super::types::{ApiResponse, RateLimitError, TgFile, TgMessage, Update, User};
 
+#[derive(Clone)]
 pub struct TelegramBot {
     client: Client,
     base_url: String,
     allowed_users: Vec<i64>,
 }
 
+/// Upload kind — picks the Bot API method, form field and size limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Document,
+    Photo,
+    Voice,
+}
+
+/// Bot API upload limit for documents and voice notes
+pub const MAX_UPLOAD_BYTES: u64 = 50 * 1024 * 1024;
+/// Bot API upload limit for photos
+pub const MAX_PHOTO_BYTES: u64 = 10 * 1024 * 1024;
+/// Captions are limited to 1024 characters
+const MAX_CAPTION_LEN: usize = 1024;
+
+impl MediaKind {
+    /// Guess the upload kind from a file extension
+    pub fn for_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" | "png" | "webp" => Self::Photo,
+            "ogg" | "oga" | "opus" => Self::Voice,
+            _ => Self::Document,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "document" => Some(Self::Document),
+            "photo" => Some(Self::Photo),
+            "voice" => Some(Self::Voice),
+            _ => None,
+        }
+    }
+
+    /// Form field / media type name ("document", "photo", "voice")
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Photo => "photo",
+            Self::Voice => "voice",
+        }
+    }
+
+    fn method(self) -> &'static str {
+        match self {
+            Self::Document => "sendDocument",
+            Self::Photo => "sendPhoto",
+            Self::Voice => "sendVoice",
+        }
+    }
+
+    pub fn max_bytes(self) -> u64 {
+        match self {
+            Self::Photo => MAX_PHOTO_BYTES,
+            Self::Document | Self::Voice => MAX_UPLOAD_BYTES,
+        }
+    }
+}
+
+/// Where a message goes: a chat, optionally a forum topic, optionally as a
+/// reply to an earlier message. Plain chat ids convert into one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChatRef {
+    pub chat_id: i64,
+    pub thread_id: Option<i64>,
+    pub reply_to: Option<i64>,
+}
+
+impl From<i64> for ChatRef {
+    fn from(chat_id: i64) -> Self {
+        Self { chat_id, thread_id: None, reply_to: None }
+    }
+}
+
+impl ChatRef {
+    /// Request body fields for JSON methods
+    fn json(&self) -> serde_json::Value {
+        let mut body = serde_json::json!({ "chat_id": self.chat_id });
+        if let Some(thread) = self.thread_id {
+            body["message_thread_id"] = thread.into();
+        }
+        if let Some(reply) = self.reply_to {
+            body["reply_parameters"] = serde_json::json!({
+                "message_id": reply,
+                "allow_sending_without_reply": true,
+            });
+        }
+        body
+    }
+
+    /// The same fields for multipart uploads
+    fn form(&self) -> reqwest::multipart::Form {
+        let mut form = reqwest::multipart::Form::new().text("chat_id", self.chat_id.to_string());
+        if let Some(thread) = self.thread_id {
+            form = form.text("message_thread_id", thread.to_string());
+        }
+        if let Some(reply) = self.reply_to {
+            form = form.text("reply_parameters", format!(r#"{{"message_id":{reply},"allow_sending_without_reply":true}}"#));
+        }
+        form
+    }
+}
+
+/// A bot plus the chat (and forum topic) it's currently serving — handed to
+/// tools that reply out-of-band (files, voice notes).
+#[derive(Clone)]
+pub struct ChatTarget {
+    pub bot: TelegramBot,
+    /// Bot name from config ("manager" or the scoped bot's name)
+    pub name: String,
+    pub chat_id: i64,
+    pub thread_id: Option<i64>,
+}
+
+impl ChatTarget {
+    pub fn dest(&self) -> ChatRef {
+        ChatRef { chat_id: self.chat_id, thread_id: self.thread_id, reply_to: None }
+    }
+}
+
 impl TelegramBot {
     pub fn new(token: String, allowed_users: Vec<i64>) -> Self {
         let client = Client::builder()
@@ -50,17 +177,62 @@ impl TelegramBot {
         Ok(resp.result.unwrap_or_default())
     }
 
-    pub async fn send_message(&self, chat_id: i64, text: &str) -> Result<TgMessage> {
-        // Try with Markdown first, then plain text, with rate limit retry
-        for attempt in 0..3 {
-            let parse_mode = if attempt == 0 { Some("Markdown") } else { None };
+    /// Send model Markdown as one or more HTML-formatted messages.
+    /// Only the first chunk is sent as a reply. Returns the last message sent.
+    pub async fn send_message(&self, chat: impl Into<ChatRef>, text: &str) -> Result<TgMessage> {
+        let mut chat = chat.into();
+        let mut last = None;
+        for chunk in format::markdown_to_chunks(text, format::MAX_MESSAGE_LEN) {
+            last = Some(self.send_html(chat, &chunk).await?);
+            chat.reply_to = None;
+        }
+        last.context("empty message")
+    }
 
-            let mut body = serde_json::json!({
-                "chat_id": chat_id,
-                "text": text,
-            });
-            if let Some(pm) = parse_mode {
-                body["parse_mode"] = serde_json::Value::String(pm.into());
+    /// Send Markdown with an inline keyboard under the last chunk.
+    /// Each button is (label, callback_data); callback_data is at most 64 bytes.
+    pub async fn send_keyboard(&self, chat: impl Into<ChatRef>, text: &str, rows: &[Vec<(String, String)>]) -> Result<TgMessage> {
+        let mut chat = chat.into();
+        let markup = serde_json::json!({
+            "inline_keyboard": rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|(label, data)| serde_json::json!({ "text": label, "callback_data": data }))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        let chunks = format::markdown_to_chunks(text, format::MAX_MESSAGE_LEN);
+        let last = chunks.len().saturating_sub(1);
+        let mut sent = None;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let keyboard = (i == last).then_some(&markup);
+            sent = Some(self.send_html_with(chat, chunk, keyboard).await?);
+            chat.reply_to = None;
+        }
+        sent.context("empty message")
+    }
+
+    /// Send a single pre-rendered HTML chunk
+    pub async fn send_html(&self, chat: impl Into<ChatRef>, html: &str) -> Result<TgMessage> {
+        self.send_html_with(chat.into(), html, None).await
+    }
+
+    async fn send_html_with(&self, chat: ChatRef, html: &str, reply_markup: Option<&serde_json::Value>) -> Result<TgMessage> {
+        // Try as HTML first, then plain text, with rate limit retry
+        let mut as_html = true;
+        for _attempt in 0..3 {
+            let mut body = chat.json();
+            if let Some(markup) = reply_markup {
+                body["reply_markup"] = markup.clone();
+            }
+            if as_html {
+                body["text"] = html.into();
+                body["parse_mode"] = "HTML".into();
+            } else {
+                body["text"] = format::html_to_plain(html).into();
             }
 
             let resp: ApiResponse<TgMessage> = self
@@ -90,9 +262,10 @@ impl TelegramBot {
                 continue;
             }
 
-            // Markdown parse error — retry without parse_mode
-            if parse_mode.is_some() && (desc.contains("parse") || desc.contains("format") || desc.contains("entities")) {
-                tracing::debug!("Markdown send failed ({desc}), retrying as plain text");
+            // Entity parse error — retry as plain text
+            if as_html && (desc.contains("parse") || desc.contains("format") || desc.contains("entities")) {
+                tracing::debug!("HTML send failed ({desc}), retrying as plain text");
+                as_html = false;
                 continue;
             }
 
@@ -102,14 +275,189 @@ impl TelegramBot {
         anyhow::bail!("sendMessage failed after 3 attempts")
     }
 
-    pub async fn send_typing(&self, chat_id: i64) -> Result<()> {
+    pub async fn send_document(&self, chat: impl Into<ChatRef>, path: &Path, caption: Option<&str>) -> Result<TgMessage> {
+        self.send_media(chat, MediaKind::Document, path, caption).await
+    }
+
+    pub async fn send_photo(&self, chat: impl Into<ChatRef>, path: &Path, caption: Option<&str>) -> Result<TgMessage> {
+        self.send_media(chat, MediaKind::Photo, path, caption).await
+    }
+
+    pub async fn send_voice(&self, chat: impl Into<ChatRef>, path: &Path, caption: Option<&str>) -> Result<TgMessage> {
+        self.send_media(chat, MediaKind::Voice, path, caption).await
+    }
+
+    /// Upload a local file with sendDocument / sendPhoto / sendVoice
+    pub async fn send_media(&self, chat: impl Into<ChatRef>, kind: MediaKind, path: &Path, caption: Option<&str>) -> Result<TgMessage> {
+        let chat = chat.into();
+        let (bytes, file_name) = read_upload(path, kind)?;
+        let caption = caption.map(caption_html);
+
+        self.post_multipart(kind.method(), || {
+            let mut form = chat
+                .form()
+                .part(kind.as_str(), reqwest::multipart::Part::bytes(bytes.clone()).file_name(file_name.clone()));
+            if let Some(ref c) = caption {
+                form = form.text("caption", c.clone()).text("parse_mode", "HTML");
+            }
+            form
+        })
+        .await
+    }
+
+    /// Send 2–10 photos or documents as one album. Telegram doesn't allow
+    /// mixing photos with documents, or voice notes in albums.
+    pub async fn send_media_group(&self, chat: impl Into<ChatRef>, items: &[(MediaKind, PathBuf)], caption: Option<&str>) -> Result<Vec<TgMessage>> {
+        let chat = chat.into();
+        if !(2..=10).contains(&items.len()) {
+            anyhow::bail!("media groups need 2–10 items, got {}", items.len());
+        }
+        let kind = items[0].0;
+        if kind == MediaKind::Voice || items.iter().any(|(k, _)| *k != kind) {
+            anyhow::bail!("media groups must be all photos or all documents");
+        }
+
+        let mut files = Vec::new();
+        for (k, path) in items {
+            files.push(read_upload(path, *k)?);
+        }
+        let caption = caption.map(caption_html);
+
+        let media: Vec<serde_json::Value> = (0..files.len())
+            .map(|i| {
+                let mut m = serde_json::json!({
+                    "type": kind.as_str(),
+                    "media": format!("attach://file{i}"),
+                });
+                if i == 0
+                    && let Some(ref c) = caption
+                {
+                    m["caption"] = serde_json::Value::String(c.clone());
+                    m["parse_mode"] = serde_json::Value::String("HTML".into());
+                }
+                m
+            })
+            .collect();
+        let media = serde_json::to_string(&media)?;
+
+        self.post_multipart("sendMediaGroup", || {
+            let mut form = chat.form().text("media", media.clone());
+            for (i, (bytes, name)) in files.iter().enumerate() {
+                form = form.part(format!("file{i}"), reqwest::multipart::Part::bytes(bytes.clone()).file_name(name.clone()));
+            }
+            form
+        })
+        .await
+    }
+
+    /// POST a multipart form, retrying on rate limits. The form is rebuilt
+    /// per attempt since reqwest consumes it.
+    async fn post_multipart<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        build: impl Fn() -> reqwest::multipart::Form,
+    ) -> Result<T> {
+        for _attempt in 0..3 {
+            let resp: ApiResponse<T> = self
+                .client
+                .post(format!("{}{method}", self.base_url))
+                .timeout(Duration::from_secs(300))
+                .multipart(build())
+                .send()
+                .await
+                .with_context(|| format!("uploading via {method}"))?
+                .json()
+                .await
+                .with_context(|| format!("parsing {method} response"))?;
+
+            if resp.ok {
+                return resp.result.with_context(|| format!("no result in {method} response"));
+            }
+
+            let desc = resp.description.as_deref().unwrap_or("");
+            if desc.contains("Too Many Requests") || desc.contains("retry after") {
+                let wait = resp.parameters
+                    .and_then(|p| p.retry_after)
+                    .unwrap_or(5);
+                tracing::warn!("Telegram rate limited — waiting {wait}s");
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+
+            anyhow::bail!("{method} failed: {desc}");
+        }
+
+        anyhow::bail!("{method} failed after 3 attempts")
+    }
+
+    /// Acknowledge a button press (stops the client's spinner), with an optional toast
+    pub async fn answer_callback(&self, callback_id: &str, text: Option<&str>) -> Result<()> {
+        let mut body = serde_json::json!({ "callback_query_id": callback_id });
+        if let Some(t) = text {
+            body["text"] = t.into();
+        }
+        self.post_json::<bool>("answerCallbackQuery", &body).await.map(|_| ())
+    }
+
+    /// Replace a message's text (Markdown), dropping its inline keyboard
+    pub async fn edit_message(&self, chat_id: i64, message_id: i64, text: &str) -> Result<()> {
+        let html = format::markdown_to_chunks(text, format::MAX_MESSAGE_LEN)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let body = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": html,
+            "parse_mode": "HTML",
+        });
+        // Returns the edited message, or `true` for inline messages
+        self.post_json::<serde_json::Value>("editMessageText", &body).await.map(|_| ())
+    }
+
+    /// The bot's own user (id and @username, for mention detection)
+    pub async fn get_me(&self) -> Result<User> {
+        self.post_json("getMe", &serde_json::json!({})).await
+    }
+
+    /// Register the bot's command menu (setMyCommands)
+    pub async fn set_my_commands(&self, commands: &[(&str, &str)]) -> Result<()> {
+        let commands: Vec<serde_json::Value> = commands
+            .iter()
+            .map(|(command, description)| serde_json::json!({ "command": command, "description": description }))
+            .collect();
+        self.post_json::<bool>("setMyCommands", &serde_json::json!({ "commands": commands }))
+            .await
+            .map(|_| ())
+    }
+
+    /// POST a JSON body to a Bot API method and unwrap the result
+    async fn post_json<T: serde::de::DeserializeOwned>(&self, method: &str, body: &serde_json::Value) -> Result<T> {
+        let resp: ApiResponse<T> = self
+            .client
+            .post(format!("{}{method}", self.base_url))
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("sending {method}"))?
+            .json()
+            .await
+            .with_context(|| format!("parsing {method} response"))?;
+
+        if !resp.ok {
+            anyhow::bail!("{method} failed: {}", resp.description.unwrap_or_default());
+        }
+        resp.result.with_context(|| format!("no result in {method} response"))
+    }
+
+    pub async fn send_typing(&self, chat: impl Into<ChatRef>) -> Result<()> {
+        let chat = ChatRef { reply_to: None, ..chat.into() };
+        let mut body = chat.json();
+        body["action"] = "typing".into();
         let _resp: ApiResponse<bool> = self
             .client
             .post(format!("{}sendChatAction", self.base_url))
-            .json(&serde_json::json!({
-                "chat_id": chat_id,
-                "action": "typing",
-            }))
+            .json(&body)
             .send()
             .await
             .context("sending typing action")?
@@ -191,3 +539,38 @@ impl TelegramBot {
         self.allowed_users.is_empty() || self.allowed_users.contains(&user_id)
     }
 }
+
+/// Read a file for upload, enforcing the Bot API size limit for its kind
+fn read_upload(path: &Path, kind: MediaKind) -> Result<(Vec<u8>, String)> {
+    let meta = std::fs::metadata(path)
+        .with_context(|| format!("reading {}", path.display()))?;
+    if !meta.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    if meta.len() > kind.max_bytes() {
+        anyhow::bail!(
+            "{} is {} bytes — Telegram's limit for a {} is {} MB",
+            path.display(),
+            meta.len(),
+            kind.as_str(),
+            kind.max_bytes() / (1024 * 1024)
+        );
+    }
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".into());
+    Ok((bytes, name))
+}
+
+/// Render a caption as HTML, falling back to truncated plain text if the
+/// markup would push it over the caption limit
+fn caption_html(caption: &str) -> String {
+    let html = format::markdown_to_html(caption);
+    if html.chars().count() <= MAX_CAPTION_LEN {
+        return html;
+    }
+    let plain: String = caption.chars().take(MAX_CAPTION_LEN - 1).collect();
+    format::escape_html(&format!("{plain}…"))
+}
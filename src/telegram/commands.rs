@@ -0,0 +1,89 @@
+/// Slash commands handled by `serve` before a message reaches the agent.
+/// Registered with setMyCommands as (command, description).
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("new", "Start a fresh conversation (/new compact keeps a summary)"),
+    ("stop", "Cancel the running turn"),
+    ("model", "Model for this chat: quick, standard, complex or default"),
+    ("thinking", "Extended thinking: off, low, medium, high or default"),
+    ("task", "Pick the active task for this chat"),
+    ("undo", "Undo the agent's file changes from the last turn (/undo N for more)"),
+    ("changes", "List files the agent changed in this conversation, with diffs"),
+    ("dryrun", "Preview the agent's changes instead of making them: on or off"),
+    ("cost", "Show this chat's cost and context size"),
+    ("status", "Show model, thinking, task, dry run and context for this chat"),
+];
+
+/// A parsed `/command@bot args` message
+#[derive(Debug, PartialEq)]
+pub struct BotCommand<'a> {
+    /// Lowercased command name without the slash
+    pub name: String,
+    /// `@botname` suffix, if the command was addressed to a specific bot
+    pub bot: Option<&'a str>,
+    /// Everything after the command, trimmed
+    pub args: &'a str,
+}
+
+/// Parse a message as a slash command. Returns `None` for ordinary text.
+pub fn parse(text: &str) -> Option<BotCommand<'_>> {
+    let rest = text.trim().strip_prefix('/')?;
+    let (head, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let (name, bot) = match head.split_once('@') {
+        Some((n, b)) => (n, Some(b)),
+        None => (head, None),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(BotCommand {
+        name: name.to_ascii_lowercase(),
+        bot,
+        args: args.trim(),
+    })
+}
+
+/// Is this one of ours? Unknown commands go to the agent as plain text.
+pub fn is_known(name: &str) -> bool {
+    name == "start" || name == "help" || COMMANDS.iter().any(|(n, _)| *n == name)
+}
+
+/// Help text listing every command
+pub fn help_text() -> String {
+    let mut out = String::from("Commands:\n");
+    for (name, desc) in COMMANDS {
+        out.push_str(&format!("/{name} — {desc}\n"));
+    }
+    out
+}
+
+/// Byte range of the first `@username` mention (case-insensitive, whole word).
+/// Telegram usernames are ASCII, so matching on bytes is safe.
+pub fn find_mention(text: &str, username: &str) -> Option<std::ops::Range<usize>> {
+    let bytes = text.as_bytes();
+    let name = username.as_bytes();
+    if name.is_empty() {
+        return None;
+    }
+    (0..bytes.len()).find_map(|start| {
+        let end = start + 1 + name.len();
+        let is_match = bytes[start] == b'@'
+            && end <= bytes.len()
+            && bytes[start + 1..end].eq_ignore_ascii_case(name)
+            && bytes.get(end).is_none_or(|c| !(c.is_ascii_alphanumeric() || *c == b'_'));
+        is_match.then_some(start..end)
+    })
+}
+
+/// Remove every `@username` mention (and one adjoining space)
+pub fn strip_mention(text: &str, username: &str) -> String {
+    let mut out = text.to_string();
+    while let Some(mut range) = find_mention(&out, username) {
+        if out[range.end..].starts_with(' ') {
+            range.end += 1;
+        } else if out[..range.start].ends_with(' ') {
+            range.start -= 1;
+        }
+        out.replace_range(range, "");
+    }
+    out.trim().to_string()
+}
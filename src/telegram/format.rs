@@ -0,0 +1,657 @@
+//! Markdown → Telegram HTML conversion and entity-safe message splitting.
+//!
+//! Claude answers in CommonMark. Telegram's legacy `Markdown` parse mode
+//! rejects most of it, so we convert to the (much more forgiving) `HTML`
+//! parse mode instead and split long messages without cutting through tags.
+
+/// Telegram rejects messages over 4096 characters; leave some headroom.
+pub const MAX_MESSAGE_LEN: usize = 4000;
+
+/// Convert model Markdown to Telegram-flavoured HTML.
+///
+/// Supports fenced/inline code, bold, italic, strikethrough, links,
+/// headings, blockquotes and bullet lists. Anything else is escaped and
+/// passed through as plain text.
+pub fn markdown_to_html(md: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut quote: Vec<String> = Vec::new();
+    let mut lines = md.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        // Blockquotes are grouped so consecutive `>` lines become one entity
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            quote.push(inline_to_html(rest.strip_prefix(' ').unwrap_or(rest)));
+            continue;
+        }
+        if !quote.is_empty() {
+            out.push(format!("<blockquote>{}</blockquote>", quote.join("\n")));
+            quote.clear();
+        }
+
+        if indent <= 3 {
+            if let Some((fence, lang)) = fence_open(trimmed) {
+                let mut code: Vec<&str> = Vec::new();
+                for inner in lines.by_ref() {
+                    let t = inner.trim_start();
+                    if t.starts_with(fence) && t[fence.len()..].trim().is_empty() {
+                        break;
+                    }
+                    code.push(inner);
+                }
+                let body = escape_html(&code.join("\n"));
+                if lang.is_empty() {
+                    out.push(format!("<pre>{body}</pre>"));
+                } else {
+                    out.push(format!(
+                        "<pre><code class=\"language-{}\">{body}</code></pre>",
+                        escape_html(lang)
+                    ));
+                }
+                continue;
+            }
+
+            if let Some(heading) = heading_text(trimmed) {
+                out.push(format!("<b>{}</b>", inline_to_html(heading)));
+                continue;
+            }
+
+            if is_rule(trimmed) {
+                out.push("——————".into());
+                continue;
+            }
+        }
+
+        if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|m| trimmed.strip_prefix(m)) {
+            out.push(format!("{}• {}", &line[..indent], inline_to_html(item)));
+            continue;
+        }
+
+        out.push(inline_to_html(line));
+    }
+
+    if !quote.is_empty() {
+        out.push(format!("<blockquote>{}</blockquote>", quote.join("\n")));
+    }
+
+    out.join("\n")
+}
+
+/// Opening code fence: returns the fence marker and the info string (language)
+fn fence_open(line: &str) -> Option<(&'static str, &str)> {
+    let fence = if line.starts_with("```") {
+        "```"
+    } else if line.starts_with("~~~") {
+        "~~~"
+    } else {
+        return None;
+    };
+    let lang = line[fence.len()..].trim_start_matches(&fence[..1]).trim();
+    let lang = lang.split_whitespace().next().unwrap_or("");
+    Some((fence, lang))
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    line[hashes..]
+        .strip_prefix(' ')
+        .map(|t| t.trim().trim_end_matches('#').trim_end())
+        .filter(|t| !t.is_empty())
+}
+
+fn is_rule(line: &str) -> bool {
+    let t: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    t.len() >= 3 && ['-', '*', '_'].iter().any(|&c| t.chars().all(|x| x == c))
+}
+
+/// Convert inline Markdown (code spans, emphasis, links) to HTML
+pub fn inline_to_html(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Backslash escapes
+        if c == '\\' && i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() {
+            push_escaped(&mut out, chars[i + 1]);
+            i += 2;
+            continue;
+        }
+
+        // Code spans — content is literal
+        if c == '`' {
+            let run = count_run(&chars, i, '`');
+            if let Some(end) = find_backtick_close(&chars, i + run, run) {
+                let code: String = chars[i + run..end].iter().collect();
+                let code = if code.len() > 2 && code.starts_with(' ') && code.ends_with(' ') {
+                    &code[1..code.len() - 1]
+                } else {
+                    code.as_str()
+                };
+                out.push_str(&format!("<code>{}</code>", escape_html(code)));
+                i = end + run;
+                continue;
+            }
+            for _ in 0..run {
+                out.push('`');
+            }
+            i += run;
+            continue;
+        }
+
+        // Links: [text](url)
+        if c == '['
+            && let Some((label, url, next)) = parse_link(&chars, i)
+        {
+            out.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape_attr(&url),
+                inline_to_html(&label)
+            ));
+            i = next;
+            continue;
+        }
+
+        // Autolinks: <https://...>
+        if c == '<'
+            && let Some(close) = chars[i + 1..].iter().position(|&x| x == '>')
+        {
+            let inner: String = chars[i + 1..i + 1 + close].iter().collect();
+            if (inner.starts_with("http://") || inner.starts_with("https://"))
+                && !inner.contains(char::is_whitespace)
+            {
+                out.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_attr(&inner),
+                    escape_html(&inner)
+                ));
+                i += close + 2;
+                continue;
+            }
+        }
+
+        // Emphasis: **bold**, __bold__, *italic*, _italic_, ~~strike~~
+        if c == '*' || c == '_' || c == '~' {
+            let run = count_run(&chars, i, c);
+            let width = match (c, run) {
+                ('~', n) if n >= 2 => 2,
+                ('~', _) => 0,
+                (_, n) if n >= 2 => 2,
+                _ => 1,
+            };
+            if width > 0
+                && let Some(end) = find_emphasis_close(&chars, i, c, width)
+            {
+                let inner: String = chars[i + width..end].iter().collect();
+                let tag = match (c, width) {
+                    ('~', _) => "s",
+                    (_, 2) => "b",
+                    _ => "i",
+                };
+                out.push_str(&format!("<{tag}>{}</{tag}>", inline_to_html(&inner)));
+                i = end + width;
+                continue;
+            }
+            for _ in 0..run {
+                push_escaped(&mut out, c);
+            }
+            i += run;
+            continue;
+        }
+
+        push_escaped(&mut out, c);
+        i += 1;
+    }
+
+    out
+}
+
+fn count_run(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|&&x| x == c).count()
+}
+
+fn find_backtick_close(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut j = from;
+    while j < chars.len() {
+        if chars[j] == '`' {
+            let r = count_run(chars, j, '`');
+            if r == run {
+                return Some(j);
+            }
+            j += r;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// Find the closing delimiter for an emphasis run opened at `start`.
+/// Follows the CommonMark flanking rules closely enough for model output:
+/// the opener must not be followed by whitespace, the closer must not be
+/// preceded by whitespace, and `_` never opens or closes inside a word.
+fn find_emphasis_close(chars: &[char], start: usize, c: char, width: usize) -> Option<usize> {
+    let after_open = *chars.get(start + width)?;
+    if after_open.is_whitespace() {
+        return None;
+    }
+    if c == '_' && start > 0 && chars[start - 1].is_alphanumeric() {
+        return None;
+    }
+
+    let mut j = start + width;
+    while j < chars.len() {
+        if chars[j] == '\\' {
+            j += 2;
+            continue;
+        }
+        if chars[j] == '`' {
+            let run = count_run(chars, j, '`');
+            j = find_backtick_close(chars, j + run, run).map_or(j + run, |e| e + run);
+            continue;
+        }
+        if chars[j] == c {
+            let run = count_run(chars, j, c);
+            let before = chars[j - 1];
+            let after = chars.get(j + run).copied();
+            let closes = run >= width
+                && j > start + width
+                && !before.is_whitespace()
+                && !(c == '_' && after.is_some_and(|a| a.is_alphanumeric()));
+            if closes {
+                // For single delimiters, skip over nested `**` runs
+                if width == 1 && run >= 2 && c != '~' {
+                    j += run;
+                    continue;
+                }
+                return Some(j + run - width);
+            }
+            j += run;
+            continue;
+        }
+        j += 1;
+    }
+    None
+}
+
+/// Parse `[label](url)` starting at `start`; returns (label, url, next index)
+fn parse_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut j = start;
+    let close = loop {
+        match chars.get(j)? {
+            '\\' => j += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break j;
+                }
+            }
+            _ => {}
+        }
+        j += 1;
+    };
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let mut parens = 0;
+    let mut k = close + 2;
+    let end = loop {
+        match chars.get(k)? {
+            '(' => parens += 1,
+            ')' if parens == 0 => break k,
+            ')' => parens -= 1,
+            c if c.is_whitespace() => return None,
+            _ => {}
+        }
+        k += 1;
+    };
+    let label: String = chars[start + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+    if url.is_empty() {
+        return None;
+    }
+    Some((label, url, end + 1))
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        _ => out.push(c),
+    }
+}
+
+/// Escape text for Telegram HTML
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_escaped(&mut out, c);
+    }
+    out
+}
+
+fn escape_attr(text: &str) -> String {
+    escape_html(text).replace('"', "&quot;")
+}
+
+/// Strip tags and decode entities — the plain-text fallback for a chunk
+/// Telegram refuses to parse.
+pub fn html_to_plain(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// Convert Markdown and split it into Telegram-sized HTML chunks
+pub fn markdown_to_chunks(md: &str, max_len: usize) -> Vec<String> {
+    split_html(&markdown_to_html(md), max_len)
+}
+
+enum Token<'a> {
+    Open { name: &'a str, raw: &'a str },
+    Close { raw: &'a str },
+    Text(&'a str),
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with('<')
+            && let Some(end) = rest.find('>')
+        {
+            let raw = &rest[..=end];
+            if raw.starts_with("</") {
+                tokens.push(Token::Close { raw });
+            } else {
+                let name_end = raw[1..]
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .map_or(raw.len() - 1, |p| p + 1);
+                tokens.push(Token::Open { name: &raw[1..name_end], raw });
+            }
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '<')
+            .map_or(rest.len(), |(p, _)| p);
+        tokens.push(Token::Text(&rest[..end]));
+        rest = &rest[end..];
+    }
+    tokens
+}
+
+/// Split Telegram HTML into chunks of at most `max_len` bytes.
+///
+/// Prefers to break at newlines, then spaces. Never cuts through a tag or
+/// an `&entity;`. Tags open at a split point are closed at the end of the
+/// chunk and re-opened at the start of the next one, so a long code block
+/// continues as a code block (same language) in the following message.
+pub fn split_html(html: &str, max_len: usize) -> Vec<String> {
+    if html.len() <= max_len {
+        return vec![html.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut stack: Vec<(&str, &str)> = Vec::new();
+    let mut current = String::new();
+    // Length of `current` that is only re-opened tags (no content yet)
+    let mut prefix_len = 0;
+
+    let closing_len = |stack: &[(&str, &str)]| -> usize {
+        stack.iter().map(|(name, _)| name.len() + 3).sum()
+    };
+
+    let flush = |current: &mut String, prefix_len: &mut usize, stack: &[(&str, &str)], chunks: &mut Vec<String>| {
+        if current.len() > *prefix_len {
+            for (name, _) in stack.iter().rev() {
+                current.push_str(&format!("</{name}>"));
+            }
+            chunks.push(std::mem::take(current));
+        }
+        current.clear();
+        for (_, raw) in stack {
+            current.push_str(raw);
+        }
+        *prefix_len = current.len();
+    };
+
+    for token in tokenize(html) {
+        match token {
+            Token::Open { name, raw } => {
+                if current.len() + raw.len() + name.len() + 3 + closing_len(&stack) > max_len {
+                    flush(&mut current, &mut prefix_len, &stack, &mut chunks);
+                }
+                current.push_str(raw);
+                stack.push((name, raw));
+            }
+            Token::Close { raw } => {
+                current.push_str(raw);
+                stack.pop();
+            }
+            Token::Text(mut text) => loop {
+                let budget = max_len.saturating_sub(current.len() + closing_len(&stack));
+                if text.len() <= budget {
+                    current.push_str(text);
+                    break;
+                }
+                let cut = split_point(text, budget);
+                if cut == 0 {
+                    if current.len() > prefix_len {
+                        // Nothing fits — start a fresh chunk and retry
+                        flush(&mut current, &mut prefix_len, &stack, &mut chunks);
+                        continue;
+                    }
+                    // Pathological: tags alone fill the budget — hard cut
+                    let cut = floor_char_boundary(text, budget.max(1)).max(text.chars().next().map_or(1, char::len_utf8));
+                    current.push_str(&text[..cut]);
+                    text = &text[cut..];
+                } else {
+                    current.push_str(text[..cut].trim_end_matches('\n'));
+                    text = &text[cut..];
+                }
+                flush(&mut current, &mut prefix_len, &stack, &mut chunks);
+                text = text.strip_prefix('\n').unwrap_or(text);
+                if text.is_empty() {
+                    break;
+                }
+            },
+        }
+    }
+
+    if current.len() > prefix_len || chunks.is_empty() {
+        for (name, _) in stack.iter().rev() {
+            current.push_str(&format!("</{name}>"));
+        }
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Best place to cut `text` within `budget` bytes: last newline, then last
+/// space, then a hard cut — never inside an `&entity;`. Returns 0 if only a
+/// hard cut is possible and the caller should try a fresh chunk first.
+fn split_point(text: &str, budget: usize) -> usize {
+    if budget == 0 {
+        return 0;
+    }
+    let window = &text[..floor_char_boundary(text, budget)];
+    if let Some(p) = window.rfind('\n').filter(|&p| p > 0) {
+        return p;
+    }
+    if let Some(p) = window.rfind(' ').filter(|&p| p > 0) {
+        return p + 1;
+    }
+    // Hard cut — back off if we'd land inside an entity
+    let mut cut = window.len();
+    if let Some(amp) = window.rfind('&')
+        && !window[amp..].contains(';')
+    {
+        cut = amp;
+    }
+    cut
+}
+
+fn floor_char_boundary(s: &str, mut idx: usize) -> usize {
+    if idx >= s.len() {
+        return s.len();
+    }
+    while !s.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escapes_html() {
+        assert_eq!(markdown_to_html("a < b && c > d"), "a &lt; b &amp;&amp; c &gt; d");
+    }
+
+    #[test]
+    fn test_bold_italic_strike() {
+        assert_eq!(
+            markdown_to_html("**bold** and *it* and _it_ and ~~gone~~"),
+            "<b>bold</b> and <i>it</i> and <i>it</i> and <s>gone</s>"
+        );
+    }
+
+    #[test]
+    fn test_nested_emphasis() {
+        assert_eq!(markdown_to_html("**bold *and it***"), "<b>bold <i>and it</i></b>");
+    }
+
+    #[test]
+    fn test_snake_case_untouched() {
+        assert_eq!(markdown_to_html("call my_func_name now"), "call my_func_name now");
+    }
+
+    #[test]
+    fn test_lone_asterisks_literal() {
+        assert_eq!(markdown_to_html("2 * 3 * 4"), "2 * 3 * 4");
+    }
+
+    #[test]
+    fn test_inline_code_is_literal() {
+        assert_eq!(
+            markdown_to_html("run `a **b** <c>` now"),
+            "run <code>a **b** &lt;c&gt;</code> now"
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_block() {
+        let md = "Here:\n```rust\nfn main() { x < 1 }\n```\ndone";
+        assert_eq!(
+            markdown_to_html(md),
+            "Here:\n<pre><code class=\"language-rust\">fn main() { x &lt; 1 }</code></pre>\ndone"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_fence_runs_to_end() {
+        assert_eq!(markdown_to_html("```\n**x**"), "<pre>**x**</pre>");
+    }
+
+    #[test]
+    fn test_link() {
+        assert_eq!(
+            markdown_to_html("see [the *docs*](https://x.io/a?b=1&c=\"2\")"),
+            "see <a href=\"https://x.io/a?b=1&amp;c=&quot;2&quot;\">the <i>docs</i></a>"
+        );
+    }
+
+    #[test]
+    fn test_brackets_without_link() {
+        assert_eq!(markdown_to_html("arr[0] = [1]"), "arr[0] = [1]");
+    }
+
+    #[test]
+    fn test_heading_list_quote() {
+        let md = "## Title\n- one\n  * two\n> quoted\n> more\nafter";
+        assert_eq!(
+            markdown_to_html(md),
+            "<b>Title</b>\n• one\n  • two\n<blockquote>quoted\nmore</blockquote>\nafter"
+        );
+    }
+
+    #[test]
+    fn test_backslash_escape() {
+        assert_eq!(markdown_to_html(r"\*not italic\*"), "*not italic*");
+    }
+
+    #[test]
+    fn test_split_short_message_untouched() {
+        assert_eq!(split_html("<b>hi</b>", 100), vec!["<b>hi</b>"]);
+    }
+
+    #[test]
+    fn test_split_prefers_newlines() {
+        let chunks = split_html("aaaa\nbbbb\ncccc", 10);
+        assert_eq!(chunks, vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn test_split_reopens_code_block() {
+        let body = (0..40).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let md = format!("```python\n{body}\n```");
+        let chunks = markdown_to_chunks(&md, 120);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 120, "chunk too long: {}", chunk.len());
+            assert!(chunk.starts_with("<pre><code class=\"language-python\">"), "{chunk}");
+            assert!(chunk.ends_with("</code></pre>"), "{chunk}");
+        }
+        let text: String = chunks.iter().map(|c| html_to_plain(c)).collect::<Vec<_>>().join("\n");
+        assert_eq!(text, body);
+    }
+
+    #[test]
+    fn test_split_never_breaks_entity() {
+        let html = "&amp;".repeat(50);
+        for chunk in split_html(&html, 23) {
+            assert!(chunk.len() <= 23);
+            assert!(chunk.starts_with('&') && chunk.ends_with(';'), "{chunk}");
+        }
+    }
+
+    #[test]
+    fn test_split_multibyte() {
+        let html = "é".repeat(100);
+        let chunks = split_html(&html, 15);
+        assert!(chunks.iter().all(|c| c.len() <= 15));
+        assert_eq!(chunks.concat(), html);
+    }
+
+    #[test]
+    fn test_html_to_plain() {
+        assert_eq!(html_to_plain("<b>a &lt;b&gt; &amp;</b>"), "a <b> &");
+    }
+}
@@ -1,2 +1,4 @@
 pub mod api;
+pub mod commands;
+pub mod format;
 pub mod types;
@@ -4,6 +4,18 @@ use serde::Deserialize;
 pub struct Update {
     pub update_id: i64,
     pub message: Option<TgMessage>,
+    /// An inline keyboard button was pressed
+    pub callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    /// The message carrying the keyboard
+    pub message: Option<TgMessage>,
+    /// The button's callback_data
+    pub data: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -21,6 +33,67 @@ pub struct TgMessage {
     pub audio: Option<Audio>,
     pub video: Option<Video>,
     pub sticker: Option<Sticker>,
+    /// Forum topic (or reply thread) this message belongs to
+    pub message_thread_id: Option<i64>,
+    /// True if the message was sent in a forum topic
+    #[serde(default)]
+    pub is_topic_message: bool,
+    pub reply_to_message: Option<Box<TgMessage>>,
+    /// The part of the replied-to message the user quoted
+    pub quote: Option<TextQuote>,
+    pub entities: Option<Vec<MessageEntity>>,
+    pub caption_entities: Option<Vec<MessageEntity>>,
+}
+
+impl TgMessage {
+    /// Text, or the caption for media messages
+    pub fn body(&self) -> Option<&str> {
+        self.text.as_deref().or(self.caption.as_deref())
+    }
+
+    /// The message this one replies to. In forum topics every message points
+    /// at the topic's first message, which isn't a real reply.
+    pub fn replied(&self) -> Option<&TgMessage> {
+        let reply = self.reply_to_message.as_deref()?;
+        if self.is_topic_message && Some(reply.message_id) == self.message_thread_id {
+            return None;
+        }
+        Some(reply)
+    }
+
+    /// Is this a reply to something `user_id` sent?
+    pub fn is_reply_to(&self, user_id: i64) -> bool {
+        self.replied()
+            .and_then(|r| r.from.as_ref())
+            .is_some_and(|u| u.id == user_id)
+    }
+
+    /// Does the message @mention this user, by username or text_mention?
+    pub fn mentions(&self, username: &str, user_id: i64) -> bool {
+        let by_entity = self
+            .entities
+            .iter()
+            .chain(self.caption_entities.iter())
+            .flatten()
+            .any(|e| e.entity_type == "text_mention" && e.user.as_ref().is_some_and(|u| u.id == user_id));
+        by_entity || self.body().is_some_and(|b| super::commands::find_mention(b, username).is_some())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageEntity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    /// Offset and length are in UTF-16 code units
+    pub offset: usize,
+    pub length: usize,
+    /// For "text_mention" (users without a username)
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextQuote {
+    pub text: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -92,17 +165,34 @@ pub struct TgFile {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Chat {
     pub id: i64,
+    /// "private", "group", "supergroup" or "channel"
     #[serde(rename = "type")]
     pub chat_type: String,
+    pub title: Option<String>,
+}
+
+impl Chat {
+    pub fn is_group(&self) -> bool {
+        self.chat_type == "group" || self.chat_type == "supergroup"
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct User {
     pub id: i64,
+    #[serde(default)]
+    pub is_bot: bool,
     pub first_name: String,
     pub username: Option<String>,
 }
 
+impl User {
+    /// @username if set, otherwise first name
+    pub fn display_name(&self) -> String {
+        self.username.clone().unwrap_or_else(|| self.first_name.clone())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ApiResponse<T> {
     pub ok: bool,
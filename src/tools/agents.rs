@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::inbox::Inbox;
+use crate::types::ToolDefinition;
+
+// Tools only sub-agents get: they need a run of their own to speak for
+
+pub fn message_agent_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "message_agent".into(),
+        description: "Send a note to another running sub-agent — findings it needs, a file you've written for it, a warning about something you hit. It sees the note before its next step. Address it by run id, or by task id to reach every agent on that task.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "to": {
+                    "type": "string",
+                    "description": "Run id or task id of the agent(s) to message"
+                },
+                "message": {
+                    "type": "string",
+                    "description": "What to tell them"
+                }
+            },
+            "required": ["to", "message"]
+        }),
+    }
+}
+
+pub fn message_agent_execute(input: &serde_json::Value, inbox: &Inbox) -> Result<String> {
+    let to = input["to"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'to' field"))?;
+    let message = input["message"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'message' field"))?;
+
+    let recipients = inbox.resolve(to);
+    if recipients.is_empty() {
+        anyhow::bail!("no running agent with run id or task id '{to}'");
+    }
+    for run_id in &recipients {
+        inbox.send_to(run_id, message)?;
+    }
+    Ok(format!("Sent to {}", recipients.join(", ")))
+}
+
+pub fn request_escalation_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "request_escalation".into(),
+        description: "Hand this task to a stronger model when it's beyond you: you've tried and keep failing, or it needs deeper reasoning than you can give. You stop here and the stronger model carries on from this conversation, so say what you've tried and where you're stuck.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "reason": {
+                    "type": "string",
+                    "description": "Why, what you've tried, and where you're stuck"
+                }
+            },
+            "required": ["reason"]
+        }),
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::ask::{self, Origin, Question, QuestionStore, SuspendedRun};
+use crate::telegram::api::ChatTarget;
+use crate::types::ToolDefinition;
+
+/// Default wait before giving up on an answer
+const DEFAULT_TIMEOUT_MINUTES: u64 = 10;
+/// Background agents can be left waiting overnight
+const MAX_TIMEOUT_MINUTES: u64 = 24 * 60;
+/// Telegram allows up to 100 buttons, but a long list isn't a choice
+const MAX_OPTIONS: usize = 10;
+
+pub fn definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "ask_user".into(),
+        description: "Ask the user a question and wait for their answer. Use it for decisions you shouldn't make alone: approvals, choosing between options, missing details. The question goes to wherever you were started from: the Telegram chat, the dashboard, or the terminal. With `options` the user gets buttons; they can still type an answer unless allow_text is false. Returns the answer, the `default` if nobody answers in time, or a note that nobody answered.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question (Markdown)"
+                },
+                "options": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Answers to offer as buttons (up to 10, short labels)"
+                },
+                "allow_text": {
+                    "type": "boolean",
+                    "description": "Accept a typed reply as the answer (default: true)"
+                },
+                "default": {
+                    "type": "string",
+                    "description": "Answer to go with if nobody replies in time"
+                },
+                "timeout_minutes": {
+                    "type": "integer",
+                    "description": "How long to wait (default: 10, max: 1440)"
+                }
+            },
+            "required": ["question"]
+        }),
+    }
+}
+
+/// Who's asking: where the question goes, and the agent checkpoint to
+/// resume from if the process restarts while it waits
+#[derive(Default)]
+pub struct AskContext<'a> {
+    pub chat: Option<&'a ChatTarget>,
+    pub origin: Option<&'a Origin>,
+    pub run: Option<SuspendedRun>,
+}
+
+pub async fn execute(input: &serde_json::Value, ctx: AskContext<'_>) -> Result<String> {
+    let origin = ctx
+        .origin
+        .cloned()
+        .or_else(|| ctx.chat.map(Origin::from))
+        .ok_or_else(|| anyhow::anyhow!("ask_user has nobody to ask — no chat, dashboard or terminal started this agent"))?;
+    let question = input["question"]
+        .as_str()
+        .filter(|q| !q.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("question is required"))?;
+    let options: Vec<String> = input["options"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    if options.len() > MAX_OPTIONS {
+        anyhow::bail!("at most {MAX_OPTIONS} options");
+    }
+    // Without buttons, typing is the only way to answer
+    let allow_text = input["allow_text"].as_bool().unwrap_or(true) || options.is_empty();
+    let minutes = input["timeout_minutes"]
+        .as_u64()
+        .unwrap_or(DEFAULT_TIMEOUT_MINUTES)
+        .clamp(1, MAX_TIMEOUT_MINUTES);
+    let default = input["default"].as_str().filter(|d| !d.is_empty()).map(String::from);
+
+    let now = chrono::Utc::now();
+    let q = Question {
+        id: ask::new_id(),
+        origin,
+        question: question.to_string(),
+        options,
+        allow_text,
+        default,
+        asked_at: now,
+        deadline: now + chrono::Duration::minutes(minutes as i64),
+        answer: None,
+        run: ctx.run,
+    };
+
+    let store = QuestionStore::open();
+    store.save(&q)?;
+    let rx = ask::wait_for(&q);
+    let _guard = CloseOnDrop(&q.id, &store);
+
+    let sent = match &q.origin {
+        Origin::Telegram { bot, chat_id, thread_id } => {
+            let target = match ctx.chat {
+                Some(chat) => chat.clone(),
+                None => ChatTarget {
+                    bot: ask::bot(bot).ok_or_else(|| anyhow::anyhow!("Telegram bot '{bot}' isn't running"))?,
+                    name: bot.clone(),
+                    chat_id: *chat_id,
+                    thread_id: *thread_id,
+                },
+            };
+            let message_id = send_keyboard(&target, &q).await?;
+            Some((target.bot, *chat_id, message_id))
+        }
+        // Listed at /api/questions until answered
+        Origin::Dashboard => {
+            tracing::info!("❓ Waiting for an answer on the dashboard: {question}");
+            None
+        }
+        Origin::Cli => {
+            prompt_cli(&q);
+            None
+        }
+    };
+
+    let answer = tokio::time::timeout(Duration::from_secs(minutes * 60), rx).await;
+    let (note, result) = match answer {
+        Ok(Ok(answer)) => (format!("✅ {answer}"), q.answered(&answer)),
+        Ok(Err(_)) => ("_Question withdrawn._".to_string(), "The question was withdrawn.".to_string()),
+        Err(_) => match &q.default {
+            Some(d) => (format!("_No answer — going with:_ {d}"), q.timed_out()),
+            None => ("_No answer — carrying on without one._".to_string(), q.timed_out()),
+        },
+    };
+
+    match sent {
+        // Swap the buttons for the outcome
+        Some((bot, chat_id, message_id)) => {
+            if let Err(e) = bot.edit_message(chat_id, message_id, &format!("❓ {question}\n\n{note}")).await {
+                tracing::debug!("Couldn't update question message: {e}");
+            }
+        }
+        None if q.origin == Origin::Cli => eprintln!("{note}"),
+        None => {}
+    }
+    Ok(result)
+}
+
+/// Post the question with one button per option, returning its message id
+async fn send_keyboard(chat: &ChatTarget, q: &Question) -> Result<i64> {
+    let mut text = format!("❓ {}", q.question);
+    if q.allow_text && !q.options.is_empty() {
+        text.push_str("\n\n_Tap an option or type your own answer._");
+    }
+    if let Some(d) = &q.default {
+        text.push_str(&format!("\n_If nobody answers: {d}_"));
+    }
+    let rows: Vec<Vec<(String, String)>> = q
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, label)| vec![(label.clone(), format!("{}{}:{i}", ask::CALLBACK_PREFIX, q.id))])
+        .collect();
+    Ok(chat.bot.send_keyboard(chat.dest(), &text, &rows).await?.message_id)
+}
+
+/// Print the question on the terminal and answer it from the next line of stdin
+fn prompt_cli(q: &Question) {
+    use std::io::IsTerminal;
+    eprintln!("\n❓ {}", q.question);
+    for (i, option) in q.options.iter().enumerate() {
+        eprintln!("  {}. {option}", i + 1);
+    }
+    if let Some(d) = &q.default {
+        eprintln!("  (no answer: {d})");
+    }
+    // Piped runs have nobody to ask — let the timeout/default apply
+    if !std::io::stdin().is_terminal() {
+        return;
+    }
+    eprint!("> ");
+    let q = q.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line).is_ok() && !line.trim().is_empty() {
+            ask::answer(&q.id, &q.parse_answer(&line));
+        }
+    });
+}
+
+/// Withdraws and forgets the question once the tool returns or the turn is cancelled
+struct CloseOnDrop<'a>(&'a str, &'a QuestionStore);
+
+impl Drop for CloseOnDrop<'_> {
+    fn drop(&mut self) {
+        ask::cancel(self.0);
+        self.1.remove(self.0);
+    }
+}
@@ -2,13 +2,14 @@ use anyhow::{Context, Result};
 use serde_json::Value;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::config::{Config, ScopedBotConfig};
+use crate::config::{Config, ScopedBotConfig, TelegramConfig};
 use crate::memory::MemoryManager;
 
 /// Global restart flag — set by tools that need a serve loop restart
 pub static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
 
-pub async fn assign_bot_execute(input: &Value) -> Result<String> {
+/// The config entry assign_bot would add, checked against `telegram`'s bots
+fn new_bot(input: &Value, telegram: Option<&TelegramConfig>) -> Result<ScopedBotConfig> {
     let name = input["name"].as_str().unwrap_or("").to_string();
     let bot_token = input["bot_token"].as_str().unwrap_or("").to_string();
     let tasks: Vec<String> = input["tasks"]
@@ -37,40 +38,14 @@ pub async fn assign_bot_execute(input: &Value) -> Result<String> {
 
     let system_prompt = input["system_prompt"].as_str().map(String::from);
 
-    // --- 1. Create task in memory if it doesn't exist ---
-    let mm = MemoryManager::new(MemoryManager::default_root());
-    let mut tasks_created = Vec::new();
-    for task in &tasks {
-        if task == "*" {
-            continue;
-        }
-        // Check if task already exists
-        if mm.load_task(task).is_err() {
-            // Create the task
-            match mm.create_task(task, "general") {
-                Ok(msg) => tasks_created.push(msg),
-                Err(e) => tracing::warn!("Failed to create task '{}': {}", task, e),
-            }
-        }
-    }
-
-    // --- 2. Update config ---
-    let mut config = Config::load().context("loading config")?;
-
-    let tg = config.telegram.get_or_insert_with(|| crate::config::TelegramConfig {
-        bot_token: None,
-        allowed_users: vec![],
-        bots: vec![],
-    });
-
     // Check for duplicate name
-    if tg.bots.iter().any(|b| b.name == name) {
+    if telegram.is_some_and(|tg| tg.bots.iter().any(|b| b.name == name)) {
         anyhow::bail!("Bot '{}' already exists. Remove it first or use a different name.", name);
     }
 
     // If no allowed_users specified, inherit from manager
     let users = if allowed_users.is_empty() {
-        tg.allowed_users.clone()
+        telegram.map(|tg| tg.allowed_users.clone()).unwrap_or_default()
     } else {
         allowed_users
     };
@@ -86,11 +61,11 @@ pub async fn assign_bot_execute(input: &Value) -> Result<String> {
         )
     });
 
-    let new_bot = ScopedBotConfig {
-        name: name.clone(),
+    Ok(ScopedBotConfig {
+        name,
         bot_token,
         allowed_users: users,
-        tasks: tasks.clone(),
+        tasks,
         system_prompt: Some(prompt),
         system_prompt_file: None,
         default_model,
@@ -99,10 +74,48 @@ pub async fn assign_bot_execute(input: &Value) -> Result<String> {
         max_turns: 20,
         bot_type: "standard".to_string(),
         working_directory: None,
+        test_command: None,
         max_budget_usd: 1.0,
         dev_timeout_seconds: 600,
-    };
+        voice_reply: "off".to_string(),
+        allowed_groups: Vec::new(),
+        group_trigger: "mention".to_string(),
+        sandbox: None,
+        tools: Default::default(),
+    })
+}
+
+pub async fn assign_bot_execute(input: &Value) -> Result<String> {
+    let mut config = Config::load().context("loading config")?;
+    let new_bot = new_bot(input, config.telegram.as_ref())?;
+    let (name, tasks) = (new_bot.name.clone(), new_bot.tasks.clone());
+
+    // --- 1. Create task in memory if it doesn't exist ---
+    let mm = MemoryManager::new(MemoryManager::default_root());
+    let mut tasks_created = Vec::new();
+    for task in &tasks {
+        if task == "*" {
+            continue;
+        }
+        // Check if task already exists
+        if mm.load_task(task).is_err() {
+            // Create the task
+            match mm.create_task(task, "general") {
+                Ok(msg) => tasks_created.push(msg),
+                Err(e) => tracing::warn!("Failed to create task '{}': {}", task, e),
+            }
+        }
+    }
 
+    // --- 2. Update config ---
+    let tg = config.telegram.get_or_insert_with(|| TelegramConfig {
+        bot_token: None,
+        allowed_users: vec![],
+        bots: vec![],
+        voice_reply: "off".to_string(),
+        allowed_groups: Vec::new(),
+        group_trigger: "mention".to_string(),
+    });
     tg.bots.push(new_bot);
     config.save().context("saving config")?;
 
@@ -117,6 +130,30 @@ pub async fn assign_bot_execute(input: &Value) -> Result<String> {
     Ok(result)
 }
 
+/// The config entry assign_bot would add (token masked) and the tasks it would create
+pub async fn assign_bot_preview(input: &Value) -> Result<String> {
+    let config = Config::load().context("loading config")?;
+    let mut bot = new_bot(input, config.telegram.as_ref())?;
+    let id = bot.bot_token.split(':').next().unwrap_or_default();
+    bot.bot_token = format!("{id}:…");
+
+    let mut telegram = toml::Table::new();
+    telegram.insert("bots".into(), toml::Value::Array(vec![toml::Value::try_from(&bot)?]));
+    let mut entry = toml::Table::new();
+    entry.insert("telegram".into(), toml::Value::Table(telegram));
+    let mut result = format!(
+        "Would add this bot to the config and restart:\n\n{}",
+        toml::to_string_pretty(&entry)?
+    );
+
+    let mm = MemoryManager::new(MemoryManager::default_root());
+    let missing: Vec<&str> = bot.tasks.iter().filter(|t| *t != "*" && mm.load_task(t).is_err()).map(String::as_str).collect();
+    if !missing.is_empty() {
+        result.push_str(&format!("\nWould create task(s): {}", missing.join(", ")));
+    }
+    Ok(result)
+}
+
 pub async fn list_bots_execute(_input: &Value) -> Result<String> {
     let config = Config::load().context("loading config")?;
 
@@ -165,3 +202,20 @@ pub async fn remove_bot_execute(input: &Value) -> Result<String> {
     RESTART_REQUESTED.store(true, Ordering::SeqCst);
     Ok(format!("✅ Bot '{}' removed. 🔄 Restarting to apply...", name))
 }
+
+/// The bot remove_bot would take out of the config
+pub async fn remove_bot_preview(input: &Value) -> Result<String> {
+    let name = input["name"].as_str().unwrap_or("");
+    if name.is_empty() {
+        anyhow::bail!("name is required");
+    }
+
+    let config = Config::load().context("loading config")?;
+    let bot = config.telegram.as_ref()
+        .and_then(|tg| tg.bots.iter().find(|b| b.name == name))
+        .ok_or_else(|| anyhow::anyhow!("Bot '{}' not found", name))?;
+    Ok(format!(
+        "Would remove bot '{}' (tasks: {:?}, model: {}) from the config and restart",
+        bot.name, bot.tasks, bot.default_model
+    ))
+}
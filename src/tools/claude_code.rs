@@ -85,7 +85,8 @@ pub async fn execute_with_api_key(input: &serde_json::Value, api_key: Option<&st
         .arg("--max-budget-usd").arg(format!("{:.2}", max_budget))
         .arg("--dangerously-skip-permissions") // running in trusted context
         .arg("--no-session-persistence")       // don't clutter session list
-        .current_dir(working_dir);
+        .current_dir(working_dir)
+        .kill_on_drop(true);                   // timeouts and /stop kill the process
 
     // Pass API key if provided (Claude Code needs auth)
     if let Some(key) = api_key {
@@ -1,10 +1,10 @@
 use crate::config::Config;
+use crate::sandbox::{self, Sandbox};
 use crate::types::ToolDefinition;
 use anyhow::{Context, Result};
 use serde_json;
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
-use tokio::process::Command;
 
 /// A custom user-defined tool loaded from config
 #[derive(Debug, Clone)]
@@ -25,17 +25,19 @@ impl CustomTool {
         }
     }
 
-    pub async fn execute(&self, input: &serde_json::Value) -> Result<String> {
+    /// Run the tool with `input` on stdin, inside `sandbox` if given
+    pub async fn execute(&self, input: &serde_json::Value, sandbox: Option<&Sandbox>) -> Result<String> {
         let input_json = serde_json::to_string(input)?;
 
         let program = self
             .command
             .first()
             .context("custom tool command is empty")?;
-        let args = &self.command[1..];
+        let args: Vec<&str> = self.command[1..].iter().map(String::as_str).collect();
+        let timeout = Duration::from_secs(self.timeout_secs);
+        let timeout = sandbox.map_or(timeout, |s| s.timeout(timeout));
 
-        let mut child = Command::new(program)
-            .args(args)
+        let mut child = sandbox::command(sandbox, program, &args, None)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
@@ -49,13 +51,10 @@ impl CustomTool {
         }
 
         // Apply timeout
-        let output = tokio::time::timeout(
-            Duration::from_secs(self.timeout_secs),
-            child.wait_with_output(),
-        )
-        .await
-        .with_context(|| format!("custom tool '{}' timed out after {}s", self.name, self.timeout_secs))?
-        .with_context(|| format!("running custom tool '{}'", self.name))?;
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .with_context(|| format!("custom tool '{}' timed out after {}s", self.name, timeout.as_secs()))?
+            .with_context(|| format!("running custom tool '{}'", self.name))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
 
@@ -0,0 +1,80 @@
+use anyhow::Result;
+
+use crate::memory::TaskStorage;
+use crate::tools::{bot_management, edit, git, github, patch, storage, write};
+
+/// Tools that change files, git, GitHub, storage or the bot config — previewed in a dry run
+pub const MUTATING_TOOLS: &[&str] = &[
+    "write_file",
+    "edit_file",
+    "apply_patch",
+    "git_commit",
+    "git_push",
+    "github_pr_create",
+    "github_issue_create",
+    "storage_write",
+    "storage_delete",
+    "assign_bot",
+    "remove_bot",
+];
+
+/// Also held back: tools that send something to someone, which can't be
+/// taken back. Shell and process tools still run, so the agent can look
+/// around with `ls` or read a process's output.
+const SENDING_TOOLS: &[&str] = &["send_file", "tts", "message_agent"];
+
+/// Whether dry-run mode previews `name` instead of running it
+pub fn intercepts(name: &str) -> bool {
+    MUTATING_TOOLS.contains(&name) || SENDING_TOOLS.contains(&name)
+}
+
+/// What `name` would do with `input`, worked out without doing it
+pub async fn preview(name: &str, input: &serde_json::Value, task_storage: Option<&TaskStorage>) -> Result<String> {
+    match name {
+        "write_file" => write::preview(input),
+        "edit_file" => edit::preview(input),
+        "apply_patch" => patch::preview(input),
+        "git_commit" => git::git_commit_preview(input).await,
+        "git_push" => git::git_push_preview(input).await,
+        "github_pr_create" | "github_issue_create" => github::github_create_preview(name, input),
+        "storage_write" | "storage_delete" => {
+            let ts = task_storage
+                .ok_or_else(|| anyhow::anyhow!("storage not available for this agent"))?;
+            match name {
+                "storage_write" => storage::storage_write_preview(input, ts),
+                "storage_delete" => storage::storage_delete_preview(input, ts),
+                _ => unreachable!(),
+            }
+        }
+        "assign_bot" => bot_management::assign_bot_preview(input).await,
+        "remove_bot" => bot_management::remove_bot_preview(input).await,
+        "send_file" => Ok(format!("Would send {} to the chat", input["path"].as_str().unwrap_or_default())),
+        "tts" => Ok(format!("Would speak: {}", input["text"].as_str().unwrap_or_default())),
+        "message_agent" => Ok(format!(
+            "Would message {}: {}",
+            input["to"].as_str().unwrap_or_default(),
+            input["message"].as_str().unwrap_or_default()
+        )),
+        _ => Ok(format!("Would call {name} with {input}")),
+    }
+}
+
+/// One line for the end-of-turn list: the preview's headline, or why it would fail
+pub fn action(name: &str, preview: &Result<String>) -> String {
+    match preview {
+        Ok(text) => format!("{name}: {}", text.lines().next().unwrap_or_default()),
+        Err(e) => format!("{name}: would fail — {}", e.to_string().lines().next().unwrap_or_default()),
+    }
+}
+
+/// Every action a dry-run turn held back, numbered
+pub fn summary(actions: &[String]) -> String {
+    if actions.is_empty() {
+        return "Dry run — no changes would have been made.".into();
+    }
+    let mut out = format!("Dry run — {} action(s) not taken:", actions.len());
+    for (i, action) in actions.iter().enumerate() {
+        out.push_str(&format!("\n{}. {action}", i + 1));
+    }
+    out
+}
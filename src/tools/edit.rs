@@ -2,12 +2,16 @@ use anyhow::Result;
 use serde_json::json;
 use std::fs;
 
+use crate::diff::{clip, unified_diff};
 use crate::types::ToolDefinition;
 
+/// Lines of the diff returned to the model before it's cut short
+const MAX_DIFF_LINES: usize = 200;
+
 pub fn definition() -> ToolDefinition {
     ToolDefinition {
         name: "edit_file".into(),
-        description: "Edit a file by replacing exact text. The old_text must match exactly.".into(),
+        description: "Edit a file by replacing text. Give one old_text/new_text pair, or several in `edits` — they apply in order and all-or-nothing. old_text should match exactly and be unique unless replace_all is set; if it isn't found exactly, a match ignoring indentation, spacing and line endings is used. Returns a diff of the change.".into(),
         input_schema: json!({
             "type": "object",
             "properties": {
@@ -17,28 +21,82 @@ pub fn definition() -> ToolDefinition {
                 },
                 "old_text": {
                     "type": "string",
-                    "description": "Exact text to find and replace"
+                    "description": "Text to find and replace"
                 },
                 "new_text": {
                     "type": "string",
                     "description": "New text to replace with"
+                },
+                "replace_all": {
+                    "type": "boolean",
+                    "description": "Replace every occurrence instead of requiring a unique match (default: false)"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Several replacements, applied in order to the result of the previous one",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "old_text": { "type": "string" },
+                            "new_text": { "type": "string" },
+                            "replace_all": { "type": "boolean" }
+                        },
+                        "required": ["old_text", "new_text"]
+                    }
                 }
             },
-            "required": ["path", "old_text", "new_text"]
+            "required": ["path"]
         }),
     }
 }
 
-pub async fn execute(input: &serde_json::Value) -> Result<String> {
+struct Edit<'a> {
+    old_text: &'a str,
+    new_text: &'a str,
+    replace_all: bool,
+}
+
+impl<'a> Edit<'a> {
+    fn parse(v: &'a serde_json::Value) -> Result<Self> {
+        Ok(Self {
+            old_text: v["old_text"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("missing 'old_text' field"))?,
+            new_text: v["new_text"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("missing 'new_text' field"))?,
+            replace_all: v["replace_all"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+/// A file with every edit applied in memory, not yet written
+struct Edited {
+    path: std::path::PathBuf,
+    original: String,
+    content: String,
+    /// Notes on edits that only matched ignoring whitespace
+    loose: Vec<String>,
+}
+
+impl Edited {
+    fn diff(&self) -> String {
+        clip(&unified_diff(&self.path.display().to_string(), &self.original, &self.content, 3), MAX_DIFF_LINES)
+    }
+
+    fn notes(&self) -> String {
+        if self.loose.is_empty() { String::new() } else { format!(" ({})", self.loose.join("; ")) }
+    }
+}
+
+fn edited(input: &serde_json::Value) -> Result<Edited> {
     let path = input["path"]
         .as_str()
         .ok_or_else(|| anyhow::anyhow!("missing 'path' field"))?;
-    let old_text = input["old_text"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("missing 'old_text' field"))?;
-    let new_text = input["new_text"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("missing 'new_text' field"))?;
+    let edits = match input["edits"].as_array() {
+        Some(list) if !list.is_empty() => list.iter().map(Edit::parse).collect::<Result<Vec<_>>>()?,
+        _ => vec![Edit::parse(input)?],
+    };
 
     let expanded = if path.starts_with("~/") {
         let home = dirs::home_dir().unwrap_or_default();
@@ -47,22 +105,226 @@ pub async fn execute(input: &serde_json::Value) -> Result<String> {
         std::path::PathBuf::from(path)
     };
 
-    let content = fs::read_to_string(&expanded)
+    let original = fs::read_to_string(&expanded)
         .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", expanded.display()))?;
 
-    let count = content.matches(old_text).count();
-    if count == 0 {
-        anyhow::bail!("old_text not found in {}", expanded.display());
+    // Apply everything in memory first so a bad edit leaves the file untouched
+    let mut content = original.clone();
+    let mut loose = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        let label = if edits.len() > 1 { format!("edit {}: ", i + 1) } else { String::new() };
+        match apply(&content, edit) {
+            Ok((updated, exact)) => {
+                content = updated;
+                if !exact {
+                    loose.push(format!("{label}matched ignoring whitespace"));
+                }
+            }
+            Err(e) => anyhow::bail!(
+                "{label}{e}{}",
+                if edits.len() > 1 { "\nNo edits were made." } else { "" }
+            ),
+        }
+    }
+    Ok(Edited { path: expanded, original, content, loose })
+}
+
+pub async fn execute(input: &serde_json::Value) -> Result<String> {
+    let edited = edited(input)?;
+    let display = edited.path.display();
+    if edited.content == edited.original {
+        return Ok(format!("No changes to {display} — new_text is the same as the text it replaces"));
+    }
+    fs::write(&edited.path, &edited.content)?;
+    Ok(format!("Edited {display}{}\n\n{}", edited.notes(), edited.diff()))
+}
+
+/// What `execute` would change, without writing
+pub fn preview(input: &serde_json::Value) -> Result<String> {
+    let edited = edited(input)?;
+    let display = edited.path.display();
+    if edited.content == edited.original {
+        return Ok(format!("Would leave {display} unchanged — new_text is the same as the text it replaces"));
+    }
+    Ok(format!("Would edit {display}{}\n\n{}", edited.notes(), edited.diff()))
+}
+
+/// Apply one edit. Returns the new content and whether the match was exact.
+fn apply(content: &str, edit: &Edit) -> Result<(String, bool)> {
+    if edit.old_text.is_empty() {
+        anyhow::bail!("old_text is empty");
+    }
+    let count = content.matches(edit.old_text).count();
+    match count {
+        1 => return Ok((content.replacen(edit.old_text, edit.new_text, 1), true)),
+        n if n > 1 && edit.replace_all => return Ok((content.replace(edit.old_text, edit.new_text), true)),
+        n if n > 1 => anyhow::bail!(
+            "old_text found {n} times — include more surrounding lines to make it unique, or set replace_all"
+        ),
+        _ => {}
+    }
+
+    let spans = loose_matches(content, edit.old_text);
+    if spans.is_empty() {
+        anyhow::bail!("{}", not_found(content, edit.old_text));
     }
-    if count > 1 {
+    if spans.len() > 1 && !edit.replace_all {
+        let at: Vec<String> = spans.iter().map(|s| s.line.to_string()).collect();
         anyhow::bail!(
-            "old_text found {count} times in {} — must be unique",
-            expanded.display()
+            "old_text matches {} places when whitespace is ignored (lines {}) — include more surrounding lines to make it unique, or set replace_all",
+            spans.len(),
+            at.join(", ")
         );
     }
 
-    let new_content = content.replacen(old_text, new_text, 1);
-    fs::write(&expanded, &new_content)?;
+    let crlf = content.contains("\r\n");
+    let mut out = content.to_string();
+    for span in spans.iter().rev() {
+        let replacement = adapt(edit.new_text, &leading_ws(edit.old_text), &span.indent, crlf);
+        out.replace_range(span.start..span.end, &replacement);
+    }
+    Ok((out, false))
+}
+
+/// Where old_text matched line-for-line once whitespace was normalized
+struct Span {
+    start: usize,
+    end: usize,
+    /// 1-based line number of the first line
+    line: usize,
+    /// Indentation of the file line matching old_text's first non-blank line
+    indent: String,
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn leading_ws(text: &str) -> String {
+    text.lines()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l[..l.len() - l.trim_start().len()].to_string())
+        .unwrap_or_default()
+}
+
+/// Whole-line matches of `old` comparing lines with runs of whitespace
+/// collapsed, so indentation, trailing spaces and CRLF don't matter
+fn loose_matches(content: &str, old: &str) -> Vec<Span> {
+    let wanted: Vec<String> = old.lines().map(normalize).collect();
+    if wanted.iter().all(|l| l.is_empty()) {
+        return Vec::new();
+    }
+    let first_text = wanted.iter().position(|l| !l.is_empty()).unwrap_or(0);
+
+    // Byte offset of each line's start, with one past the end
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut offsets = Vec::with_capacity(lines.len() + 1);
+    let mut at = 0;
+    for l in &lines {
+        offsets.push(at);
+        at += l.len();
+    }
+    offsets.push(at);
+    let normalized: Vec<String> = lines.iter().map(|l| normalize(l)).collect();
 
-    Ok(format!("Edited {}", expanded.display()))
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i + wanted.len() <= lines.len() {
+        if normalized[i..i + wanted.len()] != wanted[..] {
+            i += 1;
+            continue;
+        }
+        let last = i + wanted.len() - 1;
+        // Keep the last line's ending unless old_text had one too
+        let end = if old.ends_with('\n') {
+            offsets[last + 1]
+        } else {
+            offsets[last] + lines[last].trim_end_matches(['\n', '\r']).len()
+        };
+        spans.push(Span {
+            start: offsets[i],
+            end,
+            line: i + 1,
+            indent: leading_ws(lines[i + first_text]),
+        });
+        i += wanted.len();
+    }
+    spans
+}
+
+/// Re-indent and re-terminate new_text to fit where it's going, when
+/// old_text was written with different indentation or line endings
+fn adapt(new_text: &str, old_indent: &str, file_indent: &str, crlf: bool) -> String {
+    let mut text = if old_indent == file_indent {
+        new_text.to_string()
+    } else {
+        new_text
+            .split_inclusive('\n')
+            .map(|l| match l.strip_prefix(old_indent) {
+                Some(rest) if !l.trim().is_empty() => format!("{file_indent}{rest}"),
+                _ => l.to_string(),
+            })
+            .collect()
+    };
+    text = text.replace("\r\n", "\n");
+    if crlf {
+        text = text.replace('\n', "\r\n");
+    }
+    text
+}
+
+/// "Not found", with the stretch of the file that looks most like old_text
+fn not_found(content: &str, old: &str) -> String {
+    let wanted: Vec<String> = old.lines().map(normalize).collect();
+    let lines: Vec<&str> = content.lines().collect();
+    let n = wanted.len().max(1);
+    if lines.is_empty() {
+        return "old_text not found — the file is empty".into();
+    }
+    let normalized: Vec<String> = lines.iter().map(|l| normalize(l)).collect();
+
+    let windows = lines.len().saturating_sub(n) + 1;
+    let (best, score) = (0..windows)
+        .map(|i| {
+            let score: f64 = wanted
+                .iter()
+                .zip(&normalized[i..])
+                .map(|(a, b)| similarity(a, b))
+                .sum::<f64>()
+                / n as f64;
+            (i, score)
+        })
+        .fold((0, 0.0), |best, cur| if cur.1 > best.1 { cur } else { best });
+
+    if score < 0.3 {
+        return "old_text not found, and nothing in the file resembles it".into();
+    }
+    let end = (best + n).min(lines.len());
+    let excerpt: Vec<String> = (best..end).map(|i| format!("{:>5} | {}", i + 1, lines[i])).collect();
+    format!(
+        "old_text not found. Closest match ({:.0}% similar) at lines {}-{}:\n{}",
+        score * 100.0,
+        best + 1,
+        end,
+        excerpt.join("\n")
+    )
+}
+
+/// Dice coefficient over characters — cheap, and good enough to point at the right lines
+fn similarity(a: &str, b: &str) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut counts = std::collections::HashMap::new();
+    for c in a.chars() {
+        *counts.entry(c).or_insert(0i32) += 1;
+    }
+    let mut common = 0;
+    for c in b.chars() {
+        if let Some(n) = counts.get_mut(&c).filter(|n| **n > 0) {
+            *n -= 1;
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / (a.chars().count() + b.chars().count()) as f64
 }
@@ -101,6 +101,25 @@ pub async fn git_commit_execute(input: &serde_json::Value) -> Result<String> {
     run_git(&["commit", "-m", message], path).await
 }
 
+/// What git_commit would record: its message and everything `add -A` would stage
+pub async fn git_commit_preview(input: &serde_json::Value) -> Result<String> {
+    let message = input["message"].as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'message' field"))?;
+    let path = input["path"].as_str();
+    let status = run_git(&["status", "--short", "--untracked-files=all"], path).await?;
+    if status == "(no output)" {
+        return Ok("Nothing to commit — the working tree is clean".into());
+    }
+    let mut out = format!("Would stage every change and commit it with the message:\n{message}\n\nChanges:\n{status}");
+    // Tracked files only, and nothing before the first commit
+    if let Ok(stat) = run_git(&["diff", "HEAD", "--stat"], path).await
+        && stat != "(no output)"
+    {
+        out.push_str(&format!("\n{stat}"));
+    }
+    Ok(out)
+}
+
 // --- git_push ---
 
 pub fn git_push_definition() -> ToolDefinition {
@@ -128,6 +147,29 @@ pub async fn git_push_execute(input: &serde_json::Value) -> Result<String> {
     run_git(&args, path).await
 }
 
+/// What git_push would send, as of the last fetch
+pub async fn git_push_preview(input: &serde_json::Value) -> Result<String> {
+    let path = input["path"].as_str();
+    let remote = input["remote"].as_str().unwrap_or("origin");
+    let branch = match input["branch"].as_str() {
+        Some(branch) => branch.to_string(),
+        None => run_git(&["rev-parse", "--abbrev-ref", "HEAD"], path).await?.trim().to_string(),
+    };
+    let url = run_git(&["remote", "get-url", remote], path).await?;
+    // A `local:remote` refspec pushes one branch onto another
+    let (local, target) = branch.split_once(':').unwrap_or((&branch, &branch));
+    let range = format!("{remote}/{target}..{local}");
+    let commits = match run_git(&["log", "--oneline", &range], path).await {
+        Ok(commits) if commits == "(no output)" => "none — it's up to date".to_string(),
+        Ok(commits) => commits,
+        Err(_) => {
+            let recent = run_git(&["log", "--oneline", "-20", local], path).await?;
+            format!("{remote} has no {target} yet; the latest commits on {local}:\n{recent}")
+        }
+    };
+    Ok(format!("Would push {branch} to {remote} ({})\n\nCommits to push:\n{commits}", url.trim()))
+}
+
 // --- git_log ---
 
 pub fn git_log_definition() -> ToolDefinition {
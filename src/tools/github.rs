@@ -41,10 +41,17 @@ async fn github_api(
 }
 
 fn detect_repo() -> Option<(String, String)> {
-    let output = std::process::Command::new("git")
-        .args(["remote", "get-url", "origin"])
-        .output()
-        .ok()?;
+    detect_repo_at(None)
+}
+
+/// Owner and repo name of the GitHub `origin` remote of the repository at `dir` (default: cwd)
+pub fn detect_repo_at(dir: Option<&std::path::Path>) -> Option<(String, String)> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(["remote", "get-url", "origin"]);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd.output().ok()?;
     if !output.status.success() {
         return None;
     }
@@ -94,6 +101,16 @@ fn current_branch() -> Option<String> {
     }
 }
 
+/// The request a creation tool would send, without sending it
+pub fn github_create_preview(name: &str, input: &serde_json::Value) -> Result<String> {
+    let (path, body_json) = match name {
+        "github_pr_create" => pr_request(input)?,
+        "github_issue_create" => issue_request(input)?,
+        _ => anyhow::bail!("{name} doesn't create anything"),
+    };
+    Ok(format!("Would send POST https://api.github.com{path}\n{}", serde_json::to_string_pretty(&body_json)?))
+}
+
 // --- github_pr_create ---
 
 pub fn github_pr_create_definition() -> ToolDefinition {
@@ -114,8 +131,8 @@ pub fn github_pr_create_definition() -> ToolDefinition {
     }
 }
 
-pub async fn github_pr_create_execute(input: &serde_json::Value, token: Option<&str>) -> Result<String> {
-    let token = require_token(token)?;
+/// API path and payload for a new pull request
+fn pr_request(input: &serde_json::Value) -> Result<(String, serde_json::Value)> {
     let (owner, repo) = parse_repo(input)?;
     let title = input["title"].as_str().ok_or_else(|| anyhow::anyhow!("missing 'title'"))?;
     let head = input["head"].as_str().ok_or_else(|| anyhow::anyhow!("missing 'head'"))?;
@@ -129,8 +146,13 @@ pub async fn github_pr_create_execute(input: &serde_json::Value, token: Option<&
     if let Some(b) = input["body"].as_str() {
         body_json["body"] = json!(b);
     }
+    Ok((format!("/repos/{owner}/{repo}/pulls"), body_json))
+}
 
-    let result = github_api("POST", &format!("/repos/{owner}/{repo}/pulls"), token, Some(&body_json)).await?;
+pub async fn github_pr_create_execute(input: &serde_json::Value, token: Option<&str>) -> Result<String> {
+    let token = require_token(token)?;
+    let (path, body_json) = pr_request(input)?;
+    let result = github_api("POST", &path, token, Some(&body_json)).await?;
     let number = result["number"].as_u64().unwrap_or(0);
     let url = result["html_url"].as_str().unwrap_or("unknown");
     Ok(format!("Created PR #{number}: {url}"))
@@ -242,8 +264,8 @@ pub fn github_issue_create_definition() -> ToolDefinition {
     }
 }
 
-pub async fn github_issue_create_execute(input: &serde_json::Value, token: Option<&str>) -> Result<String> {
-    let token = require_token(token)?;
+/// API path and payload for a new issue
+fn issue_request(input: &serde_json::Value) -> Result<(String, serde_json::Value)> {
     let (owner, repo) = parse_repo(input)?;
     let title = input["title"].as_str().ok_or_else(|| anyhow::anyhow!("missing 'title'"))?;
 
@@ -254,8 +276,13 @@ pub async fn github_issue_create_execute(input: &serde_json::Value, token: Optio
     if let Some(labels) = input["labels"].as_array() {
         body_json["labels"] = json!(labels);
     }
+    Ok((format!("/repos/{owner}/{repo}/issues"), body_json))
+}
 
-    let result = github_api("POST", &format!("/repos/{owner}/{repo}/issues"), token, Some(&body_json)).await?;
+pub async fn github_issue_create_execute(input: &serde_json::Value, token: Option<&str>) -> Result<String> {
+    let token = require_token(token)?;
+    let (path, body_json) = issue_request(input)?;
+    let result = github_api("POST", &path, token, Some(&body_json)).await?;
     let number = result["number"].as_u64().unwrap_or(0);
     let url = result["html_url"].as_str().unwrap_or("unknown");
     Ok(format!("Created issue #{number}: {url}"))
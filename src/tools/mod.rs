@@ -1,14 +1,22 @@
+pub mod agents;
+pub mod ask_user;
 pub mod bot_management;
 pub mod claude_code;
 pub mod custom;
+pub mod dry_run;
 pub mod edit;
 pub mod git;
 pub mod github;
 pub mod improve;
 pub mod memory;
 pub mod patch;
+pub mod policy;
+pub mod process;
 pub mod read;
+pub mod repo_map;
 pub mod research;
+pub mod search;
+pub mod send_file;
 pub mod shell;
 pub mod storage;
 pub mod web_fetch;
@@ -19,7 +27,8 @@ pub mod write;
 use anyhow::Result;
 
 use crate::memory::{MemoryManager, TaskStorage};
-use crate::types::ToolDefinition;
+use crate::telegram::api::ChatTarget;
+use crate::types::{ToolDefinition, ToolResultContent};
 
 /// Execute a tool call by name
 pub async fn execute_tool(
@@ -29,18 +38,23 @@ pub async fn execute_tool(
     memory_manager: Option<&MemoryManager>,
     github_token: Option<&str>,
     task_storage: Option<&TaskStorage>,
-) -> Result<String> {
-    match name {
-        "shell" => shell::execute(input).await,
+    chat: Option<&ChatTarget>,
+) -> Result<ToolResultContent> {
+    let output = match name {
+        "shell" => shell::execute(input, None).await,
         "git_status" => git::git_status_execute(input).await,
         "git_diff" => git::git_diff_execute(input).await,
         "git_commit" => git::git_commit_execute(input).await,
         "git_push" => git::git_push_execute(input).await,
         "git_log" => git::git_log_execute(input).await,
         "git_branch" => git::git_branch_execute(input).await,
-        "read_file" => read::execute(input).await,
+        // read_file is the only tool that can return image blocks
+        "read_file" => return read::execute_content(input).await,
         "write_file" => write::execute(input).await,
         "edit_file" => edit::execute(input).await,
+        "grep" => search::grep_execute(input).await,
+        "glob" => search::glob_execute(input).await,
+        "repo_map" => repo_map::execute(input).await,
         "web_search" => web_search::execute(input, brave_api_key).await,
         "web_fetch" => web_fetch::execute(input).await,
         "apply_patch" => patch::execute(input).await,
@@ -83,22 +97,36 @@ pub async fn execute_tool(
                 _ => unreachable!(),
             }
         }
+        "send_file" => send_file::execute(input, task_storage, chat).await,
+        "ask_user" => ask_user::execute(input, ask_user::AskContext { chat, ..Default::default() }).await,
         "claude_code" => claude_code::execute(input).await,
+        "message_agent" | "request_escalation" => {
+            anyhow::bail!("{name} is only available to sub-agents")
+        }
+        "shell_reset" => anyhow::bail!("shell_reset needs a shell session"),
+        "process_start" | "process_output" | "process_wait" | "process_kill" | "process_list" => {
+            anyhow::bail!("{name} needs an agent's process table")
+        }
         "tts" | "self_improve" => {
             Ok("Tool not yet available in standalone mode".to_string())
         }
         _ => anyhow::bail!("Unknown tool: {name}"),
-    }
+    };
+    output.map(ToolResultContent::Text)
 }
 
 /// Get all built-in tool definitions
 pub fn builtin_tool_definitions(web_enabled: bool, github_enabled: bool) -> Vec<ToolDefinition> {
-    let mut tools = vec![
-        shell::definition(),
+    let mut tools = vec![shell::definition()];
+    tools.extend(process::definitions());
+    tools.extend([
         read::definition(),
         write::definition(),
         edit::definition(),
-    ];
+        search::grep_definition(),
+        search::glob_definition(),
+        repo_map::definition(),
+    ]);
     if web_enabled {
         tools.push(web_search::definition());
         tools.push(web_fetch::definition());
@@ -137,5 +165,7 @@ pub fn builtin_tool_definitions(web_enabled: bool, github_enabled: bool) -> Vec<
     tools.push(storage::storage_read_definition());
     tools.push(storage::storage_list_definition());
     tools.push(storage::storage_delete_definition());
+    tools.push(send_file::definition());
+    tools.push(ask_user::definition());
     tools
 }
@@ -1,15 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_json::json;
-use std::io::Write;
-use std::path::PathBuf;
-use tokio::process::Command;
+use std::path::{Path, PathBuf};
 
+use crate::diff::{clip, unified_diff};
 use crate::types::ToolDefinition;
 
+/// File lines shown either side of a hunk that didn't apply
+const FAILURE_CONTEXT: usize = 4;
+const DEFAULT_FUZZ: usize = 2;
+/// Lines of a preview's diff before it's cut short
+const MAX_DIFF_LINES: usize = 200;
+
 pub fn definition() -> ToolDefinition {
     ToolDefinition {
         name: "apply_patch".into(),
-        description: "Apply a unified diff patch to one or more files".into(),
+        description: "Apply a unified diff (git-style or plain) to one or more files. Handles new, deleted and renamed files and mode changes. All or nothing: if any hunk fails no file is changed, and each failing hunk comes back with the file's actual lines around where it should go.".into(),
         input_schema: json!({
             "type": "object",
             "properties": {
@@ -20,6 +25,18 @@ pub fn definition() -> ToolDefinition {
                 "workdir": {
                     "type": "string",
                     "description": "Working directory (optional, defaults to cwd)"
+                },
+                "strip": {
+                    "type": "integer",
+                    "description": "Leading path components to strip from file names, like patch -pN (default: 1, for a/ and b/ prefixes)"
+                },
+                "fuzz": {
+                    "type": "integer",
+                    "description": "Context lines a hunk may ignore at its edges if the file has changed around it (default: 2)"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Report which hunks would apply or fail without changing any files"
                 }
             },
             "required": ["patch"]
@@ -27,56 +44,556 @@ pub fn definition() -> ToolDefinition {
     }
 }
 
+/// The patch in `input` planned against its working directory, and the number of hunks in it
+fn planned(input: &serde_json::Value) -> Result<(PathBuf, Plan, usize)> {
+    let patch = input["patch"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'patch' field"))?;
+    let root = match input["workdir"].as_str() {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir()?,
+    };
+    let strip = input["strip"].as_u64().unwrap_or(1) as usize;
+    let fuzz = input["fuzz"].as_u64().map_or(DEFAULT_FUZZ, |f| f as usize);
+
+    let files = parse(patch, strip)?;
+    if files.is_empty() {
+        anyhow::bail!("no file changes found in the patch");
+    }
+    let plan = plan(&files, &root, fuzz);
+    let hunks = files.iter().map(|f| f.hunks.len()).sum();
+    Ok((root, plan, hunks))
+}
+
+/// Every failure in `plan`, then what would have applied
+fn failure_report(plan: &Plan, total: usize, dry_run: bool) -> String {
+    let mut report = if dry_run {
+        format!("Dry run: the patch would fail — {} problem(s) in {total} hunk(s):", plan.failures.len())
+    } else {
+        format!("Patch not applied — {} problem(s) in {total} hunk(s), no files were changed:", plan.failures.len())
+    };
+    for failure in &plan.failures {
+        report.push_str(&format!("\n\n{failure}"));
+    }
+    if !plan.summary.is_empty() {
+        report.push_str(&format!("\n\nThese would apply:\n{}", plan.summary.join("\n")));
+    }
+    report
+}
+
 pub async fn execute(input: &serde_json::Value) -> Result<String> {
-    let patch_content = input["patch"]
+    let dry_run = input["dry_run"].as_bool().unwrap_or(false);
+    let (_, plan, total) = planned(input)?;
+
+    if !plan.failures.is_empty() {
+        anyhow::bail!(failure_report(&plan, total, dry_run));
+    }
+
+    if dry_run {
+        return Ok(format!("Dry run: the patch applies cleanly.\n{}", plan.summary.join("\n")));
+    }
+    write(&plan.staged)?;
+    Ok(format!("Patch applied.\n{}", plan.summary.join("\n")))
+}
+
+/// What `execute` would change, with a diff per file, without writing
+pub fn preview(input: &serde_json::Value) -> Result<String> {
+    let (root, plan, total) = planned(input)?;
+    if !plan.failures.is_empty() {
+        anyhow::bail!(failure_report(&plan, total, true));
+    }
+    let mut out = format!("Would apply the patch:\n{}", plan.summary.join("\n"));
+    let mut diffs = String::new();
+    for (path, change) in &plan.staged {
+        let name = path.strip_prefix(&root).unwrap_or(path).display().to_string();
+        let original = std::fs::read_to_string(path).unwrap_or_default();
+        let content = match change {
+            Staged::Write { content, .. } => content.as_str(),
+            Staged::Delete => "",
+        };
+        diffs.push_str(&unified_diff(&name, &original, content, 3));
+    }
+    if !diffs.is_empty() {
+        out.push_str(&format!("\n\n{}", clip(&diffs, MAX_DIFF_LINES)));
+    }
+    Ok(out)
+}
+
+// ── Parsing ─────────────────────────────────────────────────────────
+
+/// Files a call would write or delete, so they can be checkpointed first
+pub fn touched_paths(input: &serde_json::Value) -> Result<Vec<PathBuf>> {
+    let patch = input["patch"]
         .as_str()
         .ok_or_else(|| anyhow::anyhow!("missing 'patch' field"))?;
+    if input["dry_run"].as_bool().unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+    let root = match input["workdir"].as_str() {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir()?,
+    };
+    let strip = input["strip"].as_u64().unwrap_or(1) as usize;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for file in parse(patch, strip)? {
+        for path in [file.old_path, file.new_path].into_iter().flatten() {
+            let path = root.join(path);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
 
-    // Write patch to temp file
-    let tmp_path = PathBuf::from(format!("/tmp/devman-patch-{}.patch", uuid::Uuid::new_v4()));
-    {
-        let mut f = std::fs::File::create(&tmp_path)?;
-        f.write_all(patch_content.as_bytes())?;
-        f.flush()?;
+/// One file's part of a patch. A `None` path is /dev/null.
+#[derive(Debug, Default)]
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    /// Git `copy from`/`copy to` — the old file stays
+    copy: bool,
+    new_mode: Option<u32>,
+    binary: bool,
+    hunks: Vec<Hunk>,
+    /// Came from a `diff --git` header, so the `---`/`+++` lines that follow belong to it
+    git: bool,
+}
+
+#[derive(Debug)]
+struct Hunk {
+    header: String,
+    old_start: usize,
+    /// (' ' | '-' | '+', text)
+    lines: Vec<(char, String)>,
+    /// The new side ends with a newline (no `\ No newline at end of file` after it)
+    new_eof_newline: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter(|(k, _)| *k != '+').map(|(_, t)| t.as_str()).collect()
+    }
+}
+
+/// Drop `strip` leading components, like `patch -pN`. A name with no more
+/// components than that is left alone.
+fn strip_path(path: &str, strip: usize) -> String {
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    if strip == 0 || parts.len() <= strip {
+        return path.to_string();
     }
+    parts[strip..].join("/")
+}
 
-    let mut cmd = Command::new("patch");
-    cmd.args(["-p1", "--forward"])
-        .stdin(std::process::Stdio::from(std::fs::File::open(&tmp_path)?));
+/// The path in a `---`/`+++` line: drop any timestamp, quotes and prefix
+fn header_path(s: &str, strip: usize) -> Option<String> {
+    let s = s.split('\t').next().unwrap_or(s).trim();
+    let s = s.trim_matches('"');
+    if s == "/dev/null" {
+        return None;
+    }
+    Some(strip_path(s, strip))
+}
 
-    if let Some(workdir) = input["workdir"].as_str() {
-        cmd.current_dir(workdir);
+/// The two paths of `diff --git a/x b/x`, which may contain spaces
+fn git_header_paths(rest: &str, strip: usize) -> (Option<String>, Option<String>) {
+    let rest = rest.trim();
+    let split = rest.find(" b/").or_else(|| rest.find(' '));
+    match split {
+        Some(i) => (header_path(&rest[..i], strip), header_path(&rest[i + 1..], strip)),
+        None => (header_path(rest, strip), header_path(rest, strip)),
     }
+}
 
-    let output = cmd.output().await?;
+fn parse_mode(s: &str) -> Option<u32> {
+    u32::from_str_radix(s.trim(), 8).ok()
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+fn parse(patch: &str, strip: usize) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = patch.lines().collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old_path, new_path) = git_header_paths(rest, strip);
+            files.push(FilePatch { old_path, new_path, git: true, ..Default::default() });
+        } else if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")) {
+            // Plain diffs start a file here; git ones already have
+            if !files.last().is_some_and(|f| f.git && f.hunks.is_empty()) {
+                files.push(FilePatch::default());
+            }
+            let file = files.last_mut().expect("just pushed");
+            file.old_path = header_path(&line[4..], strip);
+            file.new_path = header_path(&lines[i + 1][4..], strip);
+            i += 2;
+            continue;
+        } else if line.starts_with("@@ ") {
+            let file = files.last_mut().context("patch has a hunk before any file header")?;
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            file.hunks.push(hunk);
+            i = next;
+            continue;
+        } else if let Some(file) = files.last_mut().filter(|f| f.git && f.hunks.is_empty()) {
+            // Git extended headers. Rename/copy paths have no a/ b/ prefix.
+            if let Some(mode) = line.strip_prefix("new file mode ") {
+                file.old_path = None;
+                file.new_mode = parse_mode(mode);
+            } else if line.starts_with("deleted file mode ") {
+                file.new_path = None;
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                file.new_mode = parse_mode(mode);
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                file.old_path = Some(path.trim().to_string());
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = Some(path.trim().to_string());
+            } else if let Some(path) = line.strip_prefix("copy from ") {
+                file.old_path = Some(path.trim().to_string());
+                file.copy = true;
+            } else if let Some(path) = line.strip_prefix("copy to ") {
+                file.new_path = Some(path.trim().to_string());
+            } else if line.starts_with("GIT binary patch") || (line.starts_with("Binary files ") && line.ends_with(" differ")) {
+                file.binary = true;
+            }
+        }
+        i += 1;
+    }
+    for path in files.iter().flat_map(|f| [&f.old_path, &f.new_path]).flatten() {
+        check_path(path)?;
+    }
+    Ok(files)
+}
 
-    let mut result = String::new();
-    if !stdout.is_empty() {
-        result.push_str(&stdout);
+/// Refuse paths that would land outside the working directory, as GNU patch does
+fn check_path(path: &str) -> Result<()> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        anyhow::bail!("refusing to patch absolute path {}", path.display());
     }
-    if !stderr.is_empty() {
-        if !result.is_empty() {
-            result.push('\n');
+    if path.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        anyhow::bail!("refusing to patch {}: it contains `..`", path.display());
+    }
+    Ok(())
+}
+
+/// Parse `@@ -a,b +c,d @@` and its body. The line counts are trusted only as
+/// far as they go: hand-written hunks often get them wrong, so body lines
+/// past the count are still taken.
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize)> {
+    let header = lines[start];
+    let ranges = header
+        .strip_prefix("@@ ")
+        .and_then(|r| r.split(" @@").next())
+        .with_context(|| format!("malformed hunk header: {header}"))?;
+    let mut parts = ranges.split_whitespace();
+    let range = |p: Option<&str>, sign: char| -> Result<(usize, usize)> {
+        let p = p
+            .and_then(|p| p.strip_prefix(sign))
+            .with_context(|| format!("malformed hunk header: {header}"))?;
+        let (start, len) = p.split_once(',').unwrap_or((p, "1"));
+        Ok((start.parse()?, len.parse()?))
+    };
+    let (old_start, old_len) = range(parts.next(), '-')?;
+    let (_, new_len) = range(parts.next(), '+')?;
+
+    let mut hunk = Hunk { header: header.to_string(), old_start, lines: Vec::new(), new_eof_newline: true };
+    let (mut old_seen, mut new_seen) = (0, 0);
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        let counted = old_seen >= old_len && new_seen >= new_len;
+        // A removed `-- ` line then an added `++ ` line looks like the next
+        // file's header, so only look for one once the counts are used up
+        let next_file = counted && line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "));
+        if line.starts_with("@@ ") || line.starts_with("diff --git ") || next_file {
+            break;
+        }
+        match line.chars().next() {
+            Some('\\') => {
+                // "\ No newline at end of file" applies to the line before it
+                if matches!(hunk.lines.last(), Some((' ' | '+', _))) {
+                    hunk.new_eof_newline = false;
+                }
+            }
+            Some(kind @ (' ' | '-' | '+')) => {
+                let text = line[1..].to_string();
+                if kind != '+' {
+                    old_seen += 1;
+                }
+                if kind != '-' {
+                    new_seen += 1;
+                }
+                hunk.lines.push((kind, text));
+            }
+            // Editors strip the space off blank context lines
+            None if !counted => {
+                old_seen += 1;
+                new_seen += 1;
+                hunk.lines.push((' ', String::new()));
+            }
+            _ => break,
         }
-        result.push_str(&stderr);
+        i += 1;
     }
+    Ok((hunk, i))
+}
+
+// ── Applying ────────────────────────────────────────────────────────
 
-    if output.status.success() {
-        if result.is_empty() {
-            result = "Patch applied successfully (no output).".into();
+/// Where a hunk matched: file position, fuzz used, and context lines
+/// dropped from the front and back to get there
+struct Match {
+    pos: usize,
+    fuzz: usize,
+    front: usize,
+    back: usize,
+}
+
+fn find_hunk(lines: &[&str], from: usize, expected: usize, hunk: &Hunk, max_fuzz: usize) -> Option<Match> {
+    let old = hunk.old_lines();
+    let lead = hunk.lines.iter().take_while(|(k, _)| *k == ' ').count();
+    let trail = hunk.lines.iter().rev().take_while(|(k, _)| *k == ' ').count();
+    for fuzz in 0..=max_fuzz {
+        if fuzz > lead.max(trail) {
+            break;
+        }
+        let front = fuzz.min(lead);
+        let back = fuzz.min(trail).min(old.len() - front);
+        let want = &old[front..old.len() - back];
+        let target = expected + front;
+        if want.is_empty() {
+            return Some(Match { pos: target.clamp(from, lines.len()), fuzz, front, back });
         }
+        let Some(last) = lines.len().checked_sub(want.len()) else { continue };
+        if from > last {
+            continue;
+        }
+        // Nearest to where the hunk says it goes, either side
+        let target = target.clamp(from, last);
+        for d in 0..=(last - from) {
+            let candidates = [target.checked_add(d), target.checked_sub(d)];
+            for pos in candidates.into_iter().flatten().filter(|p| (from..=last).contains(p)) {
+                if lines[pos..pos + want.len()] == *want {
+                    return Some(Match { pos, fuzz, front, back });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// A hunk that didn't apply, with the file's real lines where it came closest
+fn hunk_failure(path: &str, n: usize, hunk: &Hunk, lines: &[&str], expected: usize) -> String {
+    let old = hunk.old_lines();
+    let agree = |pos: usize| old.iter().zip(&lines[pos.min(lines.len())..]).filter(|(a, b)| a == b).count();
+    let (best, score) = (0..lines.len())
+        .map(|p| (p, agree(p)))
+        .min_by_key(|&(p, score)| (std::cmp::Reverse(score), p.abs_diff(expected)))
+        .unwrap_or((0, 0));
+    let anchor = if score == 0 { expected.min(lines.len()) } else { best };
+
+    let mut text = format!("{path}: hunk {} `{}` failed — ", n + 1, hunk.header);
+    if lines.is_empty() {
+        text.push_str("the file is empty");
+    } else if score == 0 {
+        text.push_str("none of its old lines are in the file");
     } else {
-        result.push_str(&format!(
-            "\nPatch failed with exit code: {}",
-            output.status.code().unwrap_or(-1)
-        ));
+        text.push_str(&format!("closest match at line {} ({score} of {} old lines agree)", best + 1, old.len()));
+        if let Some((want, found)) = old.iter().zip(&lines[best..]).find(|(a, b)| a != b) {
+            text.push_str(&format!("; expected `{want}`, found `{found}`"));
+        }
+    }
+    let start = anchor.saturating_sub(FAILURE_CONTEXT);
+    let end = (anchor + old.len() + FAILURE_CONTEXT).min(lines.len());
+    if start < end {
+        text.push_str(&format!("\nFile lines {}-{}:", start + 1, end));
+        for (i, line) in lines[start..end].iter().enumerate() {
+            text.push_str(&format!("\n{:>5} | {line}", start + i + 1));
+        }
     }
+    text
+}
 
-    // Clean up temp file
-    let _ = std::fs::remove_file(&tmp_path);
+/// Apply a file's hunks to its content. Returns the new content and notes on
+/// offsets and fuzz, or a failure report for every hunk that didn't fit.
+fn apply_hunks(path: &str, original: &str, hunks: &[Hunk], max_fuzz: usize) -> std::result::Result<(String, Vec<String>), Vec<String>> {
+    let eol = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = original.lines().collect();
+    let mut final_newline = original.is_empty() || original.ends_with('\n');
+    let mut out: Vec<&str> = Vec::new();
+    let mut notes = Vec::new();
+    let mut failures = Vec::new();
+    let mut cursor = 0;
+    let mut drift: isize = 0;
 
-    Ok(result)
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old_len = hunk.old_lines().len();
+        // "-5,0" inserts after line 5; "-5,3" replaces from line 5
+        let start = if old_len == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = start.saturating_add_signed(drift).min(lines.len());
+        let Some(m) = find_hunk(&lines, cursor, expected, hunk, max_fuzz) else {
+            failures.push(hunk_failure(path, n, hunk, &lines, expected));
+            continue;
+        };
+        let offset = m.pos as isize - (expected + m.front) as isize;
+        if offset != 0 || m.fuzz > 0 {
+            let mut note = format!("hunk {}", n + 1);
+            if offset != 0 {
+                note.push_str(&format!(" at offset {offset:+}"));
+            }
+            if m.fuzz > 0 {
+                note.push_str(&format!(" with fuzz {}", m.fuzz));
+            }
+            notes.push(note);
+        }
+        drift += offset;
+
+        out.extend_from_slice(&lines[cursor..m.pos]);
+        let body = &hunk.lines[m.front..hunk.lines.len() - m.back];
+        out.extend(body.iter().filter(|(k, _)| *k != '-').map(|(_, t)| t.as_str()));
+        cursor = m.pos + (old_len - m.front - m.back);
+        if cursor == lines.len() && m.back == 0 {
+            final_newline = hunk.new_eof_newline;
+        }
+    }
+    if !failures.is_empty() {
+        return Err(failures);
+    }
+    out.extend_from_slice(&lines[cursor..]);
+    let mut content = out.join(eol);
+    if final_newline && !out.is_empty() {
+        content.push_str(eol);
+    }
+    Ok((content, notes))
+}
+
+enum Staged {
+    Write { content: String, mode: Option<u32> },
+    Delete,
+}
+
+struct Plan {
+    staged: Vec<(PathBuf, Staged)>,
+    summary: Vec<String>,
+    failures: Vec<String>,
+}
+
+impl Plan {
+    /// A file's content as of the changes staged so far
+    fn current(&self, path: &Path) -> Option<String> {
+        match self.staged.iter().rev().find(|(p, _)| p == path) {
+            Some((_, Staged::Write { content, .. })) => Some(content.clone()),
+            Some((_, Staged::Delete)) => None,
+            None => std::fs::read_to_string(path).ok(),
+        }
+    }
+
+    fn stage(&mut self, path: PathBuf, change: Staged) {
+        self.staged.retain(|(p, _)| *p != path);
+        self.staged.push((path, change));
+    }
+}
+
+/// Work out every file's new content without touching the disk
+fn plan(files: &[FilePatch], root: &Path, fuzz: usize) -> Plan {
+    let mut plan = Plan { staged: Vec::new(), summary: Vec::new(), failures: Vec::new() };
+    for file in files {
+        let name = file.new_path.as_deref().or(file.old_path.as_deref()).unwrap_or("(unnamed)");
+        if file.binary {
+            plan.failures.push(format!("{name}: binary patches aren't supported"));
+            continue;
+        }
+        let src = file.old_path.as_ref().map(|p| root.join(p));
+        let dst = file.new_path.as_ref().map(|p| root.join(p));
+        let original = match &src {
+            Some(path) => match plan.current(path) {
+                Some(content) => content,
+                None => {
+                    plan.failures.push(format!("{name}: {} doesn't exist", path.display()));
+                    continue;
+                }
+            },
+            None => {
+                if dst.as_ref().and_then(|d| plan.current(d)).is_some_and(|c| !c.is_empty()) {
+                    plan.failures.push(format!("{name}: is a new file in the patch but already exists"));
+                    continue;
+                }
+                String::new()
+            }
+        };
+        let (content, notes) = match apply_hunks(name, &original, &file.hunks, fuzz) {
+            Ok(applied) => applied,
+            Err(failures) => {
+                plan.failures.extend(failures);
+                continue;
+            }
+        };
+        let notes = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
+
+        match (src, dst) {
+            (None, Some(dst)) => {
+                plan.summary.push(format!("A {name}{notes}"));
+                plan.stage(dst, Staged::Write { content, mode: file.new_mode });
+            }
+            (Some(src), None) => {
+                if !content.is_empty() {
+                    plan.failures.push(format!("{name}: is deleted in the patch but has lines the patch doesn't remove"));
+                    continue;
+                }
+                plan.summary.push(format!("D {name}"));
+                plan.stage(src, Staged::Delete);
+            }
+            (Some(src), Some(dst)) if src != dst => {
+                let (kind, old) = (if file.copy { "C" } else { "R" }, file.old_path.as_deref().unwrap_or_default());
+                plan.summary.push(format!("{kind} {old} → {name}{notes}"));
+                if !file.copy {
+                    plan.stage(src, Staged::Delete);
+                }
+                plan.stage(dst, Staged::Write { content, mode: file.new_mode });
+            }
+            (Some(path), Some(_)) => {
+                let hunks = file.hunks.len();
+                plan.summary.push(format!("M {name} — {hunks} hunk(s){notes}"));
+                plan.stage(path, Staged::Write { content, mode: file.new_mode });
+            }
+            (None, None) => plan.failures.push(format!("{name}: has no file name")),
+        }
+    }
+    plan
+}
+
+fn write_one(path: &Path, change: &Staged) -> Result<()> {
+    match change {
+        Staged::Write { content, mode } => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content).with_context(|| format!("writing {}", path.display()))?;
+            #[cfg(unix)]
+            if let Some(mode) = mode {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+        Staged::Delete => std::fs::remove_file(path).with_context(|| format!("deleting {}", path.display()))?,
+    }
+    Ok(())
+}
+
+/// Write every staged change, putting the originals back if any write fails
+fn write(staged: &[(PathBuf, Staged)]) -> Result<()> {
+    let backups: Vec<(&PathBuf, Option<Vec<u8>>)> = staged.iter().map(|(p, _)| (p, std::fs::read(p).ok())).collect();
+    for (path, change) in staged {
+        if let Err(e) = write_one(path, change) {
+            for (path, original) in &backups {
+                let _ = match original {
+                    Some(bytes) => std::fs::write(path, bytes),
+                    None => std::fs::remove_file(path),
+                };
+            }
+            return Err(e.context("patch rolled back"));
+        }
+    }
+    Ok(())
 }
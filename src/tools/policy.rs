@@ -0,0 +1,109 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::types::ToolDefinition;
+
+/// Tools that only look: what a `read_only` policy keeps. Anything not listed —
+/// custom tools, and ones that send or spend like `send_file` or `deep_research`,
+/// included — is assumed to change something.
+pub const READ_ONLY_TOOLS: &[&str] = &[
+    "read_file",
+    "grep",
+    "glob",
+    "repo_map",
+    "git_status",
+    "git_diff",
+    "git_log",
+    "github_pr_list",
+    "github_issues_list",
+    "github_actions_status",
+    "web_search",
+    "web_fetch",
+    "memory_search",
+    "memory_read",
+    "memory_load_task",
+    "storage_read",
+    "storage_list",
+    "ask_user",
+    "list_bots",
+    "list_agents",
+];
+
+/// Which tools an agent gets. Patterns are globs over tool names (`git_*`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolPolicy {
+    /// Only these (empty: every tool)
+    pub allow: Vec<String>,
+    /// Never these, even if allowed
+    pub deny: Vec<String>,
+    /// Drop every tool that changes files, state or the outside world
+    pub read_only: bool,
+}
+
+/// A policy's globs, compiled once. A pattern that isn't a valid glob matches
+/// only its exact name.
+struct Patterns {
+    globs: GlobSet,
+    exact: Vec<String>,
+}
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Self {
+        let mut globs = GlobSetBuilder::new();
+        let mut exact = Vec::new();
+        for pattern in patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    globs.add(glob);
+                }
+                Err(_) => exact.push(pattern.clone()),
+            }
+        }
+        Self { globs: globs.build().unwrap_or_else(|_| GlobSet::empty()), exact }
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        self.globs.is_match(name) || self.exact.iter().any(|p| p == name)
+    }
+}
+
+impl ToolPolicy {
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && !self.read_only
+    }
+
+    /// This policy narrowed to the tools named in `allowlist` (empty: no narrowing),
+    /// so the two can travel as one
+    pub fn within(&self, allowlist: &[String]) -> ToolPolicy {
+        if allowlist.is_empty() {
+            return self.clone();
+        }
+        let permits = self.matcher();
+        let allow: Vec<String> = allowlist.iter().filter(|t| permits(t)).cloned().collect();
+        // An empty allow list means every tool, so deny them all instead
+        let deny = if allow.is_empty() { vec!["*".to_string()] } else { self.deny.clone() };
+        ToolPolicy { allow, deny, read_only: self.read_only }
+    }
+
+    pub fn permits(&self, tool: &str) -> bool {
+        self.matcher()(tool)
+    }
+
+    /// Drop the definitions this policy doesn't permit
+    pub fn filter(&self, tools: &mut Vec<ToolDefinition>) {
+        if !self.is_unrestricted() {
+            let permits = self.matcher();
+            tools.retain(|t| permits(&t.name));
+        }
+    }
+
+    fn matcher(&self) -> impl Fn(&str) -> bool + '_ {
+        let (allow, deny) = (Patterns::new(&self.allow), Patterns::new(&self.deny));
+        move |tool| {
+            (self.allow.is_empty() || allow.is_match(tool))
+                && !deny.is_match(tool)
+                && (!self.read_only || READ_ONLY_TOOLS.contains(&tool))
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::process::{Process, ProcessTable};
+use crate::sandbox::Sandbox;
+use crate::types::ToolDefinition;
+
+// Background processes belong to the agent (or chat) that started them, so
+// these run in the agent loop rather than through execute_tool
+
+/// Default and largest read from process_output
+const DEFAULT_READ_BYTES: usize = 20_000;
+const MAX_READ_BYTES: usize = 50_000;
+
+/// Longest process_wait
+const MAX_WAIT_SECS: u64 = 600;
+
+pub fn process_start_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "process_start".into(),
+        description: "Start a long-running command in the background — a dev server, a long build, a file watcher, `tail -f`. Returns at once with a process id; read its output with process_output, wait for it with process_wait and stop it with process_kill. Processes are killed when this conversation ends. Use shell for anything that finishes quickly.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "Shell command to run"
+                },
+                "workdir": {
+                    "type": "string",
+                    "description": "Working directory (optional)"
+                }
+            },
+            "required": ["command"]
+        }),
+    }
+}
+
+pub fn process_output_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "process_output".into(),
+        description: "Read a background process's output (stdout and stderr together). By default returns what's new since the last read; pass offset to re-read from a point. The last 1MB is kept.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "integer",
+                    "description": "Process id from process_start"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Byte offset to read from (default: where the last read stopped)"
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Most to return (default: 20000, max: 50000)"
+                }
+            },
+            "required": ["id"]
+        }),
+    }
+}
+
+pub fn process_wait_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "process_wait".into(),
+        description: "Wait for a background process to exit, up to a timeout. Returns its exit code if it finished, and its new output either way.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "integer",
+                    "description": "Process id from process_start"
+                },
+                "timeout": {
+                    "type": "integer",
+                    "description": "Seconds to wait (default: 60, max: 600)"
+                }
+            },
+            "required": ["id"]
+        }),
+    }
+}
+
+pub fn process_kill_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "process_kill".into(),
+        description: "Stop a background process and everything it started (SIGTERM, then SIGKILL after 5 seconds).".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "integer",
+                    "description": "Process id from process_start"
+                }
+            },
+            "required": ["id"]
+        }),
+    }
+}
+
+pub fn process_list_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "process_list".into(),
+        description: "List this conversation's background processes with their status and output size.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {}
+        }),
+    }
+}
+
+pub fn definitions() -> Vec<ToolDefinition> {
+    vec![
+        process_start_definition(),
+        process_output_definition(),
+        process_wait_definition(),
+        process_kill_definition(),
+        process_list_definition(),
+    ]
+}
+
+fn status(p: &Process) -> String {
+    match p.exit_code() {
+        None => "running".into(),
+        Some(code) => format!("exited with code {code}"),
+    }
+}
+
+/// New output since the last read, with where it came from
+fn read(p: &Process, offset: Option<u64>, max: usize) -> String {
+    let out = p.read(offset, max);
+    let mut text = format!("[process {} {} | output bytes {}-{}", p.id, status(p), out.from, out.next);
+    let total = p.output_len();
+    if out.next < total {
+        text.push_str(&format!(" of {total} — read again for more"));
+    }
+    if out.dropped > 0 {
+        text.push_str(&format!(" | {} earlier bytes no longer kept", out.dropped));
+    }
+    text.push(']');
+    if !out.text.is_empty() {
+        text.push('\n');
+        text.push_str(&out.text);
+    }
+    text
+}
+
+fn id(input: &serde_json::Value) -> Result<u32> {
+    input["id"]
+        .as_u64()
+        .map(|id| id as u32)
+        .ok_or_else(|| anyhow::anyhow!("missing 'id' field"))
+}
+
+/// Run one of the process_* tools against `table`, starting processes in `sandbox` if given
+pub async fn execute(name: &str, input: &serde_json::Value, table: &ProcessTable, sandbox: Option<&Sandbox>) -> Result<String> {
+    match name {
+        "process_start" => {
+            let command = input["command"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("missing 'command' field"))?;
+            let p = table.start(command, input["workdir"].as_str(), sandbox)?;
+            // A moment for it to fail fast or print a banner
+            p.wait(Duration::from_millis(500)).await;
+            Ok(format!("Started process {} (pid {}): {command}\n{}", p.id, p.pid, read(&p, None, DEFAULT_READ_BYTES)))
+        }
+        "process_output" => {
+            let p = table.get(id(input)?)?;
+            let max = input["max_bytes"].as_u64().map_or(DEFAULT_READ_BYTES, |m| (m as usize).min(MAX_READ_BYTES));
+            Ok(read(&p, input["offset"].as_u64(), max))
+        }
+        "process_wait" => {
+            let p = table.get(id(input)?)?;
+            let timeout = input["timeout"].as_u64().unwrap_or(60).min(MAX_WAIT_SECS);
+            p.wait(Duration::from_secs(timeout)).await;
+            Ok(read(&p, None, DEFAULT_READ_BYTES))
+        }
+        "process_kill" => {
+            let p = table.get(id(input)?)?;
+            if let Some(code) = p.exit_code() {
+                return Ok(format!("Process {} had already exited with code {code}", p.id));
+            }
+            match p.kill().await {
+                Some(_) => Ok(format!("Killed process {}\n{}", p.id, read(&p, None, DEFAULT_READ_BYTES))),
+                None => anyhow::bail!("process {} (pid {}) didn't die", p.id, p.pid),
+            }
+        }
+        "process_list" => {
+            let procs = table.list();
+            if procs.is_empty() {
+                return Ok("No background processes.".into());
+            }
+            Ok(procs
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{} — {} — {} bytes of output — started {} — {}",
+                        p.id,
+                        status(p),
+                        p.output_len(),
+                        p.started.format("%H:%M:%S"),
+                        p.command
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        other => anyhow::bail!("unknown process tool: {other}"),
+    }
+}
@@ -2,12 +2,12 @@ use anyhow::Result;
 use serde_json::json;
 use std::fs;
 
-use crate::types::ToolDefinition;
+use crate::types::{ContentBlock, ToolDefinition, ToolResultContent};
 
 pub fn definition() -> ToolDefinition {
     ToolDefinition {
         name: "read_file".into(),
-        description: "Read the contents of a file. Supports offset/limit for large files.".into(),
+        description: "Read the contents of a file. Supports offset/limit for large files. Image files (PNG, JPEG, GIF, WebP) are returned as images you can see.".into(),
         input_schema: json!({
             "type": "object",
             "properties": {
@@ -29,18 +29,39 @@ pub fn definition() -> ToolDefinition {
     }
 }
 
-pub async fn execute(input: &serde_json::Value) -> Result<String> {
+/// Like `execute`, but image files come back as image blocks
+pub async fn execute_content(input: &serde_json::Value) -> Result<ToolResultContent> {
+    let expanded = expand_path(input)?;
+
+    if crate::media::sniff_file(&expanded).is_some_and(|m| m.is_image())
+        && let Some((media_type, block)) = crate::media::file_block(&expanded)?
+    {
+        let size = fs::metadata(&expanded).map(|m| m.len()).unwrap_or(0);
+        return Ok(ToolResultContent::Blocks(vec![
+            ContentBlock::Text {
+                text: format!("{} ({}, {size} bytes)", expanded.display(), media_type.as_str()),
+            },
+            block,
+        ]));
+    }
+
+    execute(input).await.map(ToolResultContent::Text)
+}
+
+fn expand_path(input: &serde_json::Value) -> Result<std::path::PathBuf> {
     let path = input["path"]
         .as_str()
         .ok_or_else(|| anyhow::anyhow!("missing 'path' field"))?;
 
     // Expand ~ to home dir
-    let expanded = if path.starts_with("~/") {
-        let home = dirs::home_dir().unwrap_or_default();
-        home.join(&path[2..])
-    } else {
-        std::path::PathBuf::from(path)
-    };
+    Ok(match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => std::path::PathBuf::from(path),
+    })
+}
+
+pub async fn execute(input: &serde_json::Value) -> Result<String> {
+    let expanded = expand_path(input)?;
 
     let content = fs::read_to_string(&expanded)
         .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", expanded.display()))?;
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::PathBuf;
+
+use crate::types::ToolDefinition;
+
+pub fn definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "repo_map".into(),
+        description: "Summarize a project: its manifest files (Cargo.toml, package.json, ...), directory tree, and the top-level functions, types and classes in each source file. Cheaper than exploring with ls and read_file. Call it on a subdirectory to see more of that part.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Project or subdirectory to map (default: current directory)"
+                },
+                "max_tokens": {
+                    "type": "integer",
+                    "description": "Rough size limit for the map (default: 4000)"
+                }
+            }
+        }),
+    }
+}
+
+pub async fn execute(input: &serde_json::Value) -> Result<String> {
+    let path = input["path"].as_str().unwrap_or(".");
+    let root = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    };
+    let max_tokens = input["max_tokens"].as_u64().unwrap_or(4000).max(200) as usize;
+    tokio::task::spawn_blocking(move || crate::repomap::repo_map(&root, max_tokens)).await?
+}
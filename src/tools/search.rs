@@ -0,0 +1,238 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use crate::search::{self, FileFilter, Sort};
+use crate::types::ToolDefinition;
+
+/// Same cap as shell and read_file
+const MAX_OUTPUT_BYTES: usize = 50_000;
+
+pub fn grep_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "grep".into(),
+        description: "Search file contents with a regex. Skips files ignored by .gitignore/.ignore, hidden files and binaries. Use this rather than grep or find in the shell.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Regular expression (Rust regex syntax) to search for"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "File or directory to search (default: current directory)"
+                },
+                "glob": {
+                    "type": "string",
+                    "description": "Only search files matching this glob, e.g. \"*.rs\" or \"src/**/*.ts\""
+                },
+                "type": {
+                    "type": "string",
+                    "description": "Only search files of these types, comma-separated: rust, py, js, ts, go, md, toml, ..."
+                },
+                "context": {
+                    "type": "integer",
+                    "description": "Lines of context around each match (default: 0)"
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case (default: false)"
+                },
+                "literal": {
+                    "type": "boolean",
+                    "description": "Treat the pattern as plain text, not a regex (default: false)"
+                },
+                "output": {
+                    "type": "string",
+                    "enum": ["content", "files", "count"],
+                    "description": "Matching lines, just the file names, or match counts per file (default: content)"
+                },
+                "sort": {
+                    "type": "string",
+                    "enum": ["relevance", "modified", "path"],
+                    "description": "Files with most matches first, most recently modified first, or by path (default: relevance)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Stop after this many matching lines, or files for output=files/count (default: 100)"
+                },
+                "hidden": {
+                    "type": "boolean",
+                    "description": "Also search dot-files and dot-directories (default: false)"
+                }
+            },
+            "required": ["pattern"]
+        }),
+    }
+}
+
+pub fn glob_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "glob".into(),
+        description: "Find files by path pattern, e.g. \"**/*.rs\" or \"src/**/mod.rs\". A pattern without / matches file names at any depth. Skips files ignored by .gitignore/.ignore and hidden files.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Glob matched against paths relative to the search directory"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search (default: current directory)"
+                },
+                "sort": {
+                    "type": "string",
+                    "enum": ["relevance", "modified", "path"],
+                    "description": "Shallowest paths first, most recently modified first, or by path (default: modified)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum files to list (default: 200)"
+                },
+                "hidden": {
+                    "type": "boolean",
+                    "description": "Include dot-files and dot-directories (default: false)"
+                }
+            },
+            "required": ["pattern"]
+        }),
+    }
+}
+
+fn root(input: &serde_json::Value) -> PathBuf {
+    let path = input["path"].as_str().unwrap_or(".");
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Paths relative to the search root read better and cost fewer tokens
+fn display(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel.display().to_string(),
+        _ => path.display().to_string(),
+    }
+}
+
+fn cap(mut out: String) -> String {
+    if out.len() > MAX_OUTPUT_BYTES {
+        let mut at = MAX_OUTPUT_BYTES;
+        while !out.is_char_boundary(at) {
+            at -= 1;
+        }
+        out.truncate(at);
+        out.push_str("\n... (output truncated — narrow the search)");
+    }
+    out
+}
+
+pub async fn grep_execute(input: &serde_json::Value) -> Result<String> {
+    let pattern = input["pattern"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'pattern' field"))?;
+    let pattern = if input["literal"].as_bool().unwrap_or(false) { regex::escape(pattern) } else { pattern.to_string() };
+    let regex = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(input["case_insensitive"].as_bool().unwrap_or(false))
+        .build()
+        .map_err(|e| anyhow::anyhow!("invalid regex: {e}"))?;
+    let filter = FileFilter {
+        glob: input["glob"].as_str().map(String::from),
+        types: input["type"]
+            .as_str()
+            .map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default(),
+        hidden: input["hidden"].as_bool().unwrap_or(false),
+    };
+    let context = input["context"].as_u64().unwrap_or(0) as usize;
+    let sort = Sort::parse(input["sort"].as_str().unwrap_or("relevance"))?;
+    let max_results = input["max_results"].as_u64().unwrap_or(100).max(1) as usize;
+    let output = input["output"].as_str().unwrap_or("content");
+    let root = root(input);
+
+    let results = tokio::task::spawn_blocking({
+        let root = root.clone();
+        move || search::grep(&root, &regex, &filter, context, sort)
+    })
+    .await??;
+    if results.is_empty() {
+        return Ok("No matches found.".into());
+    }
+    let total: usize = results.iter().map(|f| f.count).sum();
+
+    let mut out = String::new();
+    match output {
+        "files" | "count" => {
+            for file in results.iter().take(max_results) {
+                let path = display(&file.path, &root);
+                if output == "count" {
+                    out.push_str(&format!("{path}:{}\n", file.count));
+                } else {
+                    out.push_str(&format!("{path}\n"));
+                }
+            }
+            if results.len() > max_results {
+                out.push_str(&format!("... {} more files\n", results.len() - max_results));
+            }
+        }
+        "content" => {
+            let mut shown = 0;
+            for file in &results {
+                if shown >= max_results {
+                    break;
+                }
+                out.push_str(&format!("{}\n", display(&file.path, &root)));
+                let mut last = None;
+                for line in &file.lines {
+                    if line.is_match && shown >= max_results {
+                        break;
+                    }
+                    if context > 0 && last.is_some_and(|n| line.number > n + 1) {
+                        out.push_str("--\n");
+                    }
+                    let sep = if line.is_match { ':' } else { '-' };
+                    out.push_str(&format!("{}{sep}{}\n", line.number, line.text));
+                    shown += line.is_match as usize;
+                    last = Some(line.number);
+                }
+                out.push('\n');
+            }
+            if total > shown {
+                out.push_str(&format!(
+                    "... {} more matches not shown ({total} in {} files) — narrow the search or raise max_results\n",
+                    total - shown,
+                    results.len()
+                ));
+            }
+        }
+        other => anyhow::bail!("unknown output: {other} (expected content, files or count)"),
+    }
+    Ok(cap(out.trim_end().to_string()))
+}
+
+pub async fn glob_execute(input: &serde_json::Value) -> Result<String> {
+    let pattern = input["pattern"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing 'pattern' field"))?
+        .to_string();
+    let sort = Sort::parse(input["sort"].as_str().unwrap_or("modified"))?;
+    let max_results = input["max_results"].as_u64().unwrap_or(200).max(1) as usize;
+    let hidden = input["hidden"].as_bool().unwrap_or(false);
+    let root = root(input);
+
+    let entries = tokio::task::spawn_blocking({
+        let root = root.clone();
+        move || search::glob(&root, &pattern, hidden, sort)
+    })
+    .await??;
+    if entries.is_empty() {
+        return Ok("No files found.".into());
+    }
+    let mut out: Vec<String> = entries.iter().take(max_results).map(|e| display(&e.path, &root)).collect();
+    if entries.len() > max_results {
+        out.push(format!("... {} more files", entries.len() - max_results));
+    }
+    Ok(cap(out.join("\n")))
+}
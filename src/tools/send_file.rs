@@ -0,0 +1,100 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::PathBuf;
+
+use crate::memory::TaskStorage;
+use crate::telegram::api::{ChatTarget, MediaKind};
+use crate::types::ToolDefinition;
+
+pub fn definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "send_file".into(),
+        description: "Send a file from task storage to the current Telegram chat. Images are sent as photos, .ogg/.opus as voice notes, everything else as a document. Pass `paths` to send 2–10 photos or documents as one album. Limits: 10 MB per photo, 50 MB otherwise.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File path relative to storage root (e.g. 'reports/weekly.pdf')"
+                },
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Several storage paths to send as an album (instead of 'path')"
+                },
+                "caption": {
+                    "type": "string",
+                    "description": "Optional caption (Markdown, max 1024 chars)"
+                },
+                "send_as": {
+                    "type": "string",
+                    "enum": ["auto", "document", "photo", "voice"],
+                    "description": "Override how the file is sent. Default: auto (by extension)."
+                }
+            }
+        }),
+    }
+}
+
+pub async fn execute(
+    input: &serde_json::Value,
+    storage: Option<&TaskStorage>,
+    chat: Option<&ChatTarget>,
+) -> Result<String> {
+    let chat = chat.ok_or_else(|| anyhow::anyhow!("send_file is only available in Telegram chats"))?;
+    let storage = storage.ok_or_else(|| anyhow::anyhow!("storage not available for this agent"))?;
+
+    let mut paths: Vec<String> = input["paths"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    if let Some(p) = input["path"].as_str() {
+        paths.insert(0, p.to_string());
+    }
+    if paths.is_empty() {
+        anyhow::bail!("path or paths is required");
+    }
+
+    let forced = match input["send_as"].as_str().unwrap_or("auto") {
+        "auto" => None,
+        s => Some(MediaKind::parse(s).ok_or_else(|| anyhow::anyhow!("unknown send_as: {s}"))?),
+    };
+    let caption = input["caption"].as_str().filter(|c| !c.is_empty());
+
+    // Resolve through storage so the bot's scope is enforced
+    let mut items: Vec<(MediaKind, PathBuf)> = Vec::new();
+    for p in &paths {
+        let full = storage.file_path(p)?;
+        items.push((pick_kind(&full, forced), full));
+    }
+
+    if items.len() == 1 {
+        let (kind, path) = &items[0];
+        chat.bot.send_media(chat.dest(), *kind, path, caption).await?;
+        return Ok(format!("Sent {} as {}", paths[0], kind.as_str()));
+    }
+
+    // Albums can't mix photos and documents — fall back to documents
+    if items.iter().any(|(k, _)| *k != MediaKind::Photo) {
+        for item in &mut items {
+            item.0 = MediaKind::Document;
+        }
+    }
+    let kind = items[0].0;
+    chat.bot.send_media_group(chat.dest(), &items, caption).await?;
+    Ok(format!("Sent {} files as a {} album", items.len(), kind.as_str()))
+}
+
+/// Choose the upload kind, downgrading oversized photos to documents
+fn pick_kind(path: &std::path::Path, forced: Option<MediaKind>) -> MediaKind {
+    if let Some(k) = forced {
+        return k;
+    }
+    let kind = MediaKind::for_path(path);
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if kind == MediaKind::Photo && size > kind.max_bytes() {
+        MediaKind::Document
+    } else {
+        kind
+    }
+}
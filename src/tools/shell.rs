@@ -1,8 +1,9 @@
 use anyhow::Result;
 use serde_json::json;
 use std::time::Duration;
-use tokio::process::Command;
 
+use crate::sandbox::{self, Sandbox};
+use crate::shell_session::ShellSession;
 use crate::types::ToolDefinition;
 
 pub fn definition() -> ToolDefinition {
@@ -30,49 +31,112 @@ pub fn definition() -> ToolDefinition {
     }
 }
 
-pub async fn execute(input: &serde_json::Value) -> Result<String> {
-    let command = input["command"]
+/// `shell` as it's offered when commands run in a persistent session
+pub fn session_definition() -> ToolDefinition {
+    let mut def = definition();
+    def.description = "Execute a shell command in this conversation's bash session. Working directory, exported variables, \
+        shell variables and functions carry over between calls, so `cd`, `export` and `source venv/bin/activate` stick. \
+        Returns stdout and stderr. A command that runs past its timeout is interrupted without ending the session; \
+        use shell_reset to start over."
+        .into();
+    def.input_schema["properties"]["workdir"]["description"] = "Move the session to this directory first (optional)".into();
+    def
+}
+
+pub fn reset_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "shell_reset".into(),
+        description: "Kill the shell session and everything it started; the next shell command starts a fresh one with a clean environment.".into(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {}
+        }),
+    }
+}
+
+fn command(input: &serde_json::Value) -> Result<&str> {
+    input["command"]
         .as_str()
-        .ok_or_else(|| anyhow::anyhow!("missing 'command' field"))?;
+        .ok_or_else(|| anyhow::anyhow!("missing 'command' field"))
+}
 
-    // Self-kill protection: refuse commands that would kill devman
+/// Self-kill protection: refuse commands that would kill devman
+fn refusal(command: &str) -> Option<String> {
     let lower = command.to_lowercase();
     let dangerous = ["kill", "pkill", "killall", "systemctl stop devman", "systemctl restart devman"];
-    if dangerous.iter().any(|d| lower.contains(d)) && lower.contains("devman") {
-        return Ok("⚠️ Refused: cannot kill/restart own process. Use the RESTART_REQUESTED flag or systemctl from outside.".into());
-    }
-
-    let timeout_secs = input["timeout"].as_u64().unwrap_or(120);
+    (dangerous.iter().any(|d| lower.contains(d)) && lower.contains("devman"))
+        .then(|| "⚠️ Refused: cannot kill/restart own process. Use the RESTART_REQUESTED flag or systemctl from outside.".into())
+}
 
-    let mut cmd = Command::new("bash");
-    cmd.arg("-c").arg(command);
+/// Timeout asked for, within the sandbox's limit
+fn timeout(input: &serde_json::Value, sandbox: Option<&Sandbox>) -> Duration {
+    let requested = Duration::from_secs(input["timeout"].as_u64().unwrap_or(120));
+    sandbox.map_or(requested, |s| s.timeout(requested))
+}
 
-    if let Some(workdir) = input["workdir"].as_str() {
-        cmd.current_dir(workdir);
+/// Run a `shell` call in a fresh bash, inside `sandbox` if given
+pub async fn execute(input: &serde_json::Value, sandbox: Option<&Sandbox>) -> Result<String> {
+    let command = command(input)?;
+    if let Some(refusal) = refusal(command) {
+        return Ok(refusal);
     }
 
-    let output = tokio::time::timeout(Duration::from_secs(timeout_secs), cmd.output())
+    let timeout = timeout(input, sandbox);
+    let timeout_secs = timeout.as_secs();
+
+    let mut cmd = sandbox::command(sandbox, "bash", &["-c", command], input["workdir"].as_str());
+    cmd.kill_on_drop(true);
+
+    let output = tokio::time::timeout(timeout, cmd.output())
         .await
         .map_err(|_| anyhow::anyhow!("command timed out after {timeout_secs}s"))?
         .map_err(|e| anyhow::anyhow!("failed to execute command: {e}"))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(format_output(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+        output.status.code().unwrap_or(-1),
+    ))
+}
+
+/// Run a `shell` call in `session` instead of a fresh bash
+pub async fn execute_in_session(input: &serde_json::Value, session: &ShellSession, sandbox: Option<&Sandbox>) -> Result<String> {
+    let command = command(input)?;
+    if let Some(refusal) = refusal(command) {
+        return Ok(refusal);
+    }
+
+    let timeout = timeout(input, sandbox);
+    let timeout_secs = timeout.as_secs();
+    let out = session.run(command, input["workdir"].as_str(), timeout, sandbox).await?;
+    // No exit code means the shell died before the command finished: a failure
+    let mut result = format_output(&out.stdout, &out.stderr, out.exit_code.unwrap_or(-1));
+    if out.timed_out {
+        result.push_str(&format!("\nCommand timed out after {timeout_secs}s and was interrupted."));
+    }
+    if out.ended {
+        result.push_str("\nThe shell session ended before the command finished; the next command starts a new one with a clean environment.");
+    } else if out.timed_out {
+        result.push_str(" The session is still running.");
+    }
+    Ok(result)
+}
 
+fn format_output(stdout: &str, stderr: &str, exit_code: i32) -> String {
     let mut result = String::new();
     if !stdout.is_empty() {
-        result.push_str(&stdout);
+        result.push_str(stdout);
     }
     if !stderr.is_empty() {
         if !result.is_empty() {
             result.push('\n');
         }
         result.push_str("STDERR:\n");
-        result.push_str(&stderr);
+        result.push_str(stderr);
     }
 
-    if !output.status.success() {
-        result.push_str(&format!("\nExit code: {}", output.status.code().unwrap_or(-1)));
+    if exit_code != 0 {
+        result.push_str(&format!("\nExit code: {exit_code}"));
     }
 
     if result.is_empty() {
@@ -81,9 +145,9 @@ pub async fn execute(input: &serde_json::Value) -> Result<String> {
 
     // Truncate very long output
     if result.len() > 50_000 {
-        result.truncate(50_000);
+        result.truncate(result.floor_char_boundary(50_000));
         result.push_str("\n... (output truncated at 50KB)");
     }
 
-    Ok(result)
+    result
 }
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use serde_json::Value;
 
+use crate::diff::{clip, unified_diff};
 use crate::memory::TaskStorage;
 use crate::types::ToolDefinition;
 
+/// Lines of a preview's diff before it's cut short
+const MAX_DIFF_LINES: usize = 200;
+
 pub fn storage_write_definition() -> ToolDefinition {
     ToolDefinition {
         name: "storage_write".to_string(),
@@ -44,6 +48,34 @@ pub async fn storage_write_execute(input: &Value, storage: &TaskStorage) -> Resu
     Ok(format!("Written: {path} (storage total: {} files, {bytes} bytes)", storage.list_files(None)?.len()))
 }
 
+/// What storage_write would store, as a diff against the stored file if it's text
+pub fn storage_write_preview(input: &Value, storage: &TaskStorage) -> Result<String> {
+    let path = input["path"].as_str().unwrap_or("");
+    let content = input["content"].as_str().unwrap_or("");
+    let base64 = input["base64"].as_bool().unwrap_or(false);
+
+    if path.is_empty() {
+        anyhow::bail!("path is required");
+    }
+    let verb = if storage.file_path(path).is_ok() { "overwrite" } else { "create" };
+    if base64 {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(content)
+            .map_err(|_| anyhow::anyhow!("invalid base64 content"))?;
+        return Ok(format!("Would {verb} {path} in storage ({} bytes, binary)", bytes.len()));
+    }
+    let original = match verb {
+        "overwrite" => storage.read_file(path)?,
+        _ => String::new(),
+    };
+    if original.starts_with("[base64] ") {
+        return Ok(format!("Would overwrite binary {path} in storage with {} bytes of text", content.len()));
+    }
+    let diff = unified_diff(path, &original, content, 3);
+    Ok(format!("Would {verb} {path} in storage ({} bytes)\n\n{}", content.len(), clip(&diff, MAX_DIFF_LINES)))
+}
+
 pub fn storage_read_definition() -> ToolDefinition {
     ToolDefinition {
         name: "storage_read".to_string(),
@@ -124,3 +156,20 @@ pub async fn storage_delete_execute(input: &Value, storage: &TaskStorage) -> Res
     storage.delete_file(path)?;
     Ok(format!("Deleted: {path}"))
 }
+
+/// What storage_delete would remove
+pub fn storage_delete_preview(input: &Value, storage: &TaskStorage) -> Result<String> {
+    let path = input["path"].as_str().unwrap_or("");
+    if path.is_empty() {
+        anyhow::bail!("path is required");
+    }
+    if let Ok(file) = storage.file_path(path) {
+        let bytes = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        return Ok(format!("Would delete {path} from storage ({bytes} bytes)"));
+    }
+    let files = storage.list_files(Some(path))?;
+    if files.is_empty() {
+        anyhow::bail!("not found in storage: {path}");
+    }
+    Ok(format!("Would delete {path}/ from storage, with its {} file(s):\n{}", files.len(), files.join("\n")))
+}
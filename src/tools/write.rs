@@ -1,9 +1,14 @@
 use anyhow::Result;
 use serde_json::json;
 use std::fs;
+use std::path::PathBuf;
 
+use crate::diff::{clip, unified_diff};
 use crate::types::ToolDefinition;
 
+/// Lines of a preview's diff before it's cut short
+const MAX_DIFF_LINES: usize = 200;
+
 pub fn definition() -> ToolDefinition {
     ToolDefinition {
         name: "write_file".into(),
@@ -25,7 +30,7 @@ pub fn definition() -> ToolDefinition {
     }
 }
 
-pub async fn execute(input: &serde_json::Value) -> Result<String> {
+fn target(input: &serde_json::Value) -> Result<(PathBuf, &str)> {
     let path = input["path"]
         .as_str()
         .ok_or_else(|| anyhow::anyhow!("missing 'path' field"))?;
@@ -37,8 +42,13 @@ pub async fn execute(input: &serde_json::Value) -> Result<String> {
         let home = dirs::home_dir().unwrap_or_default();
         home.join(&path[2..])
     } else {
-        std::path::PathBuf::from(path)
+        PathBuf::from(path)
     };
+    Ok((expanded, content))
+}
+
+pub async fn execute(input: &serde_json::Value) -> Result<String> {
+    let (expanded, content) = target(input)?;
 
     if let Some(parent) = expanded.parent() {
         fs::create_dir_all(parent)?;
@@ -47,3 +57,19 @@ pub async fn execute(input: &serde_json::Value) -> Result<String> {
     fs::write(&expanded, content)?;
     Ok(format!("Wrote {} bytes to {}", content.len(), expanded.display()))
 }
+
+/// What `execute` would write, as a diff against the file's current contents
+pub fn preview(input: &serde_json::Value) -> Result<String> {
+    let (expanded, content) = target(input)?;
+    let display = expanded.display().to_string();
+    let (verb, original) = match fs::read_to_string(&expanded) {
+        Ok(original) => ("overwrite", original),
+        Err(_) if expanded.exists() => anyhow::bail!("{display} exists but isn't a text file"),
+        Err(_) => ("create", String::new()),
+    };
+    let diff = unified_diff(&display, &original, content, 3);
+    if diff.is_empty() && verb == "overwrite" {
+        return Ok(format!("Would leave {display} unchanged ({} bytes)", content.len()));
+    }
+    Ok(format!("Would {verb} {display} ({} bytes)\n\n{}", content.len(), clip(&diff, MAX_DIFF_LINES)))
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::client::AnthropicClient;
+use crate::memory::MemoryManager;
+use crate::orchestrator::TaskComplexity;
+use crate::types::{ContentBlock, Message, Role, Thinking, Usage};
+
+/// How much work a request looks like
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effort {
+    /// A lookup or a one-line change
+    Small,
+    #[default]
+    Medium,
+    /// Many files, many steps, or an open-ended investigation
+    Large,
+}
+
+/// A routing decision for one request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Triage {
+    pub tier: TaskComplexity,
+    #[serde(default)]
+    pub effort: Effort,
+    /// Known task slugs the request is about
+    #[serde(default)]
+    pub tasks: Vec<String>,
+    /// Search terms for relevant memory
+    #[serde(default)]
+    pub memory: Vec<String>,
+    /// One line on why — kept with the stats so bad routes can be understood
+    #[serde(default)]
+    pub reason: String,
+    /// What the triage call itself used
+    #[serde(skip)]
+    pub usage: Usage,
+}
+
+const TRIAGE_PROMPT: &str = r#"You route requests for DevMan, a coding assistant, to a model tier. Reply with a single JSON object and nothing else:
+
+{"tier": "quick" | "standard" | "complex", "effort": "small" | "medium" | "large", "tasks": [...], "memory": [...], "reason": "..."}
+
+- quick (Haiku): lookups, status checks, short factual answers.
+- standard (Sonnet): writing or changing code, debugging a known problem, drafting text.
+- complex (Opus): design and architecture, large refactors, open-ended investigations, anything where a wrong answer is expensive.
+Judge by what the work needs, not by keywords: "plan lunch" is quick, "show me why prod is down" is not.
+- effort: how much work it is, independent of tier.
+- tasks: slugs from the known task list that the request is about (may be empty).
+- memory: up to 3 short search terms for notes worth loading (may be empty).
+- reason: one short sentence."#;
+
+impl Triage {
+    /// Used when triage can't run — the middle tier is the safest guess
+    pub fn fallback(reason: &str) -> Self {
+        Self {
+            tier: TaskComplexity::Standard,
+            effort: Effort::Medium,
+            tasks: Vec::new(),
+            memory: Vec::new(),
+            reason: reason.to_string(),
+            usage: Usage::default(),
+        }
+    }
+
+    /// Parse the classifier's reply, dropping tasks it made up
+    pub fn parse(reply: &str, known_tasks: &[String]) -> Result<Self> {
+        let start = reply.find('{').context("triage reply has no JSON object")?;
+        let end = reply.rfind('}').context("triage reply has no JSON object")?;
+        let mut triage: Triage = serde_json::from_str(&reply[start..=end]).context("parsing triage reply")?;
+        triage.tasks.retain(|t| known_tasks.contains(t));
+        triage.memory.truncate(3);
+        Ok(triage)
+    }
+
+    /// Ask `model` to route `message`. Never fails — falls back to the standard tier.
+    pub async fn classify(client: &mut AnthropicClient, model: &str, message: &str, known_tasks: &[String]) -> Self {
+        let mut prompt = format!("Request:\n{message}");
+        if !known_tasks.is_empty() {
+            prompt.push_str(&format!("\n\nKnown tasks: {}", known_tasks.join(", ")));
+        }
+        let messages = [Message { role: Role::User, content: vec![ContentBlock::Text { text: prompt }] }];
+        let reply = client
+            .send_message(model, TRIAGE_PROMPT, &messages, &[], 300, Thinking::Off, &mut |_| {})
+            .await
+            .map(|r| {
+                let text = r
+                    .content
+                    .iter()
+                    .filter_map(|b| match b {
+                        ContentBlock::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("");
+                (text, r.usage)
+            });
+        // A reply that doesn't parse still cost something
+        let usage = reply.as_ref().map(|(_, usage)| usage.clone()).unwrap_or_default();
+        let mut triage = match reply.and_then(|(r, _)| Self::parse(&r, known_tasks)) {
+            Ok(triage) => triage,
+            Err(e) => {
+                tracing::warn!("Triage failed, using the standard tier: {e}");
+                Self::fallback(&format!("triage failed: {e}"))
+            }
+        };
+        triage.usage = usage;
+        triage
+    }
+
+    /// Notes for the sub-agent: the tasks and memory triage picked out
+    pub fn briefing(&self, mm: &MemoryManager) -> String {
+        let mut sections = Vec::new();
+        for task in &self.tasks {
+            if let Ok(content) = mm.load_task(task) {
+                let content: String = content.chars().take(2000).collect();
+                sections.push(format!("### Task: {task}\n{content}"));
+            }
+        }
+        for query in &self.memory {
+            let hits: Vec<String> = mm
+                .search(query)
+                .into_iter()
+                .take(3)
+                .map(|r| format!("- {}:{} {}", r.file, r.line, r.text.trim()))
+                .collect();
+            if !hits.is_empty() {
+                sections.push(format!("### Memory: {query}\n{}", hits.join("\n")));
+            }
+        }
+        if sections.is_empty() {
+            return String::new();
+        }
+        format!("## Context\n\n{}\n\n", sections.join("\n\n"))
+    }
+}
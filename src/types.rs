@@ -22,6 +22,27 @@ pub enum Thinking {
     High,
 }
 
+impl Thinking {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(Self::Off),
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
 /// A message in conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -46,6 +67,10 @@ pub enum ContentBlock {
     Image {
         source: ImageSource,
     },
+    #[serde(rename = "document")]
+    Document {
+        source: ImageSource,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -55,7 +80,7 @@ pub enum ContentBlock {
     #[serde(rename = "tool_result")]
     ToolResult {
         tool_use_id: String,
-        content: String,
+        content: ToolResultContent,
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
@@ -66,11 +91,43 @@ pub enum ContentBlock {
     },
 }
 
+/// Tool result payload — plain text, or a list of text/image/document blocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl ToolResultContent {
+    /// Text portion only — for display, previews and compaction
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(t) => t.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl From<String> for ToolResultContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Base64 source for image and document blocks
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageSource {
     #[serde(rename = "type")]
     pub source_type: String, // "base64"
-    pub media_type: String,  // "image/png", etc.
+    pub media_type: String,  // "image/png", "application/pdf", etc.
     pub data: String,
 }
 
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::agent::AgentLoop;
+use crate::client::AnthropicClient;
+use crate::config::{Config, VerifyConfig};
+use crate::context::ContextManager;
+use crate::orchestrator::TaskComplexity;
+use crate::tools;
+use crate::types::{Thinking, Usage};
+
+/// Keep this much of a test command's output — the end, where the failures are
+const TEST_OUTPUT_CHARS: usize = 4000;
+
+/// A dev task's test command and the project it runs in
+#[derive(Debug, Clone)]
+pub struct TestSpec {
+    pub command: String,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRun {
+    pub command: String,
+    pub passed: bool,
+    pub output: String,
+}
+
+/// A reviewer's judgement of one sub-agent result. Appended to
+/// `verdicts.jsonl` in the run directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    pub round: u32,
+    pub approved: bool,
+    pub feedback: String,
+    pub tests: Option<TestRun>,
+    pub model: String,
+    pub usage: Usage,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Whether results on `task_id` from `tier` get reviewed
+pub fn applies(config: &VerifyConfig, task_id: &str, tier: Option<TaskComplexity>) -> bool {
+    let by_tier = tier.is_some_and(|tier| config.tiers.iter().any(|t| TaskComplexity::parse(t) == Some(tier)));
+    let by_task = config.tasks.iter().any(|t| t == "*" || t == task_id);
+    by_tier || by_task
+}
+
+/// The test command of the dev bot that covers `task_id`, if it has one
+pub fn test_spec(config: &Config, task_id: &str) -> Option<TestSpec> {
+    let bot = config.dev_bot(task_id)?;
+    Some(TestSpec {
+        command: bot.test_command.clone()?,
+        dir: PathBuf::from(bot.working_directory.clone()?),
+    })
+}
+
+/// Run a test command; a timeout or a failure to start counts as failing
+pub async fn run_tests(spec: &TestSpec, timeout_secs: u64) -> TestRun {
+    let mut cmd = Command::new("bash");
+    cmd.arg("-c").arg(&spec.command).current_dir(&spec.dir).kill_on_drop(true);
+    let (passed, output) = match tokio::time::timeout(Duration::from_secs(timeout_secs), cmd.output()).await {
+        Ok(Ok(out)) => {
+            let text = format!("{}{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr));
+            (out.status.success(), text)
+        }
+        Ok(Err(e)) => (false, format!("failed to run: {e}")),
+        Err(_) => (false, format!("timed out after {timeout_secs}s")),
+    };
+    let skip = output.chars().count().saturating_sub(TEST_OUTPUT_CHARS);
+    TestRun {
+        command: spec.command.clone(),
+        passed,
+        output: output.chars().skip(skip).collect(),
+    }
+}
+
+const REVIEW_PROMPT: &str = "You review work done by another agent before it is reported to the user. Check the result against the instructions: is everything asked for done, correct and actually finished, not just described? Use your tools to look at the files and changes it mentions. Be specific about what is wrong or missing; don't ask for polish the instructions didn't call for. End with a line that is exactly `VERDICT: APPROVE` or `VERDICT: REVISE`.";
+
+impl Verdict {
+    /// Review `output` against `instructions`. Failing tests are never approved.
+    /// If the review itself fails the result goes through on the tests alone.
+    pub async fn review(
+        api_key: &str,
+        model: &str,
+        instructions: &str,
+        output: &str,
+        tests: Option<TestRun>,
+        round: u32,
+    ) -> Self {
+        let mut prompt = format!("## Instructions\n{instructions}\n\n## Result\n{output}");
+        if let Some(t) = &tests {
+            let status = if t.passed { "passed" } else { "FAILED" };
+            prompt.push_str(&format!("\n\n## Tests (`{}`) {status}\n```\n{}\n```", t.command, t.output));
+        }
+
+        let tool_defs = vec![
+            tools::read::definition(),
+            tools::search::grep_definition(),
+            tools::search::glob_definition(),
+            tools::git::git_status_definition(),
+            tools::git::git_diff_definition(),
+            tools::git::git_log_definition(),
+        ];
+        let mut agent = AgentLoop::new(
+            AnthropicClient::new(api_key.to_string()),
+            ContextManager::new(),
+            model.to_string(),
+            REVIEW_PROMPT.to_string(),
+            tool_defs,
+            10,
+            4096,
+            Thinking::Off,
+            None,
+            None,
+        );
+        let tests_ok = tests.as_ref().is_none_or(|t| t.passed);
+        let (approved, feedback, usage) = match agent.run_turn(&prompt).await {
+            Ok(result) => {
+                let (approved, feedback) = Self::parse(&result.text);
+                (approved.unwrap_or(tests_ok) && tests_ok, feedback, result.usage)
+            }
+            Err(e) => {
+                tracing::warn!("Review failed: {e}");
+                (tests_ok, format!("Review failed: {e}"), Usage::default())
+            }
+        };
+        Self {
+            round,
+            approved,
+            feedback,
+            tests,
+            model: model.to_string(),
+            usage,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Split a review into its verdict (if it gave one) and the feedback before it
+    pub fn parse(reply: &str) -> (Option<bool>, String) {
+        // Models like to dress the line up as `**Verdict: approve**`
+        let is_verdict = |l: &&str| l.trim_start_matches(['*', '`', '#', ' ']).to_uppercase().starts_with("VERDICT:");
+        let Some(line) = reply.lines().rev().find(is_verdict) else {
+            return (None, reply.trim().to_string());
+        };
+        // Only the exact token approves: `DO NOT APPROVE` or anything unclear is a revise
+        let decoration = ['*', '`', '#', '.', '!', ' '];
+        let token = line.trim_start_matches(decoration).split_once(':').map_or("", |(_, v)| v);
+        let approved = token.trim_matches(decoration).eq_ignore_ascii_case("APPROVE");
+        let feedback = reply.lines().filter(|l| *l != line).collect::<Vec<_>>().join("\n");
+        (Some(approved), feedback.trim().to_string())
+    }
+
+    /// What the original agent is told when its work is sent back
+    pub fn revision_request(&self) -> String {
+        let mut text = format!("[Reviewer feedback — round {}]\n{}", self.round, self.feedback);
+        if let Some(t) = self.tests.as_ref().filter(|t| !t.passed) {
+            text.push_str(&format!("\n\n`{}` failed:\n```\n{}\n```", t.command, t.output));
+        }
+        text.push_str("\n\nFix these, then finish with an updated summary of the result.");
+        text
+    }
+
+    /// Append to `verdicts.jsonl` in the run directory
+    pub fn record(&self, run_dir: &Path) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(run_dir.join("verdicts.jsonl"))?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Every verdict on a run, oldest first
+    pub fn list(run_dir: &Path) -> Vec<Self> {
+        std::fs::read_to_string(run_dir.join("verdicts.jsonl"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect()
+    }
+}
@@ -1,117 +1,268 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 
-/// Voice system — ElevenLabs TTS + optional STT
+use crate::config::Config;
+
+/// Voice system — TTS + STT via ElevenLabs or local commands (whisper.cpp, piper, ...)
+#[derive(Clone)]
 pub struct VoiceEngine {
-    api_key: String,
-    voice_id: String,
+    stt: Option<SttBackend>,
+    tts: Option<TtsBackend>,
     cache_dir: PathBuf,
 }
 
+#[derive(Clone)]
+enum SttBackend {
+    ElevenLabs { api_key: String },
+    /// `{input}` in argv is replaced with the audio path; transcript is read from stdout
+    Command { argv: Vec<String>, timeout: u64 },
+}
+
+#[derive(Clone)]
+enum TtsBackend {
+    ElevenLabs { api_key: String, voice_id: String },
+    /// Text is written to stdin; `{output}` in argv is replaced with the file to write
+    Command { argv: Vec<String>, format: String, timeout: u64 },
+}
+
 const DEFAULT_VOICE_ID: &str = "pFZP5JQG7iQjIQuC4Bku"; // Lily
 const ELEVENLABS_TTS_URL: &str = "https://api.elevenlabs.io/v1/text-to-speech";
 
 impl VoiceEngine {
+    /// ElevenLabs for both directions
     pub fn new(api_key: String, voice_id: Option<String>) -> Result<Self> {
+        Self::with_backends(
+            Some(SttBackend::ElevenLabs { api_key: api_key.clone() }),
+            Some(TtsBackend::ElevenLabs {
+                api_key,
+                voice_id: voice_id.unwrap_or_else(|| DEFAULT_VOICE_ID.to_string()),
+            }),
+        )
+    }
+
+    /// Build from the `[voice]` config section. Returns `None` if neither
+    /// direction is usable (backend "off", or ElevenLabs without a key).
+    pub fn from_config(config: &Config, elevenlabs_key: Option<String>) -> Result<Option<Self>> {
+        let vc = &config.voice;
+        let api_key = elevenlabs_key.or_else(|| config.elevenlabs.as_ref().map(|e| e.api_key.clone()));
+        let voice_id = config
+            .elevenlabs
+            .as_ref()
+            .and_then(|e| e.voice_id.clone())
+            .unwrap_or_else(|| DEFAULT_VOICE_ID.to_string());
+
+        let stt = match vc.stt.as_str() {
+            "elevenlabs" => api_key.clone().map(|api_key| SttBackend::ElevenLabs { api_key }),
+            "command" if !vc.stt_command.is_empty() => Some(SttBackend::Command {
+                argv: vc.stt_command.clone(),
+                timeout: vc.command_timeout,
+            }),
+            "command" => anyhow::bail!("voice.stt = \"command\" but voice.stt_command is empty"),
+            "off" => None,
+            other => anyhow::bail!("unknown voice.stt backend: {other}"),
+        };
+
+        let tts = match vc.tts.as_str() {
+            "elevenlabs" => api_key.map(|api_key| TtsBackend::ElevenLabs { api_key, voice_id }),
+            "command" if !vc.tts_command.is_empty() => Some(TtsBackend::Command {
+                argv: vc.tts_command.clone(),
+                format: vc.tts_format.clone(),
+                timeout: vc.command_timeout,
+            }),
+            "command" => anyhow::bail!("voice.tts = \"command\" but voice.tts_command is empty"),
+            "off" => None,
+            other => anyhow::bail!("unknown voice.tts backend: {other}"),
+        };
+
+        if stt.is_none() && tts.is_none() {
+            return Ok(None);
+        }
+        Self::with_backends(stt, tts).map(Some)
+    }
+
+    fn with_backends(stt: Option<SttBackend>, tts: Option<TtsBackend>) -> Result<Self> {
         let cache_dir = dirs::cache_dir()
             .unwrap_or_else(|| PathBuf::from("/tmp"))
             .join("devman")
             .join("voice-cache");
         std::fs::create_dir_all(&cache_dir)?;
 
-        Ok(Self {
-            api_key,
-            voice_id: voice_id.unwrap_or_else(|| DEFAULT_VOICE_ID.to_string()),
-            cache_dir,
-        })
+        Ok(Self { stt, tts, cache_dir })
+    }
+
+    pub fn can_transcribe(&self) -> bool {
+        self.stt.is_some()
+    }
+
+    pub fn can_speak(&self) -> bool {
+        self.tts.is_some()
     }
 
     /// Convert text to speech, return path to audio file
     pub async fn tts(&self, text: &str) -> Result<PathBuf> {
-        // Check cache (hash of text + voice_id)
-        let hash = simple_hash(text, &self.voice_id);
-        let cache_path = self.cache_dir.join(format!("{hash}.mp3"));
+        let backend = self.tts.as_ref().context("text-to-speech is not configured")?;
+
+        // Check cache (hash of text + backend identity)
+        let (identity, ext) = match backend {
+            TtsBackend::ElevenLabs { voice_id, .. } => (voice_id.clone(), "mp3"),
+            TtsBackend::Command { argv, format, .. } => (argv.join(" "), format.as_str()),
+        };
+        let hash = simple_hash(text, &identity);
+        let cache_path = self.cache_dir.join(format!("{hash}.{ext}"));
         if cache_path.exists() {
             return Ok(cache_path);
         }
 
-        let client = reqwest::Client::new();
-        let url = format!("{}/{}", ELEVENLABS_TTS_URL, self.voice_id);
-
-        let body = serde_json::json!({
-            "text": text,
-            "model_id": "eleven_multilingual_v2",
-            "voice_settings": {
-                "stability": 0.5,
-                "similarity_boost": 0.75,
-                "style": 0.0,
-                "use_speaker_boost": true
+        match backend {
+            TtsBackend::ElevenLabs { api_key, voice_id } => {
+                elevenlabs_tts(api_key, voice_id, text, &cache_path).await?;
+            }
+            TtsBackend::Command { argv, timeout, .. } => {
+                let output = cache_path.to_string_lossy().to_string();
+                run_command(argv, &[("{output}", &output)], Some(text), *timeout).await?;
+                if !cache_path.exists() {
+                    anyhow::bail!("TTS command did not write {output}");
+                }
             }
-        });
-
-        let response = client
-            .post(&url)
-            .header("xi-api-key", &self.api_key)
-            .header("Content-Type", "application/json")
-            .header("Accept", "audio/mpeg")
-            .json(&body)
-            .send()
-            .await
-            .context("ElevenLabs TTS request failed")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("ElevenLabs TTS error {status}: {text}");
         }
 
-        let bytes = response.bytes().await?;
-        std::fs::write(&cache_path, &bytes)?;
-
         Ok(cache_path)
     }
 
-    /// Transcribe audio file to text (ElevenLabs Scribe)
-    pub async fn stt(&self, audio_path: &PathBuf) -> Result<String> {
-        let client = reqwest::Client::new();
-
-        let file_bytes = tokio::fs::read(audio_path).await?;
-        let file_name = audio_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        let part = reqwest::multipart::Part::bytes(file_bytes)
-            .file_name(file_name)
-            .mime_str("audio/ogg")?;
-
-        let form = reqwest::multipart::Form::new()
-            .part("file", part)
-            .text("model_id", "scribe_v1");
-
-        let response = client
-            .post("https://api.elevenlabs.io/v1/speech-to-text")
-            .header("xi-api-key", &self.api_key)
-            .multipart(form)
-            .send()
-            .await
-            .context("ElevenLabs STT request failed")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("ElevenLabs STT error {status}: {text}");
+    /// Transcribe audio file to text
+    pub async fn stt(&self, audio_path: &Path) -> Result<String> {
+        match self.stt.as_ref().context("speech-to-text is not configured")? {
+            SttBackend::ElevenLabs { api_key } => elevenlabs_stt(api_key, audio_path).await,
+            SttBackend::Command { argv, timeout } => {
+                let input = audio_path.to_string_lossy().to_string();
+                let out = run_command(argv, &[("{input}", &input)], None, *timeout).await?;
+                Ok(out.trim().to_string())
+            }
         }
+    }
+}
+
+async fn elevenlabs_tts(api_key: &str, voice_id: &str, text: &str, dest: &Path) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/{}", ELEVENLABS_TTS_URL, voice_id);
+
+    let body = serde_json::json!({
+        "text": text,
+        "model_id": "eleven_multilingual_v2",
+        "voice_settings": {
+            "stability": 0.5,
+            "similarity_boost": 0.75,
+            "style": 0.0,
+            "use_speaker_boost": true
+        }
+    });
+
+    let response = client
+        .post(&url)
+        .header("xi-api-key", api_key)
+        .header("Content-Type", "application/json")
+        .header("Accept", "audio/mpeg")
+        .json(&body)
+        .send()
+        .await
+        .context("ElevenLabs TTS request failed")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("ElevenLabs TTS error {status}: {text}");
+    }
+
+    let bytes = response.bytes().await?;
+    std::fs::write(dest, &bytes)?;
+    Ok(())
+}
+
+/// Transcribe via ElevenLabs Scribe
+async fn elevenlabs_stt(api_key: &str, audio_path: &Path) -> Result<String> {
+    let client = reqwest::Client::new();
+
+    let file_bytes = tokio::fs::read(audio_path).await?;
+    let file_name = audio_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let part = reqwest::multipart::Part::bytes(file_bytes)
+        .file_name(file_name)
+        .mime_str("audio/ogg")?;
+
+    let form = reqwest::multipart::Form::new()
+        .part("file", part)
+        .text("model_id", "scribe_v1");
+
+    let response = client
+        .post("https://api.elevenlabs.io/v1/speech-to-text")
+        .header("xi-api-key", api_key)
+        .multipart(form)
+        .send()
+        .await
+        .context("ElevenLabs STT request failed")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("ElevenLabs STT error {status}: {text}");
+    }
+
+    let data: serde_json::Value = response.json().await?;
+    let text = data["text"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    Ok(text)
+}
+
+/// Run a local voice command with placeholder substitution, returning stdout
+async fn run_command(argv: &[String], replacements: &[(&str, &str)], stdin: Option<&str>, timeout: u64) -> Result<String> {
+    let args: Vec<String> = argv
+        .iter()
+        .map(|a| {
+            replacements
+                .iter()
+                .fold(a.clone(), |acc, (from, to)| acc.replace(from, to))
+        })
+        .collect();
+    let (program, rest) = args.split_first().context("empty voice command")?;
+
+    let mut child = tokio::process::Command::new(program)
+        .args(rest)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("starting voice command: {program}"))?;
+
+    if let Some(text) = stdin
+        && let Some(mut pipe) = child.stdin.take()
+    {
+        pipe.write_all(text.as_bytes()).await?;
+        drop(pipe);
+    }
 
-        let data: serde_json::Value = response.json().await?;
-        let text = data["text"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
+    let output = tokio::time::timeout(Duration::from_secs(timeout), child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("voice command timed out after {timeout}s"))??;
 
-        Ok(text)
+    if !output.status.success() {
+        anyhow::bail!(
+            "voice command failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
 /// Simple hash for cache keys
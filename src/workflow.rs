@@ -0,0 +1,421 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ask::Origin;
+use crate::orchestrator::{Priority, TaskComplexity};
+use crate::tools::policy::ToolPolicy;
+
+// ---------------------------------------------------------------------------
+// Definition
+// ---------------------------------------------------------------------------
+
+/// A goal broken into sub-agent steps. Steps start as soon as everything
+/// they depend on is done, so independent steps run in parallel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    /// Task whose storage the steps share (default: one per workflow run)
+    #[serde(default)]
+    pub task: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub id: String,
+    /// What the sub-agent is asked to do
+    pub instructions: String,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Model tier to start on (default: standard). Escalates after failed retries.
+    #[serde(default)]
+    pub tier: TaskComplexity,
+    /// Tools the step may use (default: all). Storage tools are added when it has inputs or outputs.
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Storage files from upstream steps that this step reads
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Storage files this step must write
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Extra attempts on each tier before escalating
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn default_retries() -> u32 {
+    1
+}
+
+impl Workflow {
+    /// Load a workflow from a TOML file (`name`, optional `task`, `[[steps]]`)
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("reading workflow {}", path.display()))?;
+        let workflow: Workflow = toml::from_str(&content)
+            .with_context(|| format!("parsing workflow {}", path.display()))?;
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    /// Check step ids, dependencies and data flow, and that there are no cycles
+    pub fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            anyhow::bail!("workflow '{}' has no steps", self.name);
+        }
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if step.id.is_empty() || !step.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                anyhow::bail!("step id '{}' must be letters, digits, '-' or '_'", step.id);
+            }
+            if !ids.insert(step.id.as_str()) {
+                anyhow::bail!("duplicate step id '{}'", step.id);
+            }
+        }
+        for step in &self.steps {
+            if let Some(dep) = step.depends_on.iter().find(|d| !ids.contains(d.as_str())) {
+                anyhow::bail!("step '{}' depends on unknown step '{dep}'", step.id);
+            }
+        }
+        self.order()?;
+
+        let mut written: HashMap<&str, &str> = HashMap::new();
+        for step in &self.steps {
+            for output in &step.outputs {
+                if let Some(other) = written.insert(output, &step.id) {
+                    anyhow::bail!("steps '{other}' and '{}' both write '{output}'", step.id);
+                }
+            }
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            let upstream = self.upstream(i);
+            for input in &step.inputs {
+                match written.get(input.as_str()) {
+                    Some(writer) if upstream.iter().any(|&u| self.steps[u].id == *writer) => {}
+                    Some(writer) => anyhow::bail!(
+                        "step '{}' reads '{input}' from '{writer}' but doesn't depend on it",
+                        step.id
+                    ),
+                    None => anyhow::bail!("step '{}' reads '{input}', which no step writes", step.id),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Step indices in dependency order; fails on a cycle
+    pub fn order(&self) -> Result<Vec<usize>> {
+        let index: HashMap<&str, usize> = self.steps.iter().enumerate().map(|(i, s)| (s.id.as_str(), i)).collect();
+        let mut remaining: Vec<usize> = self.steps.iter().map(|s| s.depends_on.len()).collect();
+        let mut ready: Vec<usize> = (0..self.steps.len()).filter(|&i| remaining[i] == 0).collect();
+        let mut order = Vec::new();
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            for (j, step) in self.steps.iter().enumerate() {
+                let n = step.depends_on.iter().filter(|d| index.get(d.as_str()) == Some(&i)).count();
+                if n > 0 {
+                    remaining[j] -= n;
+                    if remaining[j] == 0 {
+                        ready.push(j);
+                    }
+                }
+            }
+        }
+        if order.len() < self.steps.len() {
+            let stuck: Vec<&str> = (0..self.steps.len())
+                .filter(|i| !order.contains(i))
+                .map(|i| self.steps[i].id.as_str())
+                .collect();
+            anyhow::bail!("dependency cycle between steps: {}", stuck.join(", "));
+        }
+        Ok(order)
+    }
+
+    /// Every step `i` depends on, directly or not
+    fn upstream(&self, i: usize) -> Vec<usize> {
+        let mut seen = Vec::new();
+        let mut stack: Vec<&str> = self.steps[i].depends_on.iter().map(String::as_str).collect();
+        while let Some(id) = stack.pop() {
+            if let Some(j) = self.steps.iter().position(|s| s.id == id)
+                && !seen.contains(&j)
+            {
+                seen.push(j);
+                stack.extend(self.steps[j].depends_on.iter().map(String::as_str));
+            }
+        }
+        seen
+    }
+
+    /// Every step that depends on `i`, directly or not
+    fn downstream(&self, i: usize) -> Vec<usize> {
+        (0..self.steps.len()).filter(|&j| self.upstream(j).contains(&i)).collect()
+    }
+}
+
+impl Step {
+    /// The sub-agent's message: instructions plus where its data comes from and goes
+    pub fn message(&self, workflow: &str) -> String {
+        let mut message = format!("You are step '{}' of the workflow '{workflow}'.\n\n{}", self.id, self.instructions);
+        if !self.inputs.is_empty() {
+            message.push_str(&format!(
+                "\n\nEarlier steps left these files in storage — read them with storage_read: {}",
+                self.inputs.join(", ")
+            ));
+        }
+        if !self.outputs.is_empty() {
+            message.push_str(&format!(
+                "\n\nBefore you finish, write these files with storage_write: {}. Later steps depend on them.",
+                self.outputs.join(", ")
+            ));
+        }
+        message
+    }
+
+    /// The tool allowlist for the step's agent, or empty for all tools
+    pub fn allowed_tools(&self) -> Vec<String> {
+        let mut tools = self.tools.clone();
+        let uses_storage = !self.inputs.is_empty() || !self.outputs.is_empty();
+        if !tools.is_empty() && uses_storage {
+            for t in ["storage_read", "storage_write", "storage_list"] {
+                if !tools.iter().any(|x| x == t) {
+                    tools.push(t.to_string());
+                }
+            }
+        }
+        tools
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Run state
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "detail", rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Done,
+    Failed(String),
+    /// Never started because something upstream failed
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepState {
+    pub status: StepStatus,
+    /// Tier of the current (or last) attempt
+    pub tier: TaskComplexity,
+    /// Attempts on the current tier
+    pub attempts: u32,
+    /// Sub-agent run of the current (or last) attempt
+    pub run_id: Option<String>,
+}
+
+/// What became of a step after its agent finished
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    Done,
+    /// Trying again on the same tier
+    Retry,
+    /// Trying again on a bigger model
+    Escalated(TaskComplexity),
+    /// Out of attempts; everything downstream is skipped
+    Failed,
+}
+
+/// A workflow in progress. Saved as `<id>.json` under the workflows directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: String,
+    pub workflow: Workflow,
+    /// Parallel to `workflow.steps`
+    pub steps: Vec<StepState>,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Where the steps' `ask_user` questions go
+    #[serde(default)]
+    pub origin: Option<Origin>,
+    /// Tool policy of whoever started it; every step runs under it
+    #[serde(default)]
+    pub policy: ToolPolicy,
+}
+
+impl WorkflowRun {
+    pub fn new(workflow: Workflow) -> Self {
+        let slug: String = workflow
+            .name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .take(20)
+            .collect();
+        let steps = workflow
+            .steps
+            .iter()
+            .map(|s| StepState { status: StepStatus::Pending, tier: s.tier, attempts: 0, run_id: None })
+            .collect();
+        Self {
+            id: format!("wf-{}-{slug}", Utc::now().format("%Y%m%d-%H%M%S")),
+            workflow,
+            steps,
+            started: Utc::now(),
+            finished: None,
+            priority: Priority::default(),
+            origin: None,
+            policy: ToolPolicy::default(),
+        }
+    }
+
+    /// Storage task the steps share
+    pub fn task(&self) -> String {
+        self.workflow.task.clone().unwrap_or_else(|| self.id.clone())
+    }
+
+    /// Pending steps whose dependencies are all done
+    pub fn ready(&self) -> Vec<usize> {
+        (0..self.steps.len())
+            .filter(|&i| self.steps[i].status == StepStatus::Pending)
+            .filter(|&i| {
+                self.workflow.steps[i].depends_on.iter().all(|d| {
+                    self.workflow
+                        .steps
+                        .iter()
+                        .position(|s| s.id == *d)
+                        .is_some_and(|j| self.steps[j].status == StepStatus::Done)
+                })
+            })
+            .collect()
+    }
+
+    pub fn step_started(&mut self, i: usize, run_id: &str) {
+        let state = &mut self.steps[i];
+        state.status = StepStatus::Running;
+        state.attempts += 1;
+        state.run_id = Some(run_id.to_string());
+    }
+
+    /// The step with this sub-agent run, if it's one of ours
+    pub fn step_for_run(&self, run_id: &str) -> Option<usize> {
+        self.steps.iter().position(|s| s.run_id.as_deref() == Some(run_id) && s.status == StepStatus::Running)
+    }
+
+    /// Record how a step's agent did. Failures are retried on the same tier,
+    /// then escalated a tier at a time, then given up on.
+    pub fn step_finished(&mut self, i: usize, result: std::result::Result<(), String>) -> StepOutcome {
+        let retries = self.workflow.steps[i].retries;
+        let state = &mut self.steps[i];
+        let outcome = match result {
+            Ok(()) => {
+                state.status = StepStatus::Done;
+                StepOutcome::Done
+            }
+            Err(_) if state.attempts <= retries => {
+                state.status = StepStatus::Pending;
+                StepOutcome::Retry
+            }
+            Err(e) => match state.tier.escalate() {
+                Some(tier) => {
+                    state.status = StepStatus::Pending;
+                    state.tier = tier;
+                    state.attempts = 0;
+                    StepOutcome::Escalated(tier)
+                }
+                None => {
+                    self.step_failed(i, &e);
+                    StepOutcome::Failed
+                }
+            },
+        };
+        if self.is_finished() && self.finished.is_none() {
+            self.finished = Some(Utc::now());
+        }
+        outcome
+    }
+
+    /// Give up on a step without retrying, and skip the steps that depend on it
+    pub fn step_failed(&mut self, i: usize, reason: &str) {
+        self.steps[i].status = StepStatus::Failed(reason.to_string());
+        for j in self.workflow.downstream(i) {
+            self.steps[j].status = StepStatus::Skipped;
+        }
+        if self.is_finished() && self.finished.is_none() {
+            self.finished = Some(Utc::now());
+        }
+    }
+
+    /// Give up on whatever hasn't finished
+    pub fn abandon(&mut self, reason: &str) {
+        for state in &mut self.steps {
+            match state.status {
+                StepStatus::Running => state.status = StepStatus::Failed(reason.to_string()),
+                StepStatus::Pending => state.status = StepStatus::Skipped,
+                _ => {}
+            }
+        }
+        self.finished = Some(Utc::now());
+    }
+
+    /// Nothing running and nothing left that can start
+    pub fn is_finished(&self) -> bool {
+        self.steps.iter().all(|s| matches!(s.status, StepStatus::Done | StepStatus::Failed(_) | StepStatus::Skipped))
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.steps.iter().all(|s| s.status == StepStatus::Done)
+    }
+
+    /// One line per step, for the manager and the CLI
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("Workflow '{}' ({})", self.workflow.name, self.id)];
+        for (step, state) in self.workflow.steps.iter().zip(&self.steps) {
+            let status = match &state.status {
+                StepStatus::Pending => "⏳ pending".to_string(),
+                StepStatus::Running => format!("🔄 running ({:?})", state.tier).to_lowercase(),
+                StepStatus::Done => "✅ done".to_string(),
+                StepStatus::Failed(e) => format!("❌ failed: {e}"),
+                StepStatus::Skipped => "⏭ skipped".to_string(),
+            };
+            lines.push(format!("- {}: {status}", step.id));
+        }
+        if !self.workflow.steps.iter().all(|s| s.outputs.is_empty()) {
+            lines.push(format!("Outputs are in the storage of task '{}'.", self.task()));
+        }
+        lines.join("\n")
+    }
+
+    /// Where workflow runs are saved
+    pub fn default_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("devman")
+            .join("workflows")
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{}.json", self.id)), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Saved runs, newest first
+    pub fn list(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut runs: Vec<Self> = entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+            .filter_map(|c| serde_json::from_str(&c).ok())
+            .collect();
+        runs.sort_by_key(|r| std::cmp::Reverse(r.started));
+        runs
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What happens to a sub-agent's worktree branch when it finishes (`agents.worktree`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorktreeMode {
+    /// Agents share the dev bot's checkout
+    Off,
+    /// Merge the branch into the checkout it came from
+    Merge,
+    /// Push the branch and open a pull request
+    Pr,
+    /// Leave the branch for someone to look at
+    Keep,
+    /// Throw the work away
+    Discard,
+}
+
+impl WorktreeMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(Self::Off),
+            "merge" => Ok(Self::Merge),
+            "pr" => Ok(Self::Pr),
+            "keep" => Ok(Self::Keep),
+            "discard" => Ok(Self::Discard),
+            other => anyhow::bail!("unknown agents.worktree mode: {other} (expected off, merge, pr, keep or discard)"),
+        }
+    }
+}
+
+/// Tools whose working directory defaults to the worktree
+const CWD_TOOLS: &[&str] = &[
+    "shell",
+    "process_start",
+    "apply_patch",
+    "claude_code",
+    "grep",
+    "glob",
+    "repo_map",
+    "git_status",
+    "git_diff",
+    "git_commit",
+    "git_push",
+    "git_log",
+    "git_branch",
+];
+
+/// A git worktree and branch of its own for one sub-agent run, so parallel
+/// agents on the same repository don't commit each other's changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Worktree {
+    /// The checkout it was made from
+    pub repo: PathBuf,
+    pub path: PathBuf,
+    pub branch: String,
+    /// Branch the checkout was on — where merges and PRs go
+    pub base_branch: String,
+    /// Commit it started from
+    pub base: String,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl Worktree {
+    /// Branch `devman/<run_id>` off the repository's HEAD into `<root>/<run_id>`
+    pub fn create(repo: &Path, root: &Path, run_id: &str) -> Result<Self> {
+        let repo = PathBuf::from(git(repo, &["rev-parse", "--show-toplevel"])?);
+        let base = git(&repo, &["rev-parse", "HEAD"])?;
+        let base_branch = git(&repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let branch = format!("devman/{run_id}");
+        std::fs::create_dir_all(root)?;
+        let path = root.join(run_id);
+        git(&repo, &["worktree", "add", "-b", &branch, &path.to_string_lossy(), &base])?;
+        Ok(Self { repo, path, branch, base_branch, base })
+    }
+
+    /// Point a path at the worktree: relative paths and paths inside the
+    /// original checkout land in the worktree, anything else is left alone
+    pub fn map_path(&self, path: &str) -> String {
+        let p = Path::new(path);
+        if path.starts_with('~') {
+            return path.to_string();
+        }
+        let mapped = match p.strip_prefix(&self.repo) {
+            Ok(rel) => self.path.join(rel),
+            Err(_) if p.is_relative() => self.path.join(p),
+            Err(_) => return path.to_string(),
+        };
+        mapped.to_string_lossy().into_owned()
+    }
+
+    /// Rewrite a tool call's paths and working directory to point into the worktree
+    pub fn root_input(&self, tool: &str, input: &serde_json::Value) -> serde_json::Value {
+        let mut input = input.clone();
+        let Some(obj) = input.as_object_mut() else { return input };
+        for key in ["path", "workdir", "working_directory"] {
+            if let Some(serde_json::Value::String(p)) = obj.get(key) {
+                let mapped = self.map_path(p);
+                obj.insert(key.into(), mapped.into());
+            }
+        }
+        if CWD_TOOLS.contains(&tool) {
+            let key = match tool {
+                "shell" | "process_start" | "apply_patch" => "workdir",
+                "claude_code" => "working_directory",
+                _ => "path",
+            };
+            obj.entry(key).or_insert_with(|| self.path.to_string_lossy().into_owned().into());
+        }
+        input
+    }
+
+    /// Commit anything the agent left uncommitted. Returns whether there was anything.
+    pub fn commit_all(&self, message: &str) -> Result<bool> {
+        git(&self.path, &["add", "-A"])?;
+        if git(&self.path, &["status", "--porcelain"])?.is_empty() {
+            return Ok(false);
+        }
+        git(&self.path, &["commit", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Commits on the branch since it started
+    pub fn commits_ahead(&self) -> Result<u32> {
+        let count = git(&self.path, &["rev-list", "--count", &format!("{}..HEAD", self.base)])?;
+        count.parse().context("parsing git rev-list output")
+    }
+
+    /// Merge the branch into `base_branch`, backing out on conflicts. In the
+    /// original checkout if it's still on that branch and has no uncommitted
+    /// changes; in a scratch worktree if it has moved to another branch.
+    pub fn merge(&self) -> Result<()> {
+        if self.base_branch == "HEAD" {
+            anyhow::bail!("{} was on a detached HEAD, so there's no branch to merge into", self.repo.display());
+        }
+        let current = git(&self.repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        if current == self.base_branch {
+            if !git(&self.repo, &["status", "--porcelain", "--untracked-files=no"])?.is_empty() {
+                anyhow::bail!("{} has uncommitted changes on {}", self.repo.display(), self.base_branch);
+            }
+            return self.merge_in(&self.repo);
+        }
+
+        let name = self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let scratch = self.path.with_file_name(format!("{name}-merge"));
+        git(&self.repo, &["worktree", "add", &scratch.to_string_lossy(), &self.base_branch])?;
+        let result = self.merge_in(&scratch);
+        let _ = git(&self.repo, &["worktree", "remove", "--force", &scratch.to_string_lossy()]);
+        result
+    }
+
+    fn merge_in(&self, checkout: &Path) -> Result<()> {
+        let message = format!("Merge {}", self.branch);
+        if let Err(e) = git(checkout, &["merge", "--no-ff", "-m", &message, &self.branch]) {
+            let _ = git(checkout, &["merge", "--abort"]);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn push(&self) -> Result<()> {
+        git(&self.path, &["push", "-u", "origin", &self.branch]).map(|_| ())
+    }
+
+    /// Remove the worktree directory, and the branch too unless it's being kept
+    pub fn remove(&self, delete_branch: bool) -> Result<()> {
+        if self.path.exists() {
+            git(&self.repo, &["worktree", "remove", "--force", &self.path.to_string_lossy()])?;
+        }
+        let _ = git(&self.repo, &["worktree", "prune"]);
+        if delete_branch {
+            git(&self.repo, &["branch", "-D", &self.branch])?;
+        }
+        Ok(())
+    }
+
+    /// Told to the agent so it knows where it's working
+    pub fn prompt(&self) -> String {
+        format!(
+            "You are working in a git worktree at {} on branch {}, split from {} so other agents' changes can't mix with yours. Paths inside {} are mapped there, and your tools start in it. Commit your work on this branch; don't switch branches.",
+            self.path.display(),
+            self.branch,
+            self.repo.display(),
+            self.repo.display()
+        )
+    }
+}
@@ -172,6 +172,9 @@ fn test_add_remove_job() {
         last_run: None,
         next_run: None,
         created: chrono::Utc::now(),
+        origin: None,
+        tools: Default::default(),
+        dry_run: false,
     });
 
     assert_eq!(sched.list().len(), 1);
@@ -201,6 +204,9 @@ fn test_tick_fires_due_job() {
         last_run: None,
         next_run: Some(past), // already due
         created: chrono::Utc::now(),
+        origin: None,
+        tools: Default::default(),
+        dry_run: false,
     });
 
     let due = sched.tick();
@@ -225,6 +231,9 @@ fn test_one_shot_removed() {
         last_run: None,
         next_run: Some(past),
         created: chrono::Utc::now(),
+        origin: None,
+        tools: Default::default(),
+        dry_run: false,
     });
 
     assert_eq!(sched.list().len(), 1);
@@ -306,3 +315,1495 @@ async fn test_edit_file() {
     let content = std::fs::read_to_string(&file_path).unwrap();
     assert_eq!(content, "foo qux baz");
 }
+
+#[tokio::test]
+async fn test_edit_file_multi_and_loose_match() {
+    let dir = TempDir::new().unwrap();
+    let file_path = dir.path().join("lib.rs");
+    let path_str = file_path.to_str().unwrap();
+    let original = "fn main() {\r\n    let x = 1;\r\n    println!(\"{}\", x);\r\n    let y = x;\r\n}\r\n";
+    std::fs::write(&file_path, original).unwrap();
+
+    // Unindented LF old_text against indented CRLF lines, plus replace_all
+    let input = serde_json::json!({
+        "path": path_str,
+        "edits": [
+            { "old_text": "let x = 1;\nprintln!(\"{}\", x);\n", "new_text": "let x = 2;\nprintln!(\"x = {}\", x);\n" },
+            { "old_text": "x", "new_text": "value", "replace_all": true }
+        ]
+    });
+    let result = devman::tools::edit::execute(&input).await.unwrap();
+    assert!(result.contains("Edited"));
+    assert!(result.contains("matched ignoring whitespace"));
+    assert!(result.contains("-    let x = 1;"));
+    assert!(result.contains("+    let value = 2;"));
+    let content = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(
+        content,
+        "fn main() {\r\n    let value = 2;\r\n    println!(\"value = {}\", value);\r\n    let y = value;\r\n}\r\n"
+    );
+
+    // A later edit failing leaves the file as it was
+    let input = serde_json::json!({
+        "path": path_str,
+        "edits": [
+            { "old_text": "let y", "new_text": "let z" },
+            { "old_text": "let valeu = 3;", "new_text": "" }
+        ]
+    });
+    let err = devman::tools::edit::execute(&input).await.unwrap_err().to_string();
+    assert!(err.starts_with("edit 2:"), "{err}");
+    assert!(err.contains("Closest match"), "{err}");
+    assert!(err.contains("2 |     let value = 2;"), "{err}");
+    assert!(err.contains("No edits were made"));
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), content);
+
+    // Repeated text needs replace_all
+    let input = serde_json::json!({ "path": path_str, "old_text": "value", "new_text": "v" });
+    let err = devman::tools::edit::execute(&input).await.unwrap_err().to_string();
+    assert!(err.contains("found 4 times"), "{err}");
+}
+
+#[tokio::test]
+async fn test_grep_and_glob_tools() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src/nested")).unwrap();
+    std::fs::create_dir_all(root.join("target")).unwrap();
+    std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+    std::fs::write(root.join("src/lib.rs"), "fn alpha() {}\nfn beta() {}\n// alpha again\n").unwrap();
+    std::fs::write(root.join("src/nested/mod.rs"), "fn alpha_two() {}\n").unwrap();
+    std::fs::write(root.join("notes.md"), "alpha in docs\n").unwrap();
+    std::fs::write(root.join("target/out.rs"), "fn alpha() {}\n").unwrap();
+    std::fs::write(root.join("blob.bin"), b"alpha\0\x01\x02").unwrap();
+    let path = root.to_str().unwrap();
+
+    // Ignored and binary files are skipped; most matches first
+    let input = serde_json::json!({ "pattern": "alpha", "path": path });
+    let out = devman::tools::search::grep_execute(&input).await.unwrap();
+    assert!(out.starts_with("src/lib.rs\n1:fn alpha() {}\n3:// alpha again"), "{out}");
+    assert!(out.contains("notes.md"));
+    assert!(!out.contains("target"));
+    assert!(!out.contains("blob.bin"));
+
+    // Type filter, context lines and output modes
+    let input = serde_json::json!({ "pattern": "^fn beta", "path": path, "type": "rust", "context": 1 });
+    let out = devman::tools::search::grep_execute(&input).await.unwrap();
+    assert_eq!(out, "src/lib.rs\n1-fn alpha() {}\n2:fn beta() {}\n3-// alpha again");
+    let input = serde_json::json!({ "pattern": "ALPHA", "path": path, "case_insensitive": true, "output": "count", "glob": "*.rs" });
+    let out = devman::tools::search::grep_execute(&input).await.unwrap();
+    assert_eq!(out, "src/lib.rs:2\nsrc/nested/mod.rs:1");
+    let input = serde_json::json!({ "pattern": "alpha", "path": path, "max_results": 1 });
+    let out = devman::tools::search::grep_execute(&input).await.unwrap();
+    assert!(out.contains("3 more matches not shown"), "{out}");
+
+    let input = serde_json::json!({ "pattern": "**/*.rs", "path": path, "sort": "path" });
+    let out = devman::tools::search::glob_execute(&input).await.unwrap();
+    assert_eq!(out, "src/lib.rs\nsrc/nested/mod.rs");
+    let input = serde_json::json!({ "pattern": "mod.rs", "path": path });
+    let out = devman::tools::search::glob_execute(&input).await.unwrap();
+    assert_eq!(out, "src/nested/mod.rs");
+}
+
+#[tokio::test]
+async fn test_background_processes() {
+    use devman::process::ProcessTable;
+    use devman::tools::process::execute;
+    let table = ProcessTable::new();
+
+    let out = execute("process_start", &serde_json::json!({ "command": "echo one; echo two >&2" }), &table, None)
+        .await
+        .unwrap();
+    assert!(out.starts_with("Started process 1"));
+    let p = table.get(1).unwrap();
+    assert_eq!(p.wait(std::time::Duration::from_secs(5)).await, Some(0));
+    // Reads pick up where the last stopped; an offset re-reads
+    let all = p.read(Some(0), 100);
+    assert!(all.text.contains("one") && all.text.contains("two"));
+    assert_eq!(p.read(None, 100).text, "");
+    assert_eq!(p.read(Some(4), 100).from, 4);
+
+    execute("process_start", &serde_json::json!({ "command": "sleep 30" }), &table, None).await.unwrap();
+    let list = execute("process_list", &serde_json::json!({}), &table, None).await.unwrap();
+    assert!(list.contains("exited with code 0") && list.contains("running"));
+    let out = execute("process_wait", &serde_json::json!({ "id": 2, "timeout": 1 }), &table, None).await.unwrap();
+    assert!(out.contains("process 2 running"));
+    let out = execute("process_kill", &serde_json::json!({ "id": 2 }), &table, None).await.unwrap();
+    assert!(out.starts_with("Killed process 2"));
+    assert!(execute("process_output", &serde_json::json!({ "id": 9 }), &table, None).await.is_err());
+
+    // Dropping the last handle kills what's left
+    execute("process_start", &serde_json::json!({ "command": "sleep 30" }), &table, None).await.unwrap();
+    let p = table.get(3).unwrap();
+    drop(table);
+    assert!(p.wait(std::time::Duration::from_secs(5)).await.is_some());
+}
+
+#[tokio::test]
+async fn test_shell_session() {
+    use devman::shell_session::ShellSession;
+    use devman::tools::shell::execute_in_session;
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().to_str().unwrap();
+    let session = ShellSession::new();
+    let run = |command: &str| serde_json::json!({ "command": command, "workdir": path, "timeout": 1 });
+
+    // cwd, exports and shell variables carry over
+    execute_in_session(&run("mkdir sub && cd sub && export GREETING=hi && N=3"), &session, None).await.unwrap();
+    let out = execute_in_session(&run("pwd; echo $GREETING $N"), &session, None).await.unwrap();
+    assert!(out.contains("/sub\nhi 3"), "{out}");
+
+    // Errors, stderr and syntax errors leave the session up
+    let out = execute_in_session(&run("echo oops >&2; false"), &session, None).await.unwrap();
+    assert!(out.contains("STDERR:\noops") && out.contains("Exit code: 1"), "{out}");
+    let out = execute_in_session(&run("if then"), &session, None).await.unwrap();
+    assert!(out.contains("Exit code: 2"), "{out}");
+
+    // A timeout interrupts the command, not the session
+    let out = execute_in_session(&run("echo before; sleep 30"), &session, None).await.unwrap();
+    assert!(out.contains("before") && out.contains("timed out after 1s") && out.contains("still running"), "{out}");
+    assert_eq!(execute_in_session(&run("echo $GREETING"), &session, None).await.unwrap(), "hi\n");
+
+    // exit ends it; the next command gets a fresh shell
+    let out = execute_in_session(&run("exit 3"), &session, None).await.unwrap();
+    assert!(out.contains("Exit code: -1") && out.contains("session ended"), "{out}");
+    assert_eq!(execute_in_session(&run("echo \"[$GREETING]\""), &session, None).await.unwrap(), "[]\n");
+
+    execute_in_session(&run("export GREETING=again"), &session, None).await.unwrap();
+    assert!(session.reset().await);
+    assert_eq!(execute_in_session(&run("echo \"[$GREETING]\"; pwd"), &session, None).await.unwrap(), format!("[]\n{path}\n"));
+}
+
+#[tokio::test]
+async fn test_file_checkpoints_undo() {
+    use devman::checkpoint::CheckpointStore;
+
+    let dir = TempDir::new().unwrap();
+    let work = dir.path().join("work");
+    std::fs::create_dir_all(&work).unwrap();
+    let a = work.join("a.txt");
+    std::fs::write(&a, "one\ntwo\n").unwrap();
+    let mut store = CheckpointStore::open(dir.path().join("checkpoints"));
+
+    // Turn 1: two edits to the same file, snapshotted once
+    store.begin_turn("first change");
+    let edit = serde_json::json!({ "path": a.to_str().unwrap(), "old_text": "one", "new_text": "uno" });
+    store.before_tool("edit_file", &edit).unwrap();
+    devman::tools::edit::execute(&edit).await.unwrap();
+    let edit = serde_json::json!({ "path": a.to_str().unwrap(), "old_text": "two", "new_text": "dos" });
+    store.before_tool("edit_file", &edit).unwrap();
+    devman::tools::edit::execute(&edit).await.unwrap();
+
+    // Turn 2: a patch creating a file, and another edit
+    store.begin_turn("second change");
+    let patch = serde_json::json!({
+        "patch": "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+fresh\n",
+        "workdir": work.to_str().unwrap()
+    });
+    store.before_tool("apply_patch", &patch).unwrap();
+    devman::tools::patch::execute(&patch).await.unwrap();
+    let edit = serde_json::json!({ "path": a.to_str().unwrap(), "old_text": "dos", "new_text": "two" });
+    store.before_tool("edit_file", &edit).unwrap();
+    devman::tools::edit::execute(&edit).await.unwrap();
+
+    // Reopened from disk, the session has both turns
+    let mut store = CheckpointStore::open(dir.path().join("checkpoints"));
+    assert_eq!(store.turns().len(), 2);
+    assert_eq!(store.turns()[0].snapshots.len(), 1);
+    assert_eq!(store.turns()[1].request, "second change");
+    let changes = store.changes();
+    assert_eq!(changes.len(), 2);
+    assert_eq!((changes[0].kind, changes[0].turns.clone()), ('M', vec![1, 2]));
+    assert!(changes[0].diff.contains("-one\n+uno\n"), "{}", changes[0].diff);
+    assert_eq!(changes[1].kind, 'A');
+    assert!(store.report(10_000).contains("2 file(s) changed over 2 turn(s)"));
+
+    let done = store.undo(1).unwrap();
+    assert_eq!(done.len(), 2, "{done:?}");
+    assert!(!work.join("new.txt").exists());
+    assert_eq!(std::fs::read_to_string(&a).unwrap(), "uno\ndos\n");
+
+    store.undo(5).unwrap();
+    assert_eq!(std::fs::read_to_string(&a).unwrap(), "one\ntwo\n");
+    assert!(store.turns().is_empty());
+    assert!(store.undo(1).is_err());
+}
+
+#[test]
+fn test_repo_map() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src/tools")).unwrap();
+    std::fs::create_dir_all(root.join("web")).unwrap();
+    std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub struct Engine;\nenum Mode { A }\n\nimpl Engine {\n    pub fn run(&self) {}\n    fn helper() {}\n}\n\npub async fn start() {}\n\n#[cfg(test)]\nmod tests {\n    fn test_hidden() {}\n}\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/tools/mod.rs"), "pub trait Tool {}\n").unwrap();
+    std::fs::write(root.join("web/app.ts"), "export class App {}\nexport const VERSION = 1;\nfunction local() {}\n").unwrap();
+    std::fs::write(root.join("web/util.py"), "class Cache:\n    def get(self):\n        pass\n\ndef load():\n    pass\n").unwrap();
+
+    let map = devman::repomap::repo_map(root, 2000).unwrap();
+    assert!(map.contains("## Cargo.toml\n```\n[package]\nname = \"demo\""), "{map}");
+    assert!(map.contains("src/ (2 files)\n  tools/ (1 files)"), "{map}");
+    assert!(map.contains("src/lib.rs\n  struct Engine; enum Mode; fn run, start\n"), "{map}");
+    assert!(!map.contains("helper") && !map.contains("test_hidden"));
+    assert!(map.contains("web/app.ts\n  class App; const VERSION; function local"), "{map}");
+    assert!(map.contains("web/util.py\n  class Cache; def get, load"), "{map}");
+
+    // Cached until a file changes
+    assert_eq!(devman::repomap::repo_map(root, 2000).unwrap(), map);
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    std::fs::write(root.join("src/tools/mod.rs"), "pub trait Tool {}\npub struct Registry;\n").unwrap();
+    let updated = devman::repomap::repo_map(root, 2000).unwrap();
+    assert!(updated.contains("trait Tool; struct Registry"), "{updated}");
+
+    // A tight budget drops the files with the fewest symbols first
+    let small = devman::repomap::repo_map(root, 60).unwrap();
+    assert!(small.len() <= 60 * 4 + 100, "{}", small.len());
+    assert!(small.contains("more files not shown"), "{small}");
+}
+
+#[tokio::test]
+async fn test_apply_patch_git_diff() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    let body: String = (1..=20).map(|n| format!("line {n}\n")).collect();
+    // Two lines added at the top since the patch was made: hunks land at an offset
+    std::fs::write(root.join("main.txt"), format!("new 1\nnew 2\n{body}")).unwrap();
+    std::fs::write(root.join("old.txt"), "moving\n").unwrap();
+    std::fs::write(root.join("gone.txt"), "bye\n").unwrap();
+
+    let patch = "\
+diff --git a/main.txt b/main.txt
+index 1111111..2222222 100644
+--- a/main.txt
++++ b/main.txt
+@@ -3,3 +3,3 @@
+ line 3
+-line 4
++LINE FOUR
+ line 5
+@@ -18,3 +18,4 @@
+ line 18
+ line 19
+ line 20
++line 21
+diff --git a/new.txt b/new.txt
+new file mode 100755
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++#!/bin/sh
++echo hi
+diff --git a/old.txt b/renamed.txt
+similarity index 100%
+rename from old.txt
+rename to renamed.txt
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+    let input = serde_json::json!({ "patch": patch, "workdir": root, "dry_run": true });
+    let report = devman::tools::patch::execute(&input).await.unwrap();
+    assert!(report.contains("applies cleanly"), "{report}");
+    assert!(report.contains("at offset +2"), "{report}");
+    assert!(!root.join("new.txt").exists());
+
+    let input = serde_json::json!({ "patch": patch, "workdir": root });
+    devman::tools::patch::execute(&input).await.unwrap();
+    let main = std::fs::read_to_string(root.join("main.txt")).unwrap();
+    assert!(main.contains("line 3\nLINE FOUR\nline 5"));
+    assert!(main.ends_with("line 20\nline 21\n"));
+    assert_eq!(std::fs::read_to_string(root.join("new.txt")).unwrap(), "#!/bin/sh\necho hi\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(root.join("new.txt")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+    assert_eq!(std::fs::read_to_string(root.join("renamed.txt")).unwrap(), "moving\n");
+    assert!(!root.join("old.txt").exists());
+    assert!(!root.join("gone.txt").exists());
+}
+
+#[tokio::test]
+async fn test_apply_patch_fuzz_and_atomic_failure() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("a.rs"), "fn a() {\n    one();\n    two();\n}\n").unwrap();
+    std::fs::write(root.join("b.rs"), "fn b() {\n    three();\n}\n").unwrap();
+
+    // The first context line is stale: applies with fuzz 1, not with fuzz 0
+    let fuzzy = "\
+--- a/a.rs
++++ b/a.rs
+@@ -1,4 +1,4 @@
+ fn alpha() {
+     one();
+-    two();
++    TWO();
+ }
+";
+    let strict = serde_json::json!({ "patch": fuzzy, "workdir": root, "fuzz": 0 });
+    let err = devman::tools::patch::execute(&strict).await.unwrap_err().to_string();
+    assert!(err.contains("expected `fn alpha() {`, found `fn a() {`"), "{err}");
+    assert!(err.contains("    1 | fn a() {"), "{err}");
+    let input = serde_json::json!({ "patch": fuzzy, "workdir": root });
+    let report = devman::tools::patch::execute(&input).await.unwrap();
+    assert!(report.contains("fuzz 1"), "{report}");
+    assert!(std::fs::read_to_string(root.join("a.rs")).unwrap().contains("TWO();"));
+
+    // One good file, one bad: nothing is written
+    let mixed = "\
+--- a/a.rs
++++ b/a.rs
+@@ -2,1 +2,1 @@
+-    one();
++    ONE();
+--- a/b.rs
++++ b/b.rs
+@@ -2,1 +2,1 @@
+-    four();
++    FOUR();
+";
+    let input = serde_json::json!({ "patch": mixed, "workdir": root });
+    let err = devman::tools::patch::execute(&input).await.unwrap_err().to_string();
+    assert!(err.contains("no files were changed"), "{err}");
+    assert!(err.contains("b.rs: hunk 1"), "{err}");
+    assert!(err.contains("M a.rs"), "{err}");
+    assert!(std::fs::read_to_string(root.join("a.rs")).unwrap().contains("    one();"));
+
+    // -p0 for paths without a prefix
+    let p0 = "--- b.rs\n+++ b.rs\n@@ -2 +2 @@\n-    three();\n+    THREE();\n";
+    let input = serde_json::json!({ "patch": p0, "workdir": root, "strip": 0 });
+    devman::tools::patch::execute(&input).await.unwrap();
+    assert!(std::fs::read_to_string(root.join("b.rs")).unwrap().contains("THREE();"));
+}
+
+#[tokio::test]
+async fn test_apply_patch_paths_and_counts() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("sig.txt"), "body\n-- \nold sig\n").unwrap();
+
+    // A removed `-- ` line and an added `++ ` line inside the counts are body, not a header
+    let patch = "--- a/sig.txt\n+++ b/sig.txt\n@@ -1,3 +1,3 @@\n body\n--- \n+++ \n old sig\n";
+    let input = serde_json::json!({ "patch": patch, "workdir": root });
+    devman::tools::patch::execute(&input).await.unwrap();
+    assert_eq!(std::fs::read_to_string(root.join("sig.txt")).unwrap(), "body\n++ \nold sig\n");
+
+    // Nothing outside the working directory
+    for escape in [
+        "--- a/../outside.txt\n+++ b/../outside.txt\n@@ -0,0 +1 @@\n+x\n",
+        "--- /tmp/outside.txt\n+++ /tmp/outside.txt\n@@ -0,0 +1 @@\n+x\n",
+        "diff --git a/x b/y\nrename from x\nrename to ../y\n",
+    ] {
+        let strip = if escape.contains("/tmp/") { 0 } else { 1 };
+        let input = serde_json::json!({ "patch": escape, "workdir": root, "strip": strip });
+        let err = devman::tools::patch::execute(&input).await.unwrap_err().to_string();
+        assert!(err.contains("refusing to patch"), "{err}");
+    }
+    assert!(!root.parent().unwrap().join("outside.txt").exists());
+}
+
+// ───────────────────── Telegram ─────────────────────
+
+#[test]
+fn test_media_kind_for_path() {
+    use devman::telegram::api::MediaKind;
+    use std::path::Path;
+    assert_eq!(MediaKind::for_path(Path::new("chart.PNG")), MediaKind::Photo);
+    assert_eq!(MediaKind::for_path(Path::new("clip.ogg")), MediaKind::Voice);
+    assert_eq!(MediaKind::for_path(Path::new("report.pdf")), MediaKind::Document);
+}
+
+#[tokio::test]
+async fn test_send_file_outside_telegram() {
+    let dir = TempDir::new().unwrap();
+    let storage = devman::memory::TaskStorage::new(dir.path().to_path_buf(), false);
+    let input = serde_json::json!({ "path": "report.md" });
+    let err = devman::tools::send_file::execute(&input, Some(&storage), None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Telegram"));
+}
+
+#[test]
+fn test_parse_bot_command() {
+    use devman::telegram::commands::{is_known, parse};
+    let cmd = parse("/Model@devman_bot  complex ").unwrap();
+    assert_eq!(cmd.name, "model");
+    assert_eq!(cmd.bot, Some("devman_bot"));
+    assert_eq!(cmd.args, "complex");
+    assert!(is_known(&cmd.name));
+
+    assert_eq!(parse("/stop").unwrap().args, "");
+    assert!(!is_known(&parse("/deploy now").unwrap().name));
+    assert!(parse("no slash").is_none());
+    assert!(parse("/etc/hosts is broken").is_none());
+}
+
+#[test]
+fn test_group_mentions_and_replies() {
+    use devman::telegram::commands::strip_mention;
+    use devman::telegram::types::TgMessage;
+
+    let msg: TgMessage = serde_json::from_value(serde_json::json!({
+        "message_id": 7,
+        "date": 0,
+        "chat": { "id": -100, "type": "supergroup" },
+        "from": { "id": 1, "first_name": "Sam" },
+        "text": "hey @DevMan_bot can you check this?",
+        "message_thread_id": 3,
+        "is_topic_message": true,
+        "reply_to_message": {
+            "message_id": 3, "date": 0,
+            "chat": { "id": -100, "type": "supergroup" },
+            "from": { "id": 42, "is_bot": true, "first_name": "DevMan" }
+        }
+    }))
+    .unwrap();
+
+    assert!(msg.chat.is_group());
+    assert!(msg.mentions("devman_bot", 42));
+    assert!(!msg.mentions("devman", 42));
+    // The topic's first message isn't a real reply
+    assert!(msg.replied().is_none());
+    assert!(!msg.is_reply_to(42));
+
+    assert_eq!(strip_mention("hey @DevMan_bot can you check this?", "devman_bot"), "hey can you check this?");
+    assert_eq!(strip_mention("@devman_bot\n    indented", "devman_bot"), "indented");
+}
+
+fn question(origin: devman::ask::Origin, options: &[&str], allow_text: bool) -> devman::ask::Question {
+    let now = chrono::Utc::now();
+    devman::ask::Question {
+        id: devman::ask::new_id(),
+        origin,
+        question: "Which one?".into(),
+        options: options.iter().map(|o| o.to_string()).collect(),
+        allow_text,
+        default: None,
+        asked_at: now,
+        deadline: now + chrono::Duration::minutes(10),
+        answer: None,
+        run: None,
+    }
+}
+
+#[tokio::test]
+async fn test_ask_routes_answers() {
+    use devman::ask::{self, Origin};
+    let chat = |chat_id, thread_id| Origin::Telegram { bot: "manager".into(), chat_id, thread_id };
+
+    let q = question(chat(5, None), &["Yes", "No"], false);
+    let rx = ask::wait_for(&q);
+    // Typed replies don't count when only buttons are allowed
+    assert!(!ask::answer_text(&chat(5, None), "maybe"));
+    assert_eq!(ask::answer_callback(&format!("ask:{}:1", q.id)).as_deref(), Some("No"));
+    assert_eq!(rx.await.unwrap(), "No");
+    assert!(ask::answer_callback(&format!("ask:{}:0", q.id)).is_none());
+
+    let rx = ask::wait_for(&question(chat(6, Some(2)), &[], true));
+    assert!(!ask::answer_text(&chat(6, None), "wrong topic"));
+    assert!(!ask::answer_text(&Origin::Dashboard, "wrong surface"));
+    assert!(ask::answer_text(&chat(6, Some(2)), "blue"));
+    assert_eq!(rx.await.unwrap(), "blue");
+
+    let q = question(Origin::Cli, &["red", "green"], true);
+    assert_eq!(q.parse_answer("2\n"), "green");
+    assert_eq!(q.parse_answer(" 7 "), "7");
+}
+
+#[test]
+fn test_question_store_resumes_suspended_runs() {
+    use devman::ask::{Origin, QuestionStore, SuspendedRun};
+    use devman::types::Thinking;
+
+    let dir = TempDir::new().unwrap();
+    let store = QuestionStore::new(dir.path().to_path_buf());
+    let run = SuspendedRun {
+        run_id: "run-1".into(),
+        context_path: dir.path().join("conversation.json"),
+        tool_use_id: "toolu_1".into(),
+        model: "sonnet".into(),
+        system_prompt: "You are a sub-agent".into(),
+        thinking: Thinking::Off,
+        storage: None,
+        tools: Default::default(),
+        dry_run: false,
+        worktree: None,
+    };
+
+    // Answered after the asking process went away
+    let mut answered = question(Origin::Dashboard, &[], true);
+    answered.run = Some(run.clone());
+    store.save(&answered).unwrap();
+    // Timed out, falls back to its default
+    let mut expired = question(Origin::Dashboard, &[], true);
+    expired.run = Some(run);
+    expired.default = Some("skip it".into());
+    expired.deadline = expired.asked_at;
+    store.save(&expired).unwrap();
+    // Its asker died with the old process — nothing to resume
+    let orphan = question(Origin::Dashboard, &[], true);
+    store.save(&orphan).unwrap();
+
+    assert!(store.answer(&answered.id, "ship it"));
+    assert!(!store.answer(&answered.id, "again"));
+    assert!(!store.answer(&orphan.id, "too late"));
+    assert!(store.get("../escape").is_none());
+
+    store.discard_stale();
+    assert!(store.get(&orphan.id).is_none());
+
+    let ready = store.take_ready(chrono::Utc::now());
+    assert_eq!(ready.len(), 2);
+    let result = |id: &str| ready.iter().find(|(q, _)| q.id == id).map(|(_, r)| r.clone()).unwrap();
+    assert_eq!(result(&answered.id), "User answered: ship it");
+    assert!(result(&expired.id).contains("going with the default: skip it"));
+    assert!(store.list().is_empty());
+}
+
+// ───────────────────── Media ─────────────────────
+
+/// Minimal 1×1 PNG
+const TINY_PNG: &[u8] = &[
+    0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F, 0x15, 0xC4,
+    0x89, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x00, 0x01, 0x00, 0x00,
+    0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE,
+    0x42, 0x60, 0x82,
+];
+
+#[test]
+fn test_media_sniff_and_dimensions() {
+    use devman::media::{image_dimensions, MediaType};
+    assert_eq!(MediaType::sniff(TINY_PNG), Some(MediaType::Png));
+    assert_eq!(MediaType::sniff(b"%PDF-1.7\n"), Some(MediaType::Pdf));
+    assert_eq!(MediaType::sniff(b"hello"), None);
+    assert_eq!(image_dimensions(TINY_PNG), Some((1, 1)));
+}
+
+#[tokio::test]
+async fn test_read_file_returns_image_block() {
+    use devman::types::ToolResultContent;
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("pixel.dat"); // extension doesn't matter
+    std::fs::write(&path, TINY_PNG).unwrap();
+
+    let input = serde_json::json!({ "path": path.to_str().unwrap() });
+    match devman::tools::read::execute_content(&input).await.unwrap() {
+        ToolResultContent::Blocks(blocks) => {
+            assert!(matches!(blocks[1], ContentBlock::Image { .. }));
+        }
+        ToolResultContent::Text(t) => panic!("expected image block, got text: {t}"),
+    }
+}
+
+#[test]
+fn test_legacy_tool_result_deserializes() {
+    // Conversations saved before multi-block results stored plain strings
+    let json = r#"{"type":"tool_result","tool_use_id":"t1","content":"ok"}"#;
+    let block: ContentBlock = serde_json::from_str(json).unwrap();
+    match block {
+        ContentBlock::ToolResult { content, .. } => assert_eq!(content.text(), "ok"),
+        _ => panic!("expected tool_result"),
+    }
+}
+
+// ───────────────────── Voice ─────────────────────
+
+#[test]
+fn test_voice_engine_from_config() {
+    use devman::config::Config;
+    use devman::voice::VoiceEngine;
+
+    // No key and no commands — nothing to build
+    let config = Config::default();
+    assert!(VoiceEngine::from_config(&config, None).unwrap().is_none());
+
+    let config: Config = toml::from_str(
+        "[voice]\nstt = \"command\"\nstt_command = [\"whisper\", \"{input}\"]\ntts = \"off\"\n",
+    )
+    .unwrap();
+    let engine = VoiceEngine::from_config(&config, None).unwrap().unwrap();
+    assert!(engine.can_transcribe());
+    assert!(!engine.can_speak());
+
+    // A command backend without a command is a config error
+    let config: Config = toml::from_str("[voice]\ntts = \"command\"\n").unwrap();
+    assert!(VoiceEngine::from_config(&config, None).is_err());
+}
+
+// ───────────────────── Orchestrator ─────────────────────
+
+fn write_record(dir: &std::path::Path, run_id: &str, status: devman::orchestrator::SubAgentStatus) {
+    use devman::orchestrator::SubAgentRecord;
+    let record = SubAgentRecord {
+        run_id: run_id.into(),
+        task_id: "my-task".into(),
+        model: "sonnet".into(),
+        status,
+        started: chrono::Utc::now(),
+        output: None,
+        usage: Default::default(),
+        message: "Do the thing".into(),
+        system_prompt: String::new(),
+        thinking: Default::default(),
+        origin: None,
+        priority: Default::default(),
+        tools: Vec::new(),
+        policy: Default::default(),
+        storage: None,
+        decision_id: None,
+        verify_rounds: 0,
+        worktree: None,
+        step: None,
+    };
+    std::fs::create_dir_all(dir.join(run_id)).unwrap();
+    std::fs::write(dir.join(run_id).join("record.json"), serde_json::to_string(&record).unwrap()).unwrap();
+}
+
+#[test]
+fn test_orchestrator_recovery() {
+    use devman::config::Config;
+    use devman::orchestrator::{Orchestrator, SubAgentMessage, SubAgentStatus};
+
+    let dir = TempDir::new().unwrap();
+    write_record(dir.path(), "run-a", SubAgentStatus::Running);
+    write_record(dir.path(), "run-b", SubAgentStatus::Complete);
+
+    let mut config = Config::default();
+    assert_eq!(config.agents.recovery, "report");
+    let mut orch = Orchestrator::new(config.clone(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    let interrupted = orch.recover().unwrap();
+    assert_eq!(interrupted.len(), 1);
+    assert_eq!(interrupted[0].run_id, "run-a");
+    assert!(matches!(orch.try_recv(), Some(SubAgentMessage::Interrupted { run_id, .. }) if run_id == "run-a"));
+
+    // The new status is saved, so the next restart leaves it alone
+    let records = orch.load_records();
+    assert!(records.iter().any(|r| r.run_id == "run-a" && matches!(r.status, SubAgentStatus::Failed(_))));
+    let mut orch = Orchestrator::new(config.clone(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    assert!(orch.recover().unwrap().is_empty());
+
+    write_record(dir.path(), "run-c", SubAgentStatus::Running);
+    config.agents.recovery = "discard".into();
+    let mut orch = Orchestrator::new(config.clone(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    assert_eq!(orch.recover().unwrap().len(), 1);
+    assert!(orch.try_recv().is_none());
+    assert_eq!(orch.agents["run-c"].status, SubAgentStatus::Killed);
+
+    // More interrupted runs than the result channel holds are all reported
+    let dir = TempDir::new().unwrap();
+    for i in 0..40 {
+        write_record(dir.path(), &format!("run-{i:02}"), SubAgentStatus::Running);
+    }
+    config.agents.recovery = "report".into();
+    let mut orch = Orchestrator::new(config.clone(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    assert_eq!(orch.recover().unwrap().len(), 40);
+    assert_eq!(std::iter::from_fn(|| orch.try_recv()).count(), 40);
+
+    config.agents.recovery = "restart".into();
+    let mut orch = Orchestrator::new(config, "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    assert!(orch.recover().is_err());
+}
+
+#[tokio::test]
+async fn test_orchestrator_queues_by_priority() {
+    use devman::config::Config;
+    use devman::orchestrator::{Orchestrator, Priority, SpawnOptions, SubAgentStatus};
+
+    let dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.agents.max_concurrent = 1;
+    let mut orch = Orchestrator::new(config, "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+
+    // Fill the only slot by hand so nothing actually launches
+    write_record(dir.path(), "run-busy", SubAgentStatus::Running);
+    let busy = orch.load_records().remove(0);
+    orch.agents.insert(busy.run_id.clone(), busy);
+
+    let mut spawn = async |task: &str, priority| {
+        let opts = SpawnOptions { task_id: task.into(), message: "go".into(), priority, ..Default::default() };
+        orch.spawn("sonnet", opts).await.unwrap()
+    };
+    let background = spawn("bg-task", Priority::Background).await;
+    let cron = spawn("cron-task", Priority::Cron).await;
+    let first = spawn("urgent-1", Priority::Manager).await;
+    let second = spawn("urgent-2", Priority::Manager).await;
+
+    assert_eq!(orch.queue_position(&first), Some(1));
+    assert_eq!(orch.queue_position(&second), Some(2));
+    assert_eq!(orch.queue_position(&cron), Some(3));
+    assert_eq!(orch.queue_position(&background), Some(4));
+    assert_eq!(orch.queue_position("run-busy"), None);
+    assert_eq!(orch.agents[&cron].status, SubAgentStatus::Queued);
+    assert!(orch.describe_agents().contains("queued #3 (cron priority)"));
+
+    // Killing a queued agent moves everyone behind it up
+    orch.kill(&first).unwrap();
+    assert_eq!(orch.queue_position(&second), Some(1));
+    let info = orch.agent_infos().into_iter().find(|a| a.run_id == background).unwrap();
+    assert_eq!(info.queue_position, Some(3));
+    assert_eq!(info.priority, "background");
+}
+
+#[tokio::test]
+async fn test_orchestrator_run_ids_and_task_limits() {
+    use devman::config::Config;
+    use devman::orchestrator::{Orchestrator, SpawnOptions, SubAgentStatus};
+
+    let dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.agents.max_concurrent = 2;
+    config.agents.max_per_task = 1;
+    let mut orch = Orchestrator::new(config, "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+
+    // A running workflow step holds its workflow's task
+    let task = "ünïcödé/redesign wïth a löng nàme";
+    write_record(dir.path(), "run-step", SubAgentStatus::Running);
+    let mut step = orch.load_records().remove(0);
+    step.task_id = format!("{task}:build");
+    step.step = Some("build".into());
+    assert_eq!(step.task(), task);
+    orch.agents.insert(step.run_id.clone(), step);
+
+    // So another agent on that task waits despite the free slot. Two spawns in
+    // the same second, on a multi-byte name, still get distinct, path-safe ids
+    let opts = SpawnOptions { task_id: task.into(), message: "go".into(), ..Default::default() };
+    let first = orch.spawn("sonnet", opts.clone()).await.unwrap();
+    let second = orch.spawn("sonnet", opts).await.unwrap();
+    assert_ne!(first, second);
+    assert_eq!(orch.agents[&first].status, SubAgentStatus::Queued);
+    assert_eq!(orch.queue_position(&second), Some(2));
+    assert!(!first.contains('/') && !first.contains(' '), "{first}");
+    assert!(dir.path().join(&first).is_dir() && dir.path().join(&second).is_dir());
+}
+
+#[tokio::test]
+async fn test_workflow_steps_share_their_task_and_die_when_killed() {
+    use devman::config::Config;
+    use devman::orchestrator::{Orchestrator, SubAgentStatus};
+    use devman::tools::policy::ToolPolicy;
+
+    // The default one-agent-per-task limit doesn't serialise a workflow's independent steps.
+    // Nothing is awaited, so the launched loops never get to run.
+    let dir = TempDir::new().unwrap();
+    let config = Config::default();
+    assert_eq!(config.agents.max_per_task, 1);
+    let mut orch = Orchestrator::new(config, "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    let plan = serde_json::json!({
+        "name": "site",
+        "task": "site",
+        "steps": [
+            { "id": "copy", "instructions": "Write the copy" },
+            { "id": "images", "instructions": "Pick the images" },
+            { "id": "publish", "instructions": "Publish it", "depends_on": ["copy", "images"] }
+        ]
+    });
+    orch.plan_workflow(&plan, None, ToolPolicy::default()).unwrap();
+    let step = |orch: &Orchestrator, id: &str| orch.agents.values().find(|a| a.step.as_deref() == Some(id)).cloned();
+    assert_eq!(step(&orch, "copy").unwrap().status, SubAgentStatus::Running);
+    assert_eq!(step(&orch, "images").unwrap().status, SubAgentStatus::Running);
+    assert!(step(&orch, "publish").is_none());
+
+    // Another run on the workflow's task still waits its turn
+    let opts = devman::orchestrator::SpawnOptions { task_id: "site".into(), message: "go".into(), ..Default::default() };
+    let other = orch.spawn("sonnet", opts).await.unwrap();
+    assert_eq!(orch.queue_position(&other), Some(1));
+
+    // A killed step fails without a retry, and what depends on it is skipped
+    let copy = step(&orch, "copy").unwrap().run_id;
+    orch.kill(&copy).unwrap();
+    assert_eq!(orch.agents[&copy].status, SubAgentStatus::Killed);
+    let summary = orch.workflows()[0].summary();
+    assert!(summary.contains("copy") && summary.contains("Killed"), "{summary}");
+    assert_eq!(orch.agents.values().filter(|a| a.step.as_deref() == Some("copy")).count(), 1);
+    assert_eq!(step(&orch, "images").unwrap().status, SubAgentStatus::Running);
+    assert!(orch.kill("run-missing").is_err());
+}
+
+#[tokio::test]
+async fn test_sub_agent_resumes_through_the_pool() {
+    use devman::ask::SuspendedRun;
+    use devman::config::Config;
+    use devman::orchestrator::{Orchestrator, SubAgentStatus};
+
+    // A run the last process left waiting in ask_user goes back through the
+    // pool once answered, so its result is collected like any other run's
+    let dir = TempDir::new().unwrap();
+    write_record(dir.path(), "run-asking", SubAgentStatus::WaitingForUser);
+    let mut orch = Orchestrator::new(Config::default(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    for record in orch.load_records() {
+        orch.agents.insert(record.run_id.clone(), record);
+    }
+    let suspended = |run_id: &str| SuspendedRun {
+        run_id: run_id.into(),
+        context_path: dir.path().join(run_id).join("conversation.json"),
+        tool_use_id: "toolu_1".into(),
+        model: "sonnet".into(),
+        system_prompt: String::new(),
+        thinking: Default::default(),
+        storage: None,
+        tools: Default::default(),
+        dry_run: false,
+        worktree: None,
+    };
+    assert!(orch.resume_answered(&suspended("run-asking"), "The user answered: yes"));
+    assert_eq!(orch.agents["run-asking"].status, SubAgentStatus::Running);
+    assert_eq!(orch.list_active().len(), 1);
+    // Cron runs and chats aren't the pool's to resume
+    assert!(!orch.resume_answered(&suspended("cron-nightly"), "yes"));
+}
+
+#[test]
+fn test_inbox_steering_and_messages() {
+    use devman::config::Config;
+    use devman::inbox::Inbox;
+    use devman::orchestrator::{Orchestrator, SubAgentStatus};
+
+    let dir = TempDir::new().unwrap();
+    write_record(dir.path(), "run-a", SubAgentStatus::Running);
+    write_record(dir.path(), "run-b", SubAgentStatus::Running);
+    write_record(dir.path(), "run-done", SubAgentStatus::Complete);
+    let mut orch = Orchestrator::new(Config::default(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    for record in orch.load_records() {
+        orch.agents.insert(record.run_id.clone(), record);
+    }
+
+    orch.steer("run-a", "Use the staging database").unwrap();
+    assert!(orch.steer("run-done", "too late").is_err());
+    assert!(orch.steer("run-missing", "hello").is_err());
+
+    // By task id: every active agent on it except the sender
+    let a = Inbox::new(dir.path().to_path_buf(), "run-a");
+    assert_eq!(a.resolve("my-task"), vec!["run-b"]);
+    assert_eq!(a.resolve("run-b"), vec!["run-b"]);
+    assert!(a.resolve("run-done").is_empty());
+    assert!(a.resolve("run-a").is_empty());
+    a.send_to("run-b", "schema is in storage: schema.sql").unwrap();
+
+    let mail = a.drain();
+    assert_eq!(mail.len(), 1);
+    assert_eq!((mail[0].from.as_str(), mail[0].text.as_str()), ("manager", "Use the staging database"));
+    assert!(!a.has_mail());
+    assert!(a.drain().is_empty());
+
+    let b = Inbox::new(dir.path().to_path_buf(), "run-b");
+    assert_eq!(b.drain()[0].from, "run-a");
+
+    assert_eq!(orch.tier_for_model(&Config::default().models.quick), Some(devman::orchestrator::TaskComplexity::Quick));
+    assert_eq!(orch.tier_for_model("some-other-model"), None);
+}
+
+#[tokio::test]
+async fn test_manager_tools_drive_the_orchestrator() {
+    use devman::ask::Origin;
+    use devman::config::Config;
+    use devman::inbox::Inbox;
+    use devman::manager::Manager;
+    use devman::orchestrator::{Orchestrator, Priority, SubAgentRecord, SubAgentStatus};
+    use devman::tools::policy::ToolPolicy;
+    use serde_json::json;
+
+    // One slot, already taken — whatever the tools spawn stays queued
+    let dir = TempDir::new().unwrap();
+    write_record(dir.path(), "run-busy", SubAgentStatus::Running);
+    let mut config = Config::default();
+    config.agents.max_concurrent = 1;
+    let mut orch = Orchestrator::new(config, "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    for record in orch.load_records() {
+        orch.agents.insert(record.run_id.clone(), record);
+    }
+    let manager = Manager::with_orchestrator(orch);
+
+    // The manager's loop is offered the tools, and runs them rather than calling them unknown
+    let agent = devman::agent::AgentLoop::new(
+        devman::client::AnthropicClient::new("test".into()),
+        devman::context::ContextManager::new(),
+        "model".into(),
+        String::new(),
+        devman::tools::builtin_tool_definitions(false, false),
+        1,
+        1024,
+        devman::types::Thinking::Off,
+        None,
+        None,
+    )
+    .with_manager(manager.clone());
+    let names: Vec<&str> = agent.tool_definitions().iter().map(|t| t.name.as_str()).collect();
+    for tool in ["spawn_agent", "plan_workflow", "list_agents", "kill_agent", "steer_agent", "correct_tier", "assign_bot"] {
+        assert!(names.contains(&tool), "{tool} missing");
+    }
+    assert!(Manager::handles("steer_agent") && !Manager::handles("assign_bot"));
+
+    // spawn_agent: queued, reporting back to its origin, and never with more tools than its caller
+    let caller = ToolPolicy { deny: vec!["git_push".into()], ..Default::default() };
+    let origin = Origin::Cli;
+    let input = json!({
+        "task_id": "docs",
+        "message": "Tidy the README",
+        "model_tier": "quick",
+        "tools": { "deny": ["web_*"], "read_only": true }
+    });
+    let spawned = manager.execute("spawn_agent", &input, Some(&origin), &caller).await.unwrap();
+    assert!(spawned.starts_with("Queued run-"), "{spawned}");
+    let run_id = spawned.split_whitespace().nth(1).unwrap().to_string();
+    let record: SubAgentRecord =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join(&run_id).join("record.json")).unwrap()).unwrap();
+    assert_eq!(record.model, Config::default().models.quick);
+    assert_eq!(record.priority, Priority::Manager);
+    assert_eq!(record.origin, Some(Origin::Cli));
+    assert_eq!(record.policy.deny, vec!["git_push", "web_*"]);
+    assert!(record.policy.read_only);
+    assert!(manager.execute("spawn_agent", &json!({ "task_id": "docs" }), None, &caller).await.is_err());
+
+    // Without a task, each run gets one of its own rather than queueing behind a shared one
+    let untagged = json!({ "message": "Look into the flaky test", "model_tier": "quick" });
+    let a = manager.execute("spawn_agent", &untagged, None, &caller).await.unwrap();
+    let b = manager.execute("spawn_agent", &untagged, None, &caller).await.unwrap();
+    let infos = manager.agent_infos().await;
+    let task_of = |out: &str| {
+        let id = out.split_whitespace().nth(1).unwrap();
+        infos.iter().find(|i| i.run_id == id).unwrap().task_id.clone()
+    };
+    assert!(task_of(&a).starts_with("adhoc-"), "{}", task_of(&a));
+    assert_ne!(task_of(&a), task_of(&b));
+    for out in [&a, &b] {
+        let id = out.split_whitespace().nth(1).unwrap();
+        manager.execute("kill_agent", &json!({ "run_id": id }), None, &caller).await.unwrap();
+    }
+
+    let listed = manager.execute("list_agents", &json!({}), None, &caller).await.unwrap();
+    assert!(listed.contains("run-busy") && listed.contains(&run_id) && listed.contains("queued #1"), "{listed}");
+
+    // steer_agent lands in the inbox the running loop reads between API calls
+    let steer = json!({ "run_id": "run-busy", "message": "Use the staging database" });
+    manager.execute("steer_agent", &steer, None, &caller).await.unwrap();
+    let mail = Inbox::new(dir.path().to_path_buf(), "run-busy").drain();
+    assert_eq!(mail.len(), 1);
+    assert_eq!((mail[0].from.as_str(), mail[0].text.as_str()), ("manager", "Use the staging database"));
+
+    // correct_tier only applies to runs triage routed
+    let correct = json!({ "run_id": run_id, "model_tier": "complex" });
+    assert!(manager.execute("correct_tier", &correct, None, &caller).await.is_err());
+
+    manager.execute("kill_agent", &json!({ "run_id": run_id }), None, &caller).await.unwrap();
+    let listed = manager.execute("list_agents", &json!({}), None, &caller).await.unwrap();
+    assert!(!listed.contains(&run_id), "{listed}");
+
+    // plan_workflow: steps run under the caller's policy and report to its origin
+    let plan = json!({
+        "name": "release",
+        "task": "release",
+        "steps": [{ "id": "notes", "instructions": "Write the release notes" }]
+    });
+    let planned = manager.execute("plan_workflow", &plan, Some(&origin), &caller).await.unwrap();
+    assert!(planned.contains("notes"), "{planned}");
+    let infos = manager.agent_infos().await;
+    let step = infos.iter().find(|a| a.task_id == "release:notes").unwrap();
+    assert_eq!(step.queue_position, Some(1));
+    let record: SubAgentRecord =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join(&step.run_id).join("record.json")).unwrap()).unwrap();
+    assert_eq!(record.policy, caller);
+    assert_eq!(record.origin, Some(Origin::Cli));
+
+    assert!(manager.execute("spawn_agent", &json!({}), None, &caller).await.is_err());
+    assert!(manager.poll().await.is_empty());
+
+    // Runs the last process left behind are reported on the next poll
+    let dir = TempDir::new().unwrap();
+    write_record(dir.path(), "run-lost", SubAgentStatus::Running);
+    let orch = Orchestrator::new(Config::default(), "key".into(), None, None).with_state_dir(dir.path().to_path_buf());
+    let manager = Manager::with_orchestrator(orch);
+    assert_eq!(manager.recover().await.unwrap().len(), 1);
+    let reports = manager.poll().await;
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].text.contains("run-lost") && reports[0].text.contains("interrupted"), "{}", reports[0].text);
+}
+
+#[tokio::test]
+async fn test_verification_config_and_verdicts() {
+    use devman::config::{Config, ScopedBotConfig, TelegramConfig, VerifyConfig};
+    use devman::orchestrator::TaskComplexity;
+    use devman::verify::{self, TestSpec, Verdict};
+
+    let config = VerifyConfig { tiers: vec!["complex".into()], tasks: vec!["billing".into()], ..Default::default() };
+    assert!(verify::applies(&config, "other", Some(TaskComplexity::Complex)));
+    assert!(verify::applies(&config, "billing", Some(TaskComplexity::Quick)));
+    assert!(!verify::applies(&config, "other", Some(TaskComplexity::Standard)));
+    assert!(!verify::applies(&config, "other", None));
+    assert!(!verify::applies(&VerifyConfig::default(), "billing", Some(TaskComplexity::Complex)));
+
+    // The test command comes from the dev bot covering the task
+    let dir = TempDir::new().unwrap();
+    let bot: ScopedBotConfig = toml::from_str(&format!(
+        r#"
+        name = "dev"
+        bot_token = "1:A"
+        allowed_users = []
+        tasks = ["billing"]
+        bot_type = "dev"
+        working_directory = "{}"
+        test_command = "echo 3 passed, 1 failed; exit 1"
+        "#,
+        dir.path().display()
+    ))
+    .unwrap();
+    let mut full = Config::default();
+    full.telegram = Some(TelegramConfig {
+        bot_token: None,
+        allowed_users: Vec::new(),
+        bots: vec![bot],
+        voice_reply: "off".into(),
+        allowed_groups: Vec::new(),
+        group_trigger: "mention".into(),
+    });
+    assert!(verify::test_spec(&full, "other").is_none());
+    let spec = verify::test_spec(&full, "billing").unwrap();
+    assert_eq!(spec.dir, dir.path());
+    let tests = verify::run_tests(&spec, 10).await;
+    assert!(!tests.passed);
+    assert!(tests.output.contains("1 failed"));
+    let timed_out = verify::run_tests(&TestSpec { command: "sleep 5".into(), dir: dir.path().into() }, 1).await;
+    assert!(!timed_out.passed);
+
+    assert_eq!(Verdict::parse("All good.\nVERDICT: APPROVE"), (Some(true), "All good.".to_string()));
+    assert_eq!(
+        Verdict::parse("Missing the migration.\n\n**Verdict: revise**"),
+        (Some(false), "Missing the migration.".to_string())
+    );
+    assert_eq!(Verdict::parse("Looks fine").0, None);
+    assert_eq!(Verdict::parse("Not done yet.\nVERDICT: DO NOT APPROVE").0, Some(false));
+    assert_eq!(Verdict::parse("I can't approve this.\n`VERDICT: REVISE — do not approve`").0, Some(false));
+    assert_eq!(Verdict::parse("Fine.\n## **VERDICT: APPROVE.**").0, Some(true));
+
+    // Verdicts pile up in the run directory
+    let verdict = Verdict {
+        round: 1,
+        approved: false,
+        feedback: "Missing the migration.".into(),
+        tests: Some(tests),
+        model: "opus".into(),
+        usage: Default::default(),
+        timestamp: chrono::Utc::now(),
+    };
+    verdict.record(dir.path()).unwrap();
+    verdict.record(dir.path()).unwrap();
+    assert_eq!(Verdict::list(dir.path()).len(), 2);
+    let request = verdict.revision_request();
+    assert!(request.contains("Missing the migration."));
+    assert!(request.contains("1 failed"));
+}
+
+#[test]
+fn test_worktree_isolation() {
+    use devman::worktree::{Worktree, WorktreeMode};
+    use std::process::Command;
+
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let out = Command::new("git").args(args).current_dir(dir).output().unwrap();
+        assert!(out.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    };
+    let dir = TempDir::new().unwrap();
+    let repo = dir.path().join("repo");
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(&repo, &["config", "user.email", "dev@example.com"]);
+    git(&repo, &["config", "user.name", "Dev"]);
+    std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-q", "-m", "init"]);
+    let repo = std::fs::canonicalize(&repo).unwrap();
+
+    let root = dir.path().join("worktrees");
+    let wt = Worktree::create(&repo, &root, "run-1").unwrap();
+    assert_eq!(wt.branch, "devman/run-1");
+    assert_eq!(wt.base_branch, "main");
+    assert!(wt.path.join("README.md").exists());
+
+    // Tool paths and working directories land in the worktree
+    let input = wt.root_input("write_file", &serde_json::json!({"path": repo.join("src/lib.rs"), "content": "x"}));
+    assert_eq!(input["path"], wt.path.join("src/lib.rs").to_string_lossy().as_ref());
+    let input = wt.root_input("read_file", &serde_json::json!({"path": "/etc/hosts"}));
+    assert_eq!(input["path"], "/etc/hosts");
+    let input = wt.root_input("shell", &serde_json::json!({"command": "ls"}));
+    assert_eq!(input["workdir"], wt.path.to_string_lossy().as_ref());
+    let input = wt.root_input("git_commit", &serde_json::json!({"message": "m"}));
+    assert_eq!(input["path"], wt.path.to_string_lossy().as_ref());
+
+    // Work in the worktree doesn't touch the checkout until it's merged
+    assert_eq!(wt.commits_ahead().unwrap(), 0);
+    std::fs::write(wt.path.join("new.txt"), "from the agent\n").unwrap();
+    assert!(wt.commit_all("agent work").unwrap());
+    assert!(!wt.commit_all("nothing").unwrap());
+    assert_eq!(wt.commits_ahead().unwrap(), 1);
+    assert!(!repo.join("new.txt").exists());
+
+    // Never into uncommitted work on the base branch
+    std::fs::write(repo.join("README.md"), "edited by hand\n").unwrap();
+    let err = wt.merge().unwrap_err().to_string();
+    assert!(err.contains("uncommitted changes on main"), "{err}");
+    git(&repo, &["checkout", "-q", "--", "README.md"]);
+
+    wt.merge().unwrap();
+    assert!(repo.join("new.txt").exists());
+    wt.remove(true).unwrap();
+    assert!(!wt.path.exists());
+    assert!(git(&repo, &["branch", "--list", "devman/*"]).is_empty());
+
+    // If the checkout has moved to another branch, that branch is left alone
+    let wt = Worktree::create(&repo, &root, "run-2").unwrap();
+    git(&repo, &["checkout", "-q", "-b", "feature"]);
+    std::fs::write(wt.path.join("second.txt"), "more\n").unwrap();
+    wt.commit_all("more agent work").unwrap();
+    wt.merge().unwrap();
+    assert_eq!(git(&repo, &["rev-parse", "--abbrev-ref", "HEAD"]), "feature");
+    assert!(!repo.join("second.txt").exists());
+    assert_eq!(git(&repo, &["show", "main:second.txt"]), "more");
+    wt.remove(true).unwrap();
+
+    assert_eq!(WorktreeMode::parse("pr").unwrap(), WorktreeMode::Pr);
+    assert!(WorktreeMode::parse("yes").is_err());
+}
+
+// ───────────────────── Workflows ─────────────────────
+
+const WORKFLOW: &str = r#"
+name = "Release notes"
+
+[[steps]]
+id = "commits"
+instructions = "Summarise the commits since the last tag"
+tier = "quick"
+outputs = ["commits.md"]
+
+[[steps]]
+id = "issues"
+instructions = "List the issues closed since the last tag"
+tier = "quick"
+outputs = ["issues.md"]
+
+[[steps]]
+id = "draft"
+instructions = "Write release notes"
+depends_on = ["commits", "issues"]
+inputs = ["commits.md", "issues.md"]
+outputs = ["notes.md"]
+tools = ["read_file"]
+retries = 0
+"#;
+
+#[test]
+fn test_workflow_validation() {
+    use devman::workflow::Workflow;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("release.toml");
+    std::fs::write(&path, WORKFLOW).unwrap();
+    let workflow = Workflow::load(&path).unwrap();
+    let order: Vec<&str> = workflow.order().unwrap().into_iter().map(|i| workflow.steps[i].id.as_str()).collect();
+    assert_eq!(order.last(), Some(&"draft"));
+    // Storage tools come with declared inputs/outputs
+    assert!(workflow.steps[2].allowed_tools().contains(&"storage_read".to_string()));
+    assert!(workflow.steps[0].allowed_tools().is_empty());
+
+    let mut cyclic = workflow.clone();
+    cyclic.steps[0].depends_on = vec!["draft".into()];
+    assert!(cyclic.validate().unwrap_err().to_string().contains("cycle"));
+
+    let mut unordered = workflow.clone();
+    unordered.steps[2].depends_on = vec!["commits".into()];
+    assert!(unordered.validate().unwrap_err().to_string().contains("doesn't depend on it"));
+
+    let mut clash = workflow;
+    clash.steps[1].outputs = vec!["commits.md".into()];
+    assert!(clash.validate().is_err());
+}
+
+#[test]
+fn test_workflow_run_retries_and_escalates() {
+    use devman::orchestrator::TaskComplexity;
+    use devman::workflow::{StepOutcome, StepStatus, Workflow, WorkflowRun};
+
+    let workflow: Workflow = toml::from_str(WORKFLOW).unwrap();
+    let mut run = WorkflowRun::new(workflow);
+    // Both independent steps can start straight away
+    assert_eq!(run.ready(), vec![0, 1]);
+    run.step_started(0, "r0");
+    run.step_started(1, "r1");
+    assert!(run.ready().is_empty());
+
+    assert_eq!(run.step_finished(0, Ok(())), StepOutcome::Done);
+    // One retry on the same tier, then up a tier
+    assert_eq!(run.step_for_run("r1"), Some(1));
+    assert_eq!(run.step_finished(1, Err("boom".into())), StepOutcome::Retry);
+    run.step_started(1, "r1b");
+    assert_eq!(run.step_finished(1, Err("boom".into())), StepOutcome::Escalated(TaskComplexity::Standard));
+    assert_eq!(run.ready(), vec![1]);
+    run.step_started(1, "r1c");
+    assert_eq!(run.step_finished(1, Ok(())), StepOutcome::Done);
+
+    assert_eq!(run.ready(), vec![2]);
+    run.step_started(2, "r2");
+    // No retries, but it can still move up from standard to complex once
+    assert!(matches!(run.step_finished(2, Err("nope".into())), StepOutcome::Escalated(TaskComplexity::Complex)));
+    run.step_started(2, "r2b");
+    assert_eq!(run.step_finished(2, Err("nope".into())), StepOutcome::Failed);
+    assert!(run.is_finished());
+    assert!(!run.succeeded());
+    assert_eq!(run.steps[2].status, StepStatus::Failed("nope".into()));
+    assert!(run.summary().contains("draft: ❌ failed: nope"));
+}
+
+// ───────────────────── Triage ─────────────────────
+
+#[test]
+fn test_triage_parse() {
+    use devman::orchestrator::TaskComplexity;
+    use devman::triage::{Effort, Triage};
+
+    let known = vec!["billing".to_string()];
+    let reply = r#"Sure: {"tier": "quick", "effort": "small", "tasks": ["billing", "made-up"], "memory": ["a", "b", "c", "d"], "reason": "a lookup"}"#;
+    let triage = Triage::parse(reply, &known).unwrap();
+    assert_eq!(triage.tier, TaskComplexity::Quick);
+    assert_eq!(triage.effort, Effort::Small);
+    assert_eq!(triage.tasks, vec!["billing"]);
+    assert_eq!(triage.memory.len(), 3);
+
+    // Only the tier is required
+    let triage = Triage::parse(r#"{"tier": "complex"}"#, &known).unwrap();
+    assert_eq!(triage.tier, TaskComplexity::Complex);
+    assert_eq!(triage.effort, Effort::Medium);
+
+    assert!(Triage::parse("no idea", &known).is_err());
+    assert!(Triage::parse(r#"{"tier": "huge"}"#, &known).is_err());
+}
+
+#[test]
+fn test_routing_stats() {
+    use devman::improve::ImprovementEngine;
+    use devman::orchestrator::TaskComplexity;
+    use devman::triage::Triage;
+
+    let dir = TempDir::new().unwrap();
+    let mut engine = ImprovementEngine::load(dir.path()).unwrap();
+    assert_eq!(engine.routing_summary(), "No routed runs yet.");
+
+    let mut quick = Triage::fallback("lookup");
+    quick.tier = TaskComplexity::Quick;
+    let ok = engine.record_triage("what's the status", &quick, "haiku");
+    let wrong = engine.record_triage("show me why prod is down", &quick, "haiku");
+    engine.record_triage("still running", &Triage::fallback("code"), "sonnet");
+    engine.record_outcome(&ok, 0.01, true);
+    engine.record_outcome(&wrong, 0.02, true);
+    engine.record_correction(&wrong, TaskComplexity::Complex);
+    engine.save().unwrap();
+
+    // Unfinished decisions don't count towards accuracy
+    let engine = ImprovementEngine::load(dir.path()).unwrap();
+    assert_eq!(engine.get_stats().routing.len(), 3);
+    let summary = engine.routing_summary();
+    assert!(summary.contains("Quick: 2 runs, 2 succeeded, 1 corrected"), "{summary}");
+    assert!(summary.contains("Routing accuracy: 50% (1/2)"), "{summary}");
+    assert!(engine.generate_retrospective().contains("## Routing (3 decisions)"));
+}
+
+#[tokio::test]
+async fn test_sandbox() {
+    use devman::config::SandboxConfig;
+    use devman::sandbox::Sandbox;
+    use devman::tools::shell;
+    // Needs unprivileged user namespaces, which some CI runners don't allow
+    let userns = std::process::Command::new("unshare").args(["--user", "--map-root-user", "true"]).status();
+    if !userns.is_ok_and(|s| s.success()) {
+        return;
+    }
+    let work = TempDir::new().unwrap();
+    let hidden = TempDir::new().unwrap();
+    let outside = TempDir::new_in(env!("CARGO_TARGET_TMPDIR")).unwrap();
+    std::fs::write(outside.path().join("keep.txt"), "original").unwrap();
+    let config = SandboxConfig { backend: "unshare".into(), ..Default::default() };
+    let sandbox = Sandbox::new(&config, [work.path().to_path_buf()]).unwrap();
+    let sandbox = &sandbox;
+    let run = |command: String| async move { shell::execute(&serde_json::json!({ "command": command }), Some(sandbox)).await.unwrap() };
+
+    // Starts in the writable directory, which it can change
+    let out = run("pwd && echo hi > made.txt && cat made.txt".into()).await;
+    assert!(out.ends_with(&format!("{}\nhi\n", work.path().canonicalize().unwrap().display())), "{out}");
+    assert!(work.path().join("made.txt").exists());
+
+    // Everything else is read-only, /tmp is private and there's no network
+    let keep = outside.path().join("keep.txt");
+    let out = run(format!("echo changed > {}", keep.display())).await;
+    assert!(out.contains("Read-only file system"), "{out}");
+    assert_eq!(std::fs::read_to_string(&keep).unwrap(), "original");
+    let out = run(format!("ls {}", hidden.path().display())).await;
+    assert!(out.contains("No such file"), "{out}");
+    let out = run("tail -n +3 /proc/net/dev | cut -d: -f1".into()).await;
+    assert_eq!(out.trim(), "lo");
+
+    // Limits apply inside
+    let out = run("ulimit -t; ulimit -v".into()).await;
+    assert_eq!(out, "600\n2097152\n");
+
+    // Sessions start inside it too, and a timeout still only stops the command
+    let session = devman::shell_session::ShellSession::new();
+    let input = |command: &str| serde_json::json!({ "command": command, "timeout": 1 });
+    shell::execute_in_session(&input("export N=1; sleep 30"), &session, Some(sandbox)).await.unwrap();
+    let out = shell::execute_in_session(&input("echo $N; touch /etc/x"), &session, Some(sandbox)).await.unwrap();
+    assert!(out.starts_with("1\n") && out.contains("Read-only file system"), "{out}");
+
+    // Custom tools run inside it; claude_code, which can't, isn't offered
+    let custom = devman::tools::custom::CustomTool {
+        name: "netcheck".into(),
+        description: "Lists network interfaces".into(),
+        command: vec!["sh".into(), "-c".into(), "cat > /dev/null; tail -n +3 /proc/net/dev | cut -d: -f1".into()],
+        input_schema: serde_json::json!({ "type": "object" }),
+        timeout_secs: 10,
+    };
+    assert_eq!(custom.execute(&serde_json::json!({}), Some(sandbox)).await.unwrap().trim(), "lo");
+    let agent = devman::agent::AgentLoop::new(
+        devman::client::AnthropicClient::new("test".into()),
+        devman::context::ContextManager::new(),
+        "model".into(),
+        String::new(),
+        devman::tools::builtin_tool_definitions(false, false),
+        1,
+        1024,
+        devman::types::Thinking::Off,
+        None,
+        None,
+    )
+    .with_custom_tools(vec![custom])
+    .with_sandbox(sandbox.clone());
+    let names: Vec<&str> = agent.tool_definitions().iter().map(|t| t.name.as_str()).collect();
+    assert!(names.contains(&"netcheck") && names.contains(&"shell") && !names.contains(&"claude_code"), "{names:?}");
+}
+
+#[test]
+fn test_tool_policy() {
+    use devman::config::ScopedBotConfig;
+    use devman::tools::policy::ToolPolicy;
+
+    let bot: ScopedBotConfig = toml::from_str(
+        r#"
+        name = "marketing"
+        bot_token = "1:A"
+        allowed_users = []
+        tasks = ["launch"]
+
+        [tools]
+        allow = ["git_*", "read_file", "storage_*"]
+        deny = ["git_push", "storage_delete"]
+        "#,
+    )
+    .unwrap();
+    let policy = &bot.tools;
+    assert!(policy.permits("git_log") && policy.permits("read_file") && policy.permits("storage_write"));
+    assert!(!policy.permits("git_push") && !policy.permits("storage_delete") && !policy.permits("shell"));
+
+    let mut defs = devman::tools::builtin_tool_definitions(true, true);
+    policy.filter(&mut defs);
+    let names: Vec<&str> = defs.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["read_file", "git_status", "git_diff", "git_commit", "git_log", "git_branch", "storage_write", "storage_read", "storage_list"]
+    );
+
+    // read_only keeps the looking tools only, and stacks with allow/deny
+    let read_only = ToolPolicy { read_only: true, ..Default::default() };
+    let mut defs = devman::tools::builtin_tool_definitions(true, true);
+    read_only.filter(&mut defs);
+    for mutating in ["shell", "write_file", "edit_file", "apply_patch", "git_commit", "git_push", "github_pr_create", "memory_write", "storage_delete", "claude_code", "process_start", "send_file", "tts", "deep_research"] {
+        assert!(!defs.iter().any(|d| d.name == mutating), "{mutating}");
+    }
+    assert!(defs.iter().any(|d| d.name == "grep") && defs.iter().any(|d| d.name == "git_diff"));
+    let both = ToolPolicy { read_only: true, ..policy.clone() };
+    assert!(both.permits("git_log") && !both.permits("git_commit") && !both.permits("storage_write"));
+
+    // A sub-agent's allowlist folds into the policy it runs (and resumes) under
+    let narrowed = policy.within(&["git_log".into(), "git_push".into(), "shell".into()]);
+    assert!(narrowed.permits("git_log") && !narrowed.permits("git_push") && !narrowed.permits("git_diff"));
+    assert_eq!(narrowed.within(&[]), narrowed);
+    let none = policy.within(&["shell".into()]);
+    assert!(!none.permits("shell") && !none.permits("git_log"));
+
+    // Bots without a [tools] table get everything
+    let bot: ScopedBotConfig = toml::from_str("name = \"b\"\nbot_token = \"1:A\"\nallowed_users = []\ntasks = []\n").unwrap();
+    assert!(bot.tools.is_unrestricted() && bot.tools.permits("shell"));
+}
+
+#[tokio::test]
+async fn test_dry_run_previews() {
+    use devman::memory::TaskStorage;
+    use devman::tools::dry_run;
+
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("notes.txt");
+    std::fs::write(&file, "one\ntwo\nthree\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    // File tools show a diff and leave the file alone
+    let write = serde_json::json!({ "path": path, "content": "one\n2\nthree\n" });
+    let preview = dry_run::preview("write_file", &write, None).await.unwrap();
+    assert!(preview.starts_with("Would overwrite") && preview.contains("-two\n+2"), "{preview}");
+    let edit = serde_json::json!({ "path": path, "old_text": "three", "new_text": "3" });
+    let preview = dry_run::preview("edit_file", &edit, None).await.unwrap();
+    assert!(preview.starts_with("Would edit") && preview.contains("-three\n+3"), "{preview}");
+    let patch = serde_json::json!({
+        "workdir": dir.path().to_str().unwrap(),
+        "patch": "--- a/notes.txt\n+++ b/notes.txt\n@@ -1,3 +1,3 @@\n-one\n+1\n two\n three\n",
+    });
+    let preview = dry_run::preview("apply_patch", &patch, None).await.unwrap();
+    assert!(preview.contains("M notes.txt") && preview.contains("-one\n+1"), "{preview}");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "one\ntwo\nthree\n");
+
+    // An edit that wouldn't apply fails the same way it would for real
+    let bad = serde_json::json!({ "path": path, "old_text": "four", "new_text": "4" });
+    let failed = dry_run::preview("edit_file", &bad, None).await;
+    assert!(failed.is_err());
+    assert!(dry_run::action("edit_file", &failed).starts_with("edit_file: would fail"));
+
+    // GitHub creation shows the request it would send
+    let pr = serde_json::json!({ "repo": "acme/app", "title": "Fix it", "head": "fix" });
+    let preview = dry_run::preview("github_pr_create", &pr, None).await.unwrap();
+    assert!(preview.starts_with("Would send POST https://api.github.com/repos/acme/app/pulls"), "{preview}");
+    assert!(preview.contains("\"base\": \"main\""));
+
+    let storage = TaskStorage::new(dir.path().join("storage"), false);
+    storage.write_file("plan.md", "v1\n", false).unwrap();
+    let preview = dry_run::preview("storage_write", &serde_json::json!({ "path": "plan.md", "content": "v2\n" }), Some(&storage))
+        .await
+        .unwrap();
+    assert!(preview.contains("-v1\n+v2"), "{preview}");
+    let preview = dry_run::preview("storage_delete", &serde_json::json!({ "path": "plan.md" }), Some(&storage)).await.unwrap();
+    assert_eq!(preview, "Would delete plan.md from storage (3 bytes)");
+    assert_eq!(storage.read_file("plan.md").unwrap(), "v1\n");
+
+    // Read tools — and shell, for looking around — run normally; changes and sends are held back
+    for runs in ["read_file", "git_diff", "storage_read", "shell", "process_output", "process_list"] {
+        assert!(!dry_run::intercepts(runs), "{runs}");
+    }
+    for held in ["write_file", "edit_file", "apply_patch", "git_commit", "git_push", "github_pr_create", "github_issue_create", "storage_write", "storage_delete", "assign_bot", "remove_bot", "send_file", "tts", "message_agent"] {
+        assert!(dry_run::intercepts(held), "{held}");
+    }
+    let message = serde_json::json!({ "to": "run-1", "message": "schema is done" });
+    assert_eq!(dry_run::preview("message_agent", &message, None).await.unwrap(), "Would message run-1: schema is done");
+
+    let actions = vec!["write_file: Would create a.txt (3 bytes)".to_string(), "git_commit: Would stage every change".to_string()];
+    assert_eq!(
+        dry_run::summary(&actions),
+        "Dry run — 2 action(s) not taken:\n1. write_file: Would create a.txt (3 bytes)\n2. git_commit: Would stage every change"
+    );
+    assert_eq!(dry_run::summary(&[]), "Dry run — no changes would have been made.");
+}